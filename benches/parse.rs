@@ -0,0 +1,68 @@
+use std::fs;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use mp3info::{
+    encoder::encode_tag,
+    parser::{decode_frames, Content, Frame, Header, PictureType, Tag},
+};
+
+/// A v2.3 tag with a handful of text frames and front cover art of `art_size` bytes
+fn tag_bytes(art_size: usize) -> Vec<u8> {
+    let text = |id: &str, text: &str| Frame::Other {
+        id: id.into(),
+        content: Content::Text(text.into()),
+    };
+    let tag = Tag {
+        header: Header {
+            version: 3,
+            revision: 0,
+            unsynchronisation: false,
+            extended: false,
+            experimental: false,
+            footer_present: false,
+            size: 0,
+        },
+        frames: vec![
+            text("TIT2", "Title"),
+            text("TPE1", "Artist"),
+            text("TALB", "Album"),
+            text("TRCK", "3/12"),
+            Frame::Apic {
+                data: (0..art_size).map(|x| x as u8).collect(),
+                mime_type: "image/jpeg".into(),
+                picture_type: PictureType::CoverFront,
+                description: "Cover".into(),
+            },
+        ],
+        warnings: Vec::new(),
+        offsets: Vec::new(),
+        sizes: Vec::new(),
+        encodings: Vec::new(),
+        timings: Vec::new(),
+        trailer: Vec::new(),
+    };
+    encode_tag(&tag)
+}
+
+fn parse(c: &mut Criterion) {
+    let tag = tag_bytes(5 * 1024 * 1024);
+    let frames = tag[10..].to_vec();
+
+    c.bench_function("decode 5MB art", |b| {
+        b.iter_batched(
+            || frames.clone(),
+            |frames| decode_frames(black_box(frames), false).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    let path = std::env::temp_dir().join(format!("mp3info-bench-{}.mp3", std::process::id()));
+    fs::write(&path, &tag).unwrap();
+    c.bench_function("read file with 5MB art", |b| {
+        b.iter(|| mp3info::read_file(black_box(&path)).unwrap())
+    });
+    let _ = fs::remove_file(&path);
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
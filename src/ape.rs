@@ -0,0 +1,176 @@
+use std::{
+    io::{self, Read, Seek},
+    ops::Range,
+};
+
+use crate::{
+    id3v1::id3v1_start,
+    parser::{Content, Frame},
+    read_at,
+};
+
+const PREAMBLE: &[u8] = b"APETAGEX";
+const FOOTER_SIZE: u64 = 32;
+
+/// APE item keys and the ID3v2 frames they stand for
+const FRAME_IDS: [(&str, &str); 6] = [
+    ("Title", "TIT2"),
+    ("Artist", "TPE1"),
+    ("Album", "TALB"),
+    ("Year", "TYER"),
+    ("Track", "TRCK"),
+    ("Genre", "TCON"),
+];
+
+/// Text items of an APEv1/APEv2 tag appended to the file, as written by foobar2000 and mp3gain
+#[derive(Debug, Default)]
+pub struct Ape {
+    pub version: u32,
+    pub items: Vec<(String, String)>,
+}
+
+impl Ape {
+    /// Value of an item, keys are compared case-insensitively as the spec asks
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.items
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The items with an ID3v2 counterpart, as text frames
+    pub fn frames(&self) -> Vec<Frame> {
+        FRAME_IDS
+            .iter()
+            .filter_map(|(key, id)| {
+                self.get(key).map(|value| Frame::Other {
+                    id: id.to_string(),
+                    content: Content::Text(value.into()),
+                })
+            })
+            .collect()
+    }
+}
+
+fn le_u32(buf: &[u8]) -> u32 {
+    u32::from_le_bytes(buf[..4].try_into().unwrap())
+}
+
+fn decode_items(mut rest: &[u8], count: u32) -> Option<Vec<(String, String)>> {
+    let mut items = Vec::new();
+
+    // each item is a value size, flags, a null terminated key and the value itself
+    for _ in 0..count {
+        let size = le_u32(rest.get(0..4)?) as usize;
+        let flags = le_u32(rest.get(4..8)?);
+        rest = &rest[8..];
+
+        let key_end = rest.iter().position(|&x| x == 0)?;
+        let key = String::from_utf8(rest[..key_end].to_vec()).ok()?;
+        let value = rest.get(key_end + 1..key_end + 1 + size)?;
+        rest = &rest[key_end + 1 + size..];
+
+        // bits 1-2 give the item type, only UTF-8 text (0) is of use
+        if flags >> 1 & 0b11 == 0 {
+            // multiple values are separated by nulls
+            items.push((key, String::from_utf8_lossy(value).replace('\0', "/")));
+        }
+    }
+
+    Some(items)
+}
+
+struct Footer {
+    version: u32,
+    /// Size of the items and the footer, but not the optional header
+    size: u64,
+    count: u32,
+    has_header: bool,
+}
+
+/// The footer of the APE tag ending at `end`, if it's believable
+fn read_footer(file: &mut (impl Read + Seek), end: u64) -> io::Result<Option<Footer>> {
+    if end < FOOTER_SIZE {
+        return Ok(None);
+    }
+
+    let footer = read_at(file, end - FOOTER_SIZE, FOOTER_SIZE as usize)?;
+    if &footer[..8] != PREAMBLE {
+        return Ok(None);
+    }
+    let size = le_u32(&footer[12..]) as u64;
+    if size < FOOTER_SIZE || size > end {
+        return Ok(None);
+    }
+
+    Ok(Some(Footer {
+        version: le_u32(&footer[8..]),
+        size,
+        count: le_u32(&footer[16..]),
+        // the top bit of the flags announces the header
+        has_header: footer[23] & 0x80 != 0,
+    }))
+}
+
+/// Where the APE tag ending at `end` lies, header included, if one does
+pub(crate) fn find_ape(file: &mut (impl Read + Seek), end: u64) -> io::Result<Option<Range<u64>>> {
+    Ok(read_footer(file, end)?.map(|footer| {
+        let header = if footer.has_header { FOOTER_SIZE } else { 0 };
+        (end - footer.size).saturating_sub(header)..end
+    }))
+}
+
+/// Look for an APE tag footer at the end of the file, either right before an ID3v1 tag or as
+/// the very last thing in the file. Malformed tags are treated as absent.
+pub fn read_ape(file: &mut (impl Read + Seek)) -> io::Result<Option<Ape>> {
+    let end = id3v1_start(file)?;
+    let Some(footer) = read_footer(file, end)? else {
+        return Ok(None);
+    };
+
+    let items = read_at(
+        file,
+        end - footer.size,
+        (footer.size - FOOTER_SIZE) as usize,
+    )?;
+    Ok(decode_items(&items, footer.count).map(|items| Ape {
+        version: footer.version,
+        items,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::testutil::{ape_trailer, text_frame};
+
+    #[test]
+    fn parse_ape_before_id3v1() {
+        let mut file = b"fake audio".to_vec();
+        file.extend(ape_trailer(&[
+            ("TITLE", "Title"),
+            ("Artist", "A\0B"),
+            ("REPLAYGAIN_TRACK_GAIN", "-6.5 dB"),
+        ]));
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, 0);
+        file.extend(id3v1);
+
+        let ape = super::read_ape(&mut Cursor::new(file)).unwrap().unwrap();
+
+        assert_eq!(ape.version, 2000);
+        assert_eq!(ape.items.len(), 3);
+        assert_eq!(ape.get("replaygain_track_gain"), Some("-6.5 dB"));
+        assert_eq!(
+            ape.frames(),
+            [text_frame("TIT2", "Title"), text_frame("TPE1", "A/B")]
+        );
+    }
+
+    #[test]
+    fn no_ape_tag() {
+        let file = b"fake audio with no trailer, long enough for a footer".to_vec();
+        assert!(super::read_ape(&mut Cursor::new(file)).unwrap().is_none());
+    }
+}
@@ -0,0 +1,136 @@
+//! Zip archives, of a tag's pictures or of MP3 files, built with the `zip` feature
+
+use std::{
+    error::Error,
+    fs,
+    io::{Seek, Write},
+    path::Path,
+};
+
+use zip::{result::ZipResult, write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    parser::{mime_extension, Frame, ParseOptions, Tag},
+    read_tag_streaming,
+};
+
+/// Read the tag of an MP3 file stored in a zip archive, streaming the entry rather than
+/// extracting it
+pub fn read_entry_tag(
+    archive: impl AsRef<Path>,
+    entry: &str,
+    options: &ParseOptions,
+) -> Result<Tag, Box<dyn Error>> {
+    let mut zip = ZipArchive::new(fs::File::open(archive)?)?;
+    let entry = zip.by_name(entry)?;
+    read_tag_streaming(entry, options)
+}
+
+/// Write every embedded picture of the tag into a zip archive, each named after its picture
+/// type, e.g. "cover-front.jpg". Images don't compress any further, so they're stored as is.
+/// Linked and empty pictures are left out. Returns the number of pictures written.
+pub fn write_pictures(tag: &Tag, out: impl Write + Seek) -> ZipResult<usize> {
+    let mut zip = ZipWriter::new(out);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut stems: Vec<String> = Vec::new();
+
+    for pic in tag.pictures() {
+        let Frame::Apic {
+            data,
+            mime_type,
+            picture_type,
+            ..
+        } = pic
+        else {
+            continue;
+        };
+        if pic.is_empty_picture() || pic.linked_picture_url().is_some() {
+            continue;
+        }
+
+        let stem = picture_type.name().to_string();
+        let ext = mime_extension(mime_type);
+        // several pictures may share a type, number the ones after the first
+        let same_type = stems.iter().filter(|x| **x == stem).count();
+        let name = match same_type {
+            0 => format!("{}.{}", stem, ext),
+            n => format!("{}-{}.{}", stem, n + 1, ext),
+        };
+
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(data)?;
+        stems.push(stem);
+    }
+
+    zip.finish()?;
+    Ok(stems.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use zip::ZipArchive;
+
+    use std::{fs, io::Write};
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use crate::{
+        parser::{Frame, PictureType},
+        testutil::{tag, tagged_file, temp_dir, text_frame},
+    };
+
+    #[test]
+    fn read_tag_inside_zip() {
+        let path = temp_dir("zip-entry").join("samples.zip");
+        let mut zip = ZipWriter::new(fs::File::create(&path).unwrap());
+        zip.start_file("readme.txt", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"samples").unwrap();
+        zip.start_file("drums/kick.mp3", SimpleFileOptions::default())
+            .unwrap();
+        let mut song = tagged_file("Kick");
+        song.extend([0xFF, 0xFB, 0x90, 0x00].repeat(100));
+        zip.write_all(&song).unwrap();
+        zip.finish().unwrap();
+
+        let tag = super::read_entry_tag(&path, "drums/kick.mp3", &Default::default()).unwrap();
+
+        assert_eq!(tag.title(), Some("Kick"));
+        assert!(super::read_entry_tag(&path, "missing.mp3", &Default::default()).is_err());
+    }
+
+    fn picture(picture_type: PictureType, mime_type: &str, data: &[u8]) -> Frame {
+        Frame::Apic {
+            data: data.to_vec(),
+            mime_type: mime_type.into(),
+            picture_type,
+            description: "".into(),
+        }
+    }
+
+    #[test]
+    fn archive_every_picture() {
+        let tag = tag(vec![
+            text_frame("TIT2", "Title"),
+            picture(
+                PictureType::CoverFront,
+                "image/jpeg",
+                b"\xFF\xD8front\xFF\xD9",
+            ),
+            picture(PictureType::CoverBack, "image/png", b"\x89PNGback"),
+            picture(PictureType::Artist, "-->", b"http://example.com/a.jpg"),
+        ]);
+
+        let mut out = Cursor::new(Vec::new());
+        assert_eq!(super::write_pictures(&tag, &mut out).unwrap(), 2);
+
+        let mut zip = ZipArchive::new(out).unwrap();
+        assert_eq!(zip.len(), 2);
+        let names: Vec<&str> = zip.file_names().collect();
+        assert!(names.contains(&"cover-front.jpg"));
+        assert!(names.contains(&"cover-back.png"));
+        assert_eq!(zip.by_name("cover-back.png").unwrap().size(), 8);
+    }
+}
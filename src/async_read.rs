@@ -0,0 +1,66 @@
+//! Reading tags without tying up a thread per file, built with the `async` feature
+
+use std::{io, path::Path};
+
+use tokio::{fs::File, io::AsyncReadExt};
+
+use crate::{
+    container,
+    parser::{ParseOptions, Tag},
+    read_tag_from, tag_bytes_after_header,
+};
+
+pub async fn read_tag_async(path: impl AsRef<Path>) -> io::Result<Tag> {
+    read_tag_async_with(path, &ParseOptions::default()).await
+}
+
+/// Read the tag header and body asynchronously, then decode the frames from memory. Only the
+/// tag is read, so tags appended at the end of the file (Lyrics3, APE) are left out, while
+/// WAV and AIFF files are read whole to find their ID3 chunk. Decoding errors are returned
+/// as `InvalidData`, which keeps the future `Send`.
+pub async fn read_tag_async_with(
+    path: impl AsRef<Path>,
+    options: &ParseOptions,
+) -> io::Result<Tag> {
+    let mut file = File::open(path).await?;
+
+    let mut buf = Vec::new();
+    (&mut file).take(10).read_to_end(&mut buf).await?;
+    if container::is_container(&buf) {
+        file.read_to_end(&mut buf).await?;
+    } else if buf.len() == 10 {
+        let size = tag_bytes_after_header(&buf);
+        file.take(size).read_to_end(&mut buf).await?;
+    }
+
+    read_tag_from(&mut io::Cursor::new(buf), options)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::testutil::{tagged_file, temp_dir};
+
+    #[tokio::test]
+    async fn read_tag_asynchronously() {
+        let path = temp_dir("async").join("song.mp3");
+        fs::write(&path, tagged_file("Title")).unwrap();
+
+        let tag = super::read_tag_async(&path).await.unwrap();
+
+        assert_eq!(tag.title(), Some("Title"));
+        assert_eq!(tag.offsets, [10]);
+    }
+
+    #[tokio::test]
+    async fn read_untagged_file_asynchronously() {
+        let path = temp_dir("async-untagged").join("song.mp3");
+        fs::write(&path, b"\xFF\xFB\x90\x00 no tag here").unwrap();
+
+        let e = super::read_tag_async(&path).await.unwrap_err();
+
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
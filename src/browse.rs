@@ -0,0 +1,237 @@
+//! Terminal UI for browsing the tags of a library, built with the `tui` feature
+
+use std::{error::Error, io, path::PathBuf};
+
+use mp3info::{
+    parser::{Frame, ParseOptions, Tag},
+    walk::{scan, Walk},
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListState, Paragraph, Wrap},
+    DefaultTerminal,
+};
+
+/// What the right hand pane shows of the selected file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    Frames,
+    Lyrics,
+    Pictures,
+}
+
+/// State of the browser: every file found and what's selected
+struct App {
+    files: Vec<(PathBuf, Result<Tag, String>)>,
+    list: ListState,
+    view: View,
+    quit: bool,
+}
+
+impl App {
+    fn new(files: Vec<(PathBuf, Result<Tag, String>)>) -> Self {
+        let mut list = ListState::default();
+        if !files.is_empty() {
+            list.select(Some(0));
+        }
+        App {
+            files,
+            list,
+            view: View::Frames,
+            quit: false,
+        }
+    }
+
+    fn selected(&self) -> Option<&(PathBuf, Result<Tag, String>)> {
+        self.files.get(self.list.selected()?)
+    }
+
+    fn handle_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => self.select(1),
+            KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+            KeyCode::PageDown => self.select(10),
+            KeyCode::PageUp => self.select(-10),
+            KeyCode::Char('f') => self.view = View::Frames,
+            KeyCode::Char('l') => self.view = View::Lyrics,
+            KeyCode::Char('p') => self.view = View::Pictures,
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            _ => {}
+        }
+    }
+
+    /// Move the selection by `step` files, stopping at either end of the list
+    fn select(&mut self, step: isize) {
+        if let Some(current) = self.list.selected() {
+            let last = self.files.len() - 1;
+            self.list
+                .select(Some(current.saturating_add_signed(step).min(last)));
+        }
+    }
+
+    /// Lines of the right hand pane for the selected file
+    fn details(&self) -> Vec<Line<'static>> {
+        let Some((_, tag)) = self.selected() else {
+            return vec![Line::from("No MP3 files found")];
+        };
+        let tag = match tag {
+            Ok(tag) => tag,
+            Err(e) => return vec![Line::from(format!("Error: {}", e))],
+        };
+
+        let lines: Vec<String> = match self.view {
+            View::Frames => tag
+                .frames
+                .iter()
+                .map(|x| format!("{}: {}", x.id(), x))
+                .collect(),
+            View::Lyrics => tag
+                .frames_by_id("USLT")
+                .flat_map(|x| x.to_string().lines().map(String::from).collect::<Vec<_>>())
+                .collect(),
+            View::Pictures => tag
+                .pictures()
+                .into_iter()
+                .filter_map(|x| match x {
+                    Frame::Apic {
+                        data,
+                        mime_type,
+                        picture_type,
+                        ..
+                    } => Some(format!(
+                        "{}: {}, {} bytes",
+                        picture_type.name(),
+                        mime_type,
+                        data.len()
+                    )),
+                    _ => None,
+                })
+                .collect(),
+        };
+
+        if lines.is_empty() {
+            return vec![Line::from("Nothing to show")];
+        }
+        lines.into_iter().map(Line::from).collect()
+    }
+
+    fn draw(&mut self, frame: &mut ratatui::Frame) {
+        let [files, details] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(frame.area());
+
+        let names: Vec<String> = self
+            .files
+            .iter()
+            .map(|(path, _)| path.display().to_string())
+            .collect();
+        let list = List::new(names)
+            .block(Block::bordered().title("Files"))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, files, &mut self.list);
+
+        let title = match self.view {
+            View::Frames => "Frames (f) | lyrics (l) | pictures (p) | quit (q)",
+            View::Lyrics => "Lyrics",
+            View::Pictures => "Pictures",
+        };
+        let details_pane = Paragraph::new(self.details())
+            .block(Block::bordered().title(title))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(details_pane, details);
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key.code);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Scan the given directories and browse their tags until the user quits
+pub fn browse(walks: Vec<Walk>, options: &ParseOptions) -> Result<(), Box<dyn Error>> {
+    let mut files = Vec::new();
+    for walk in walks {
+        scan(walk, options, None, |entry, tag| {
+            files.push((entry.to_path_buf(), tag.map_err(|e| e.to_string())));
+            Ok(())
+        })?;
+    }
+
+    let mut app = App::new(files);
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    Ok(result?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use mp3info::parser::{Content, Frame, Header, Tag};
+    use ratatui::crossterm::event::KeyCode;
+
+    use super::{App, View};
+
+    fn tag(title: &str) -> Tag {
+        Tag {
+            header: Header {
+                version: 3,
+                revision: 0,
+                unsynchronisation: false,
+                extended: false,
+                experimental: false,
+                footer_present: false,
+                size: 0,
+            },
+            frames: vec![Frame::Other {
+                id: "TIT2".into(),
+                content: Content::Text(title.into()),
+            }],
+            warnings: Vec::new(),
+            offsets: Vec::new(),
+            sizes: Vec::new(),
+            encodings: Vec::new(),
+            timings: Vec::new(),
+            trailer: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn navigation_updates_state() {
+        let mut app = App::new(vec![
+            (PathBuf::from("a.mp3"), Ok(tag("A"))),
+            (PathBuf::from("b.mp3"), Ok(tag("B"))),
+            (PathBuf::from("c.mp3"), Err("broken".into())),
+        ]);
+        assert_eq!(app.details()[0].to_string(), "TIT2: A");
+
+        app.handle_key(KeyCode::Down);
+        assert_eq!(app.list.selected(), Some(1));
+        assert_eq!(app.details()[0].to_string(), "TIT2: B");
+
+        app.handle_key(KeyCode::PageDown);
+        assert_eq!(app.list.selected(), Some(2));
+        assert_eq!(app.details()[0].to_string(), "Error: broken");
+
+        app.handle_key(KeyCode::Up);
+        app.handle_key(KeyCode::Char('l'));
+        assert_eq!(app.view, View::Lyrics);
+        assert_eq!(app.details()[0].to_string(), "Nothing to show");
+
+        app.handle_key(KeyCode::PageUp);
+        assert_eq!(app.list.selected(), Some(0));
+        app.handle_key(KeyCode::Char('q'));
+        assert!(app.quit);
+    }
+}
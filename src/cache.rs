@@ -0,0 +1,215 @@
+//! On-disk cache of the fields extracted from files, so rescanning a library only reads the
+//! files that changed since
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    parser::{ParseOptions, Tag},
+    read_file_with,
+    walk::{ScanSummary, Walk},
+};
+
+/// Fields extracted from a tag, as names and values
+pub type Fields = Vec<(String, String)>;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    modified: SystemTime,
+    size: u64,
+    fields: Fields,
+}
+
+/// Fields of files keyed by their path, valid as long as the file's modification time and
+/// size stay the same. Only the fields are kept, so the warnings of a file are only seen when
+/// it's read.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    /// The options the fields were extracted with, as the caller spells them out
+    #[serde(default)]
+    options: String,
+    entries: BTreeMap<PathBuf, Entry>,
+    /// How many lookups found fields still valid
+    #[serde(skip)]
+    pub hits: usize,
+    /// Files looked up since the cache was loaded
+    #[serde(skip)]
+    seen: BTreeSet<PathBuf>,
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+impl Cache {
+    /// Load the cache from a file, for fields extracted with `options`. A missing or
+    /// unreadable cache starts out empty, as it only ever saves work, and so does one filled
+    /// with other options.
+    pub fn load(path: impl AsRef<Path>, options: &str) -> io::Result<Self> {
+        let cache: Cache = match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Cache::default(),
+            Err(e) => return Err(e),
+        };
+        if cache.options == options {
+            return Ok(cache);
+        }
+        Ok(Cache {
+            options: options.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Drop the entries of the files no lookup asked for since loading, such as files that
+    /// were deleted or moved, so the cache doesn't keep growing
+    pub fn prune(&mut self) {
+        let seen = &self.seen;
+        self.entries.retain(|path, _| seen.contains(path));
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    /// The cached fields of the file, unless it was modified since they were stored
+    pub fn get(&mut self, path: &Path) -> Option<&Fields> {
+        self.seen.insert(path.to_path_buf());
+        let (modified, size) = stamp(path)?;
+        let entry = self
+            .entries
+            .get(path)
+            .filter(|x| x.modified == modified && x.size == size)?;
+        self.hits += 1;
+        Some(&entry.fields)
+    }
+
+    pub fn insert(&mut self, path: &Path, fields: Fields) {
+        if let Some((modified, size)) = stamp(path) {
+            let entry = Entry {
+                modified,
+                size,
+                fields,
+            };
+            self.entries.insert(path.to_path_buf(), entry);
+            self.seen.insert(path.to_path_buf());
+        }
+    }
+}
+
+/// Like [`crate::walk::scan`], but hands on the fields `extract` takes from each tag,
+/// looking them up in the cache first. Only files missing from the cache are read, and the
+/// fields of those that parse are added to it.
+pub fn scan_cached(
+    walk: Walk,
+    options: &ParseOptions,
+    cache: &mut Cache,
+    limit: Option<usize>,
+    extract: impl Fn(&Path, &Tag) -> Fields,
+    mut f: impl FnMut(&Path, Result<Fields, Box<dyn Error>>) -> io::Result<()>,
+) -> io::Result<ScanSummary> {
+    let mut summary = ScanSummary::default();
+
+    for entry in walk.take(limit.unwrap_or(usize::MAX)) {
+        // a directory that can't be listed is an error like a file that can't be read
+        let Ok(entry) = entry else {
+            summary.errors += 1;
+            continue;
+        };
+        let fields = match cache.get(&entry) {
+            Some(fields) => Ok(fields.clone()),
+            None => read_file_with(&entry, options).map(|tag| {
+                let fields = extract(&entry, &tag);
+                cache.insert(&entry, fields.clone());
+                fields
+            }),
+        };
+
+        summary.scanned += 1;
+        match fields {
+            Ok(_) => summary.parsed += 1,
+            Err(_) => summary.errors += 1,
+        }
+
+        f(&entry, fields)?;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, fs};
+
+    use crate::{
+        testutil::{tagged_file, temp_dir},
+        walk::Walk,
+    };
+
+    #[test]
+    fn second_scan_reads_from_cache() {
+        let dir = temp_dir("cache");
+        fs::write(dir.join("a.mp3"), tagged_file("A")).unwrap();
+        fs::write(dir.join("b.mp3"), tagged_file("B")).unwrap();
+        fs::write(dir.join("broken.mp3"), b"not a tag").unwrap();
+        let cache_file = dir.join("cache.json");
+
+        let reads = Cell::new(0);
+        let extract = |_: &_, tag: &crate::parser::Tag| {
+            reads.set(reads.get() + 1);
+            vec![("title".to_string(), tag.title().unwrap().to_string())]
+        };
+        let scan = |options| {
+            let mut cache = super::Cache::load(&cache_file, options).unwrap();
+            let mut titles = Vec::new();
+            let summary = super::scan_cached(
+                Walk::new(&dir),
+                &Default::default(),
+                &mut cache,
+                None,
+                extract,
+                |_, fields| {
+                    if let Ok(fields) = fields {
+                        titles.push(fields[0].1.clone());
+                    }
+                    Ok(())
+                },
+            )
+            .unwrap();
+            cache.prune();
+            cache.save(&cache_file).unwrap();
+            (summary.parsed, cache.hits, titles)
+        };
+
+        assert_eq!(scan("a"), (2, 0, vec!["A".into(), "B".into()]));
+        assert_eq!(reads.get(), 2);
+        assert_eq!(scan("a"), (2, 2, vec!["A".into(), "B".into()]));
+        assert_eq!(reads.get(), 2);
+
+        // a changed file is read again
+        fs::write(dir.join("b.mp3"), tagged_file("Bee")).unwrap();
+        assert_eq!(scan("a"), (2, 1, vec!["A".into(), "Bee".into()]));
+        assert_eq!(reads.get(), 3);
+
+        // and every file is when the options change
+        assert_eq!(scan("b"), (2, 0, vec!["A".into(), "Bee".into()]));
+        assert_eq!(reads.get(), 5);
+        assert_eq!(scan("b").1, 2);
+
+        // and a file that's gone is dropped
+        fs::remove_file(dir.join("b.mp3")).unwrap();
+        assert_eq!(scan("b"), (1, 1, vec!["A".into()]));
+        let cache = super::Cache::load(&cache_file, "b").unwrap();
+        assert_eq!(
+            cache.entries.keys().collect::<Vec<_>>(),
+            [&dir.join("a.mp3")]
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Chapters from CHAP frames, and CUE sheets splitting a long mix into its tracks
+
+use crate::parser::{decode_frames, decode_str, take_terminated, Content, Encoding, Frame, Tag};
+
+/// A chapter of the recording, with the title of its embedded TIT2 frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub element_id: String,
+    /// Start and end time in milliseconds
+    pub start: u32,
+    pub end: u32,
+    pub title: Option<String>,
+}
+
+/// Decode the body of a CHAP frame: a null terminated element id, start and end times and
+/// byte offsets of 32 bits each, followed by frames describing the chapter
+pub fn decode_chap(body: &[u8], v4: bool) -> Option<Chapter> {
+    let (element_id, rest) = take_terminated(body, Encoding::ISO_8859_1);
+    let element_id = decode_str(element_id, Encoding::ISO_8859_1).ok()?;
+    let start = u32::from_be_bytes(rest.get(0..4)?.try_into().unwrap());
+    let end = u32::from_be_bytes(rest.get(4..8)?.try_into().unwrap());
+
+    // the byte offsets are rarely set, the times are what players go by
+    let frames = decode_frames(rest.get(16..)?.to_vec(), v4).unwrap_or_default();
+    let title = frames.into_iter().find_map(|x| match x {
+        Frame::Other {
+            id,
+            content: Content::Text(text),
+        } if id == "TIT2" => Some(text),
+        _ => None,
+    });
+
+    Some(Chapter {
+        element_id,
+        start,
+        end,
+        title,
+    })
+}
+
+/// Every chapter of the tag that could be decoded, in order of their start times
+pub fn chapters(tag: &Tag) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = tag
+        .frames_by_id("CHAP")
+        .filter_map(|x| match x {
+            Frame::Other {
+                content: Content::Binary(b),
+                ..
+            } => decode_chap(b, tag.header.version == 4),
+            _ => None,
+        })
+        .collect();
+    chapters.sort_by_key(|x| x.start);
+    chapters
+}
+
+/// CUE sheets have no way to escape quotes, so they're swapped for single ones
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "'"))
+}
+
+/// Format milliseconds as MM:SS:FF, counting frames at 75 a second as CD audio does
+fn cue_time(ms: u32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        ms / 60_000,
+        ms / 1000 % 60,
+        ms % 1000 * 75 / 1000
+    )
+}
+
+/// A CUE sheet with a track for every chapter, for the file of the given name. The tag's
+/// artist and album head the sheet when it has them.
+pub fn cue_sheet(file_name: &str, tag: &Tag, chapters: &[Chapter]) -> String {
+    let mut cue = String::new();
+    if let Some(artist) = tag.artist() {
+        cue.push_str(&format!("PERFORMER {}\n", quote(artist)));
+    }
+    if let Some(album) = tag.album() {
+        cue.push_str(&format!("TITLE {}\n", quote(album)));
+    }
+    cue.push_str(&format!("FILE {} MP3\n", quote(file_name)));
+
+    for (i, chapter) in chapters.iter().enumerate() {
+        cue.push_str(&format!("  TRACK {:02} AUDIO\n", i + 1));
+        if let Some(title) = &chapter.title {
+            cue.push_str(&format!("    TITLE {}\n", quote(title)));
+        }
+        cue.push_str(&format!("    INDEX 01 {}\n", cue_time(chapter.start)));
+    }
+    cue
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parser::{decode_frames, Frame},
+        testutil::{raw_frame, raw_text_frame, tag, text_frame},
+    };
+
+    fn chap(element_id: &str, start: u32, end: u32, title: &str) -> Vec<u8> {
+        let mut body = element_id.as_bytes().to_vec();
+        body.push(0);
+        body.extend(start.to_be_bytes());
+        body.extend(end.to_be_bytes());
+        body.extend([0xFF; 8]); // no byte offsets
+        body.extend(raw_text_frame("TIT2", title));
+        raw_frame("CHAP", &body)
+    }
+
+    #[test]
+    fn cue_sheet_from_two_chapters() {
+        let mut buf = chap("ch1", 252_500, 600_000, "Second \"Song\"");
+        buf.extend(chap("ch0", 0, 252_500, "First Song"));
+        let mut frames: Vec<Frame> = decode_frames(buf, false).unwrap();
+        frames.push(text_frame("TPE1", "DJ"));
+        let tag = tag(frames);
+
+        let chapters = super::chapters(&tag);
+
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].element_id, "ch0");
+        assert_eq!(chapters[1].end, 600_000);
+        assert_eq!(
+            super::cue_sheet("mix.mp3", &tag, &chapters),
+            "PERFORMER \"DJ\"\n\
+             FILE \"mix.mp3\" MP3\n  \
+               TRACK 01 AUDIO\n    \
+                 TITLE \"First Song\"\n    \
+                 INDEX 01 00:00:00\n  \
+               TRACK 02 AUDIO\n    \
+                 TITLE \"Second 'Song'\"\n    \
+                 INDEX 01 04:12:37\n"
+        );
+    }
+}
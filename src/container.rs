@@ -0,0 +1,107 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Whether the first bytes of a file belong to a RIFF (WAV) or IFF (AIFF) container
+pub fn is_container(magic: &[u8]) -> bool {
+    magic.starts_with(b"RIFF") || magic.starts_with(b"FORM")
+}
+
+/// Fail on WAV and AIFF files, whose ID3 chunk can't be rewritten yet. A tag written in front
+/// of the container, or one appended after it, would leave a file no player can read.
+pub(crate) fn refuse_container(contents: &[u8]) -> io::Result<()> {
+    if is_container(contents) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Writing tags to WAV and AIFF files isn't supported",
+        ));
+    }
+    Ok(())
+}
+
+/// Find the offset of the ID3 chunk's data in a WAV or AIFF file. WAV files usually name
+/// the chunk "id3 " and AIFF files "ID3 ", but both spellings are seen in the wild.
+pub fn find_id3_chunk(file: &mut (impl Read + Seek)) -> io::Result<Option<u64>> {
+    let mut header = [0; 12];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+
+    // RIFF sizes are little endian, IFF sizes big endian
+    let little_endian = match (&header[0..4], &header[8..12]) {
+        (b"RIFF", b"WAVE") => true,
+        (b"FORM", b"AIFF" | b"AIFC") => false,
+        _ => return Ok(None),
+    };
+
+    let mut offset = 12;
+    let mut chunk = [0; 8];
+    loop {
+        file.seek(SeekFrom::Start(offset))?;
+        match file.read_exact(&mut chunk) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            x => x?,
+        }
+
+        let size = chunk[4..8].try_into().unwrap();
+        let size = if little_endian {
+            u32::from_le_bytes(size)
+        } else {
+            u32::from_be_bytes(size)
+        } as u64;
+
+        if chunk[0..4].eq_ignore_ascii_case(b"id3 ") {
+            return Ok(Some(offset + 8));
+        }
+
+        // chunks are padded to an even size
+        offset += 8 + size + size % 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::testutil::{tagged_file, temp_dir};
+
+    fn wav(tag: &[u8]) -> Vec<u8> {
+        let mut chunks = b"fmt ".to_vec();
+        chunks.extend(16u32.to_le_bytes());
+        chunks.extend([0; 16]);
+        chunks.extend(b"data");
+        chunks.extend(3u32.to_le_bytes());
+        chunks.extend([0; 4]); // 3 bytes of samples and a pad byte
+        chunks.extend(b"id3 ");
+        chunks.extend((tag.len() as u32).to_le_bytes());
+        chunks.extend(tag);
+
+        let mut wav = b"RIFF".to_vec();
+        wav.extend((chunks.len() as u32 + 4).to_le_bytes());
+        wav.extend(b"WAVE");
+        wav.extend(chunks);
+        wav
+    }
+
+    #[test]
+    fn read_tag_from_wav() {
+        let dir = temp_dir("wav");
+        let path = dir.join("song.wav");
+        fs::write(&path, wav(&tagged_file("In a WAV"))).unwrap();
+
+        let tag = crate::read_file(&path).unwrap();
+        assert_eq!(tag.title(), Some("In a WAV"));
+    }
+
+    #[test]
+    fn refuse_writing_to_wav() {
+        let dir = temp_dir("wav-write");
+        let path = dir.join("song.wav");
+        let wav = wav(&tagged_file("In a WAV"));
+        fs::write(&path, &wav).unwrap();
+        let tag = crate::read_file(&path).unwrap();
+
+        let e = crate::encoder::write_tag(&path, &tag).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::Unsupported);
+        let v1 = crate::id3v1::from_tag(&tag);
+        assert!(crate::id3v1::write_id3v1(&path, &v1).is_err());
+        assert_eq!(fs::read(&path).unwrap(), wav);
+    }
+}
@@ -0,0 +1,130 @@
+//! Frame by frame comparison of two tags
+
+use std::{
+    fmt,
+    hash::{DefaultHasher, Hasher},
+};
+
+use crate::parser::{Content, Frame, Tag};
+
+/// A difference between two tags
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<'a> {
+    Added(&'a Frame),
+    Removed(&'a Frame),
+    Changed { old: &'a Frame, new: &'a Frame },
+}
+
+/// Short description of a frame's value. Binary data is described by its length and hash,
+/// which is enough to tell whether it changed.
+fn describe(frame: &Frame) -> String {
+    let binary = |data: &[u8]| {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(data);
+        format!("{} bytes, hash {:016x}", data.len(), hasher.finish())
+    };
+
+    match frame {
+        Frame::Apic {
+            data,
+            mime_type,
+            picture_type,
+            ..
+        } => format!("{} {}, {}", picture_type.name(), mime_type, binary(data)),
+        Frame::Other {
+            content: Content::Binary(data),
+            ..
+        } => binary(data),
+        x => x.to_string(),
+    }
+}
+
+impl fmt::Display for Change<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added(x) => write!(f, "+ {}: {}", x.id(), describe(x)),
+            Change::Removed(x) => write!(f, "- {}: {}", x.id(), describe(x)),
+            Change::Changed { old, new } => {
+                write!(f, "~ {}: {} -> {}", old.id(), describe(old), describe(new))
+            }
+        }
+    }
+}
+
+/// Compare the frames of two tags, regardless of their order. Frames with the same id that
+/// are identical in both tags are left out; the others are paired up in the order they
+/// appear, and the ones left over were added or removed.
+pub fn diff_tags<'a>(old: &'a Tag, new: &'a Tag) -> Vec<Change<'a>> {
+    let mut ids: Vec<&str> = old
+        .frames
+        .iter()
+        .chain(&new.frames)
+        .map(|x| x.id())
+        .collect();
+    ids.sort();
+    ids.dedup();
+
+    let mut changes = Vec::new();
+    for id in ids {
+        let mut before: Vec<&Frame> = old.frames.iter().filter(|x| x.id() == id).collect();
+        let mut after: Vec<&Frame> = new.frames.iter().filter(|x| x.id() == id).collect();
+
+        // frames found unchanged in both don't count, wherever they are
+        before.retain(|x| match after.iter().position(|y| y == x) {
+            Some(i) => {
+                after.remove(i);
+                false
+            }
+            None => true,
+        });
+
+        for (old, new) in before.iter().zip(&after) {
+            changes.push(Change::Changed { old, new });
+        }
+        changes.extend(before.iter().skip(after.len()).map(|x| Change::Removed(x)));
+        changes.extend(after.iter().skip(before.len()).map(|x| Change::Added(x)));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parser::{Frame, PictureType},
+        testutil::{tag, text_frame},
+    };
+
+    fn picture(data: &[u8]) -> Frame {
+        Frame::Apic {
+            data: data.to_vec(),
+            mime_type: "image/jpeg".into(),
+            picture_type: PictureType::CoverFront,
+            description: "".into(),
+        }
+    }
+
+    #[test]
+    fn diff_title_and_picture() {
+        let old = tag(vec![
+            text_frame("TIT2", "Old title"),
+            text_frame("TPE1", "Artist"),
+            picture(b"old picture"),
+        ]);
+        let new = tag(vec![
+            picture(b"new, larger picture"),
+            text_frame("TPE1", "Artist"),
+            text_frame("TIT2", "New title"),
+            text_frame("TALB", "Album"),
+        ]);
+
+        let changes = super::diff_tags(&old, &new);
+        let lines: Vec<String> = changes.iter().map(|x| x.to_string()).collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("~ APIC: cover-front image/jpeg, 11 bytes, hash "));
+        assert!(lines[0].contains(" -> cover-front image/jpeg, 19 bytes, hash "));
+        assert_eq!(lines[1], "+ TALB: Album");
+        assert_eq!(lines[2], "~ TIT2: Old title -> New title");
+        assert!(super::diff_tags(&old, &old).is_empty());
+    }
+}
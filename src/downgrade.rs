@@ -0,0 +1,124 @@
+use crate::parser::{Content, Frame, Tag, Warning};
+
+/// Frames new in v2.4 that have no v2.3 counterpart
+const V24_ONLY: [&str; 16] = [
+    "ASPI", "EQU2", "RVA2", "SEEK", "SIGN", "TDEN", "TDRL", "TDTG", "TIPL", "TMCL", "TMOO", "TPRO",
+    "TSOA", "TSOP", "TSOT", "TSST",
+];
+
+/// Split a v2.4 timestamp, "yyyy-MM-ddTHH:mm:ss" or any prefix of it, into the v2.3 year
+/// (yyyy), date (DDMM) and time (HHMM) frames
+fn split_timestamp(timestamp: &str) -> Option<(String, Option<String>, Option<String>)> {
+    let year = timestamp.get(0..4)?;
+    let part = |range: std::ops::Range<usize>| timestamp.get(range);
+
+    let date = part(5..7)
+        .zip(part(8..10))
+        .map(|(month, day)| day.to_owned() + month);
+    let time = part(11..13)
+        .zip(part(14..16))
+        .map(|(hour, min)| hour.to_owned() + min);
+    Some((year.into(), date, time))
+}
+
+/// Turn a tag into one v2.3 readers understand: recording and release times are split into
+/// the v2.3 year, date and time frames, multiple values are joined with a slash and frames
+/// v2.3 has no equivalent for are dropped. Returns a warning for every dropped frame.
+pub fn downgrade_to_v23(tag: &mut Tag) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let mut converted = Vec::with_capacity(tag.frames.len());
+
+    for frame in tag.frames.drain(..) {
+        match frame {
+            Frame::Other { id, content } if id == "TDRC" || id == "TDOR" => {
+                let timestamp = match &content {
+                    Content::Text(text) => Some(text.as_str()),
+                    Content::TextList(values) => values.first().map(|x| x.as_str()),
+                    Content::Binary(_) => None,
+                };
+                let Some((year, date, time)) = timestamp.and_then(split_timestamp) else {
+                    warnings.push(Warning::new(Some(&id), "unreadable timestamp dropped"));
+                    continue;
+                };
+
+                let text = |id: &str, text: String| Frame::Other {
+                    id: id.into(),
+                    content: Content::Text(text),
+                };
+                // v2.3 only has a year for the original release
+                if id == "TDOR" {
+                    converted.push(text("TORY", year));
+                    continue;
+                }
+                converted.push(text("TYER", year));
+                converted.extend(date.map(|x| text("TDAT", x)));
+                converted.extend(time.map(|x| text("TIME", x)));
+            }
+            Frame::Other {
+                id,
+                content: Content::TextList(values),
+            } => converted.push(Frame::Other {
+                id,
+                content: Content::Text(values.join("/")),
+            }),
+            x if V24_ONLY.contains(&x.id()) => {
+                warnings.push(Warning::new(Some(x.id()), "no v2.3 equivalent, dropped"));
+            }
+            x => converted.push(x),
+        }
+    }
+
+    tag.frames = converted;
+    tag.header.version = 3;
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encoder::encode_tag,
+        parser::{decode_frames, Content, Frame},
+        testutil::{tag, text_frame},
+    };
+
+    #[test]
+    fn v24_to_v23_round_trip() {
+        let mut tag = tag(vec![
+            text_frame("TIT2", "Title"),
+            Frame::Other {
+                id: "TPE1".into(),
+                content: Content::TextList(vec!["A".into(), "B".into()]),
+            },
+            text_frame("TDRC", "2023-05-14T12:30"),
+            text_frame("TMOO", "Calm"),
+        ]);
+        tag.header.version = 4;
+
+        let warnings = super::downgrade_to_v23(&mut tag);
+        let encoded = encode_tag(&tag);
+        let frames = decode_frames(encoded[10..].to_vec(), false).unwrap();
+
+        assert_eq!(encoded[3], 3);
+        assert_eq!(
+            frames,
+            [
+                text_frame("TIT2", "Title"),
+                text_frame("TPE1", "A/B"),
+                text_frame("TYER", "2023"),
+                text_frame("TDAT", "1405"),
+                text_frame("TIME", "1230"),
+            ]
+        );
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].to_string(), "TMOO: no v2.3 equivalent, dropped");
+    }
+
+    #[test]
+    fn year_only_timestamp() {
+        assert_eq!(
+            super::split_timestamp("1999"),
+            Some(("1999".into(), None, None))
+        );
+        assert_eq!(super::split_timestamp("99"), None);
+    }
+}
@@ -0,0 +1,191 @@
+//! Finding files that hold the same audio, however differently they're tagged
+
+use std::{
+    collections::HashMap,
+    fs,
+    hash::{DefaultHasher, Hasher},
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::{ape, appended_tag_ending_at, audio_offset, id3v1, lyrics3};
+
+/// Length and hash of the audio stream of a file, leaving out every tag. Files are only
+/// told apart by it, their bytes are compared before calling them the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AudioFingerprint {
+    pub length: u64,
+    pub hash: u64,
+}
+
+/// Offset where the audio ends: before an ID3v1 tag, and the ID3v2, APE and Lyrics3 tags
+/// appended before it, in whatever order
+fn audio_end(file: &mut (impl Read + Seek), start: u64) -> io::Result<u64> {
+    let mut end = id3v1::id3v1_start(file)?.max(start);
+
+    loop {
+        let extent = match appended_tag_ending_at(file, end)? {
+            Some(extent) => Some(extent),
+            None => match ape::find_ape(file, end)? {
+                Some(extent) => Some(extent),
+                None => lyrics3::find_lyrics3(file, end)?,
+            },
+        };
+        match extent {
+            Some(extent) if start < end && extent.start < end => end = extent.start.max(start),
+            _ => return Ok(end),
+        }
+    }
+}
+
+/// The audio of a file, from its first MPEG frame to the tags at its end
+fn open_audio(path: impl AsRef<Path>) -> io::Result<io::Take<BufReader<fs::File>>> {
+    let start = audio_offset(&path)?;
+    let mut file = fs::File::open(&path)?;
+    let end = audio_end(&mut file, start)?;
+    file.seek(SeekFrom::Start(start))?;
+    Ok(BufReader::new(file).take(end - start))
+}
+
+/// Hash the audio of a file
+pub fn audio_fingerprint(path: impl AsRef<Path>) -> io::Result<AudioFingerprint> {
+    let mut audio = open_audio(path)?;
+    let length = audio.limit();
+    let mut hasher = DefaultHasher::new();
+    let mut chunk = [0; 64 * 1024];
+    loop {
+        match audio.read(&mut chunk)? {
+            0 => break,
+            n => hasher.write(&chunk[..n]),
+        }
+    }
+
+    Ok(AudioFingerprint {
+        length,
+        hash: hasher.finish(),
+    })
+}
+
+/// Whether two files hold the same audio, byte for byte
+fn same_audio(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut a, mut b) = (open_audio(a)?, open_audio(b)?);
+    if a.limit() != b.limit() {
+        return Ok(false);
+    }
+    let (mut x, mut y) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    loop {
+        let n = a.read(&mut x)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut y[..n])?;
+        if x[..n] != y[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Group the files holding identical audio. Groups are sorted, as are the files in them.
+/// Files whose audio can't be found are returned with the error, and left out of the groups.
+pub fn find_duplicates(
+    files: impl IntoIterator<Item = PathBuf>,
+) -> (Vec<Vec<PathBuf>>, Vec<(PathBuf, io::Error)>) {
+    let mut seen: HashMap<AudioFingerprint, Vec<PathBuf>> = HashMap::new();
+    let mut errors = Vec::new();
+
+    for file in files {
+        match audio_fingerprint(&file) {
+            Ok(fingerprint) => seen.entry(fingerprint).or_default().push(file),
+            Err(e) => errors.push((file, e)),
+        }
+    }
+
+    let mut groups: Vec<Vec<PathBuf>> = Vec::new();
+    for candidates in seen.into_values().filter(|x| x.len() > 1) {
+        // a shared hash only makes files candidates, comparing their audio settles it
+        let mut confirmed: Vec<Vec<PathBuf>> = Vec::new();
+        'files: for file in candidates {
+            for group in &mut confirmed {
+                match same_audio(&group[0], &file) {
+                    Ok(true) => {
+                        group.push(file);
+                        continue 'files;
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        errors.push((file, e));
+                        continue 'files;
+                    }
+                }
+            }
+            confirmed.push(vec![file]);
+        }
+        groups.extend(confirmed.into_iter().filter(|x| x.len() > 1));
+    }
+    for group in &mut groups {
+        group.sort();
+    }
+    groups.sort();
+    (groups, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::testutil::{ape_trailer, lyrics3_block, raw_tag, raw_text_frame, temp_dir};
+
+    fn audio(fill: u8) -> Vec<u8> {
+        let mut frame = vec![0xFF, 0xFB, 0x90, 0x00];
+        frame.resize(417, fill);
+        frame.repeat(3)
+    }
+
+    #[test]
+    fn group_retagged_copies() {
+        let dir = temp_dir("dupes");
+        let files = ["a.mp3", "b.mp3", "c.mp3", "d.mp3", "e.mp3"].map(|x| dir.join(x));
+
+        let mut a = raw_tag(&[raw_text_frame("TIT2", "Song")]);
+        a.extend(audio(1));
+        let mut b = raw_tag(&[
+            raw_text_frame("TIT2", "Song (copy)"),
+            raw_text_frame("TPE1", "Artist"),
+        ]);
+        b.extend(audio(1));
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, b' ');
+        b.extend(id3v1);
+        let mut c = raw_tag(&[raw_text_frame("TIT2", "Song")]);
+        c.extend(audio(2));
+
+        fs::write(&files[0], a).unwrap();
+        fs::write(&files[1], b).unwrap();
+        fs::write(&files[2], c).unwrap();
+        fs::write(&files[3], b"no audio").unwrap();
+        // tagged at the end only, by a streamer and a couple of other taggers
+        let mut e = audio(1);
+        let mut appended = raw_tag(&[raw_text_frame("TIT2", "Song")]);
+        appended[3] = 4;
+        appended[5] = 0x10; // footer present
+        let mut footer = appended[..10].to_vec();
+        footer[..3].copy_from_slice(b"3DI");
+        appended.extend(footer);
+        e.extend(lyrics3_block(&[("ETT", "Song")]));
+        e.extend(appended);
+        e.extend(ape_trailer(&[("Title", "Song")]));
+        fs::write(&files[4], e).unwrap();
+
+        let (groups, errors) = super::find_duplicates(files.clone());
+
+        assert_eq!(
+            groups,
+            [vec![files[0].clone(), files[1].clone(), files[4].clone()]]
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, files[3]);
+
+        assert!(super::same_audio(&files[0], &files[4]).unwrap());
+        assert!(!super::same_audio(&files[0], &files[2]).unwrap());
+    }
+}
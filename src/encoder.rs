@@ -0,0 +1,662 @@
+use std::{fmt, fs, io, ops::Range, path::Path};
+
+use crate::{
+    container::refuse_container,
+    find_appended_tag, leading_tag_extent,
+    parser::{
+        byte_int, byte_int_unsynch, decode_header, extended_header_size, has_encoding_byte,
+        looks_like_frame, Content, Encoding, Frame, FrameKind, Tag,
+    },
+    replaygain,
+    sylt::TimestampFormat,
+};
+
+/// Pick the most compact encoding able to represent all the strings of a frame
+fn choose_encoding(strings: &[&str], v4: bool) -> Encoding {
+    if strings
+        .iter()
+        .all(|x| x.chars().all(|c| (c as u32) <= 0xFF))
+    {
+        Encoding::ISO_8859_1
+    } else if v4 {
+        Encoding::UTF_8
+    } else {
+        Encoding::UTF_16
+    }
+}
+
+fn encode_str(text: &str, encoding: Encoding) -> Vec<u8> {
+    match encoding {
+        Encoding::ISO_8859_1 => text.chars().map(|c| c as u8).collect(),
+        Encoding::UTF_8 => text.as_bytes().to_vec(),
+        Encoding::UTF_16 => [0xFF, 0xFE]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(|x| x.to_le_bytes()))
+            .collect(),
+        Encoding::UTF_16BE => text.encode_utf16().flat_map(|x| x.to_be_bytes()).collect(),
+    }
+}
+
+fn terminator(encoding: Encoding) -> &'static [u8] {
+    match encoding {
+        Encoding::ISO_8859_1 | Encoding::UTF_8 => &[0x0],
+        Encoding::UTF_16 | Encoding::UTF_16BE => &[0x0, 0x0],
+    }
+}
+
+/// Encode a size field, which is synchsafe in v2.4 frames and in every tag header
+pub(crate) fn encode_size(size: u32, synchsafe: bool) -> [u8; 4] {
+    if synchsafe {
+        [
+            (size >> 21) as u8 & 0x7F,
+            (size >> 14) as u8 & 0x7F,
+            (size >> 7) as u8 & 0x7F,
+            size as u8 & 0x7F,
+        ]
+    } else {
+        size.to_be_bytes()
+    }
+}
+
+/// Encode the body (everything after the 10 byte frame header) of a frame
+pub fn encode_frame_body(frame: &Frame, v4: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+
+    match frame {
+        Frame::Uslt {
+            text,
+            language,
+            description,
+        } => {
+            let description = description.trim_end_matches('\0');
+            let encoding = choose_encoding(&[text, description], v4);
+            body.push(encoding as u8);
+            body.extend(format!("{:3.3}", language).bytes());
+            body.extend(encode_str(description, encoding));
+            body.extend(terminator(encoding));
+            body.extend(encode_str(text, encoding));
+        }
+        Frame::Comm {
+            text,
+            language,
+            description,
+        } => {
+            let encoding = choose_encoding(&[text, description], v4);
+            body.push(encoding as u8);
+            body.extend(format!("{:3.3}", language).bytes());
+            body.extend(encode_str(description, encoding));
+            body.extend(terminator(encoding));
+            body.extend(encode_str(text, encoding));
+        }
+        Frame::Apic {
+            data,
+            mime_type,
+            picture_type,
+            description,
+        } => {
+            let description = description.trim_end_matches('\0');
+            let encoding = choose_encoding(&[description], v4);
+            body.push(encoding as u8);
+            body.extend(mime_type.bytes());
+            body.push(0x0);
+            body.push(*picture_type as u8);
+            body.extend(encode_str(description, encoding));
+            body.extend(terminator(encoding));
+            body.extend(data);
+        }
+        Frame::Owne {
+            price,
+            date,
+            seller,
+        } => {
+            let encoding = choose_encoding(&[seller], v4);
+            body.push(encoding as u8);
+            body.extend(encode_str(price, Encoding::ISO_8859_1));
+            body.push(0x0);
+            body.extend(format!("{:8.8}", date).bytes());
+            body.extend(encode_str(seller, encoding));
+        }
+        Frame::Comr {
+            price,
+            valid_until,
+            contact_url,
+            received_as,
+            seller,
+            description,
+            logo_mime_type,
+            logo,
+        } => {
+            let encoding = choose_encoding(&[seller, description], v4);
+            body.push(encoding as u8);
+            body.extend(encode_str(price, Encoding::ISO_8859_1));
+            body.push(0x0);
+            body.extend(format!("{:8.8}", valid_until).bytes());
+            body.extend(encode_str(contact_url, Encoding::ISO_8859_1));
+            body.push(0x0);
+            body.push(*received_as);
+            body.extend(encode_str(seller, encoding));
+            body.extend(terminator(encoding));
+            body.extend(encode_str(description, encoding));
+            body.extend(terminator(encoding));
+            // the logo and its mime type are optional, and go together
+            if !logo.is_empty() {
+                body.extend(encode_str(logo_mime_type, Encoding::ISO_8859_1));
+                body.push(0x0);
+                body.extend(logo);
+            }
+        }
+        Frame::Etco {
+            timestamp_format,
+            events,
+        } => {
+            body.push(match timestamp_format {
+                TimestampFormat::MpegFrames => 1,
+                TimestampFormat::Milliseconds => 2,
+            });
+            for (event, timestamp) in events {
+                body.push(*event);
+                body.extend(timestamp.to_be_bytes());
+            }
+        }
+        Frame::Rgad {
+            peak,
+            radio,
+            audiophile,
+        } => {
+            body.extend(peak.to_be_bytes());
+            body.extend(replaygain::encode_rgad_adjustment(radio).to_be_bytes());
+            body.extend(replaygain::encode_rgad_adjustment(audiophile).to_be_bytes());
+        }
+        Frame::Encr {
+            owner,
+            method,
+            data,
+        } => {
+            body.extend(encode_str(owner, Encoding::ISO_8859_1));
+            body.push(0x0);
+            body.push(*method);
+            body.extend(data);
+        }
+        Frame::Poss {
+            timestamp_format,
+            position,
+        } => {
+            body.push(match timestamp_format {
+                TimestampFormat::MpegFrames => 1,
+                TimestampFormat::Milliseconds => 2,
+            });
+            // the position takes as many bytes as it needs, at least four
+            let bytes = position.to_be_bytes();
+            let skip = (position.leading_zeros() / 8).min(4) as usize;
+            body.extend(&bytes[skip..]);
+        }
+        Frame::Seek { offset } => body.extend(offset.to_be_bytes()),
+        Frame::Aspi {
+            data_start,
+            data_length,
+            bits_per_point,
+            points,
+        } => {
+            body.extend(data_start.to_be_bytes());
+            body.extend(data_length.to_be_bytes());
+            body.extend((points.len() as u16).to_be_bytes());
+            body.push(*bits_per_point);
+            for point in points {
+                match bits_per_point {
+                    8 => body.push(*point as u8),
+                    _ => body.extend(point.to_be_bytes()),
+                }
+            }
+        }
+        Frame::Mllt {
+            frames_between_reference,
+            bytes_between_reference,
+            ms_between_reference,
+            bits_for_bytes_deviation,
+            bits_for_ms_deviation,
+            deviations,
+        } => {
+            body.extend(frames_between_reference.to_be_bytes());
+            body.extend(&bytes_between_reference.to_be_bytes()[1..]);
+            body.extend(&ms_between_reference.to_be_bytes()[1..]);
+            body.extend([*bits_for_bytes_deviation, *bits_for_ms_deviation]);
+
+            // deviations are packed back to back, the last byte padded with zero bits
+            let mut bits = Vec::new();
+            for (bytes, ms) in deviations {
+                for (value, count) in [
+                    (bytes, bits_for_bytes_deviation),
+                    (ms, bits_for_ms_deviation),
+                ] {
+                    bits.extend((0..*count).rev().map(|x| value >> x & 1 == 1));
+                }
+            }
+            body.extend(bits.chunks(8).map(|x| {
+                (0..8).fold(0u8, |byte, i| byte << 1 | *x.get(i).unwrap_or(&false) as u8)
+            }));
+        }
+        Frame::Encrypted {
+            method,
+            group,
+            compressed,
+            data_length,
+            data,
+            ..
+        } => {
+            let (_, extra) = encrypted_format(*method, *group, *compressed, *data_length, v4);
+            body.extend(extra);
+            body.extend(data);
+        }
+        Frame::Other { id, content } => match content {
+            // a comment set as plain text, without a language or description
+            Content::Text(text) if id == "COMM" => {
+                let encoding = choose_encoding(&[text], v4);
+                body.push(encoding as u8);
+                body.extend(b"XXX");
+                body.extend(terminator(encoding));
+                body.extend(encode_str(text, encoding));
+            }
+            Content::Text(text) if !has_encoding_byte(id) => {
+                body.extend(encode_str(text, Encoding::ISO_8859_1));
+            }
+            Content::Text(text) => {
+                let encoding = choose_encoding(&[text], v4);
+                body.push(encoding as u8);
+                body.extend(encode_str(text, encoding));
+            }
+            Content::TextList(values) => {
+                let strings: Vec<&str> = values.iter().map(|x| x.as_str()).collect();
+                let encoding = choose_encoding(&strings, v4);
+                body.push(encoding as u8);
+                if v4 {
+                    for (i, value) in values.iter().enumerate() {
+                        if i > 0 {
+                            body.extend(terminator(encoding));
+                        }
+                        body.extend(encode_str(value, encoding));
+                    }
+                } else {
+                    // v2.3 has no multiple values, the convention is to separate them with a slash
+                    body.extend(encode_str(&values.join("/"), encoding));
+                }
+            }
+            Content::Binary(data) => body.extend(data),
+        },
+    }
+
+    body
+}
+
+/// Position of a kind of frame in the canonical order
+fn canonical_rank(kind: FrameKind) -> u8 {
+    match kind {
+        FrameKind::Identifier => 0,
+        FrameKind::Text => 1,
+        FrameKind::Url => 2,
+        FrameKind::Comment => 3,
+        FrameKind::Lyrics => 4,
+        FrameKind::Commercial => 5,
+        FrameKind::Timing | FrameKind::Binary => 6,
+        FrameKind::Picture => 7,
+    }
+}
+
+/// Reorder frames into the order the ID3 spec recommends: identifiers and text frames
+/// first, large binary frames such as pictures last. Frames of the same kind keep their
+/// relative order.
+pub fn reorder_frames(frames: &mut [Frame]) {
+    frames.sort_by_key(|x| canonical_rank(x.kind()));
+}
+
+/// Format flags of an encrypted frame, and the bytes they add to the start of its body, laid
+/// out the way the version has them
+fn encrypted_format(
+    method: u8,
+    group: Option<u8>,
+    compressed: bool,
+    data_length: Option<u32>,
+    v4: bool,
+) -> (u8, Vec<u8>) {
+    let mut extra = Vec::new();
+    if v4 {
+        let mut flags = 0x04;
+        if let Some(group) = group {
+            flags |= 0x40;
+            extra.push(group);
+        }
+        extra.push(method);
+        if compressed {
+            flags |= 0x08;
+        }
+        if let Some(length) = data_length {
+            flags |= 0x01;
+            extra.extend(encode_size(length, true));
+        }
+        (flags, extra)
+    } else {
+        let mut flags = 0x40;
+        // v2.3 has no way to leave out the size of compressed data
+        if compressed {
+            flags |= 0x80;
+            extra.extend(data_length.unwrap_or_default().to_be_bytes());
+        }
+        extra.push(method);
+        if let Some(group) = group {
+            flags |= 0x20;
+            extra.push(group);
+        }
+        (flags, extra)
+    }
+}
+
+/// Encode a complete frame, including its header
+pub fn encode_frame(frame: &Frame, v4: bool) -> Vec<u8> {
+    let body = encode_frame_body(frame, v4);
+    // encrypted frames keep their format flags, everything else is written without any
+    let format_flags = match frame {
+        Frame::Encrypted {
+            method,
+            group,
+            compressed,
+            data_length,
+            ..
+        } => encrypted_format(*method, *group, *compressed, *data_length, v4).0,
+        _ => 0x0,
+    };
+
+    let mut out = Vec::with_capacity(body.len() + 10);
+    out.extend(format!("{:4.4}", frame.id()).bytes());
+    out.extend(encode_size(body.len() as u32, v4));
+    out.extend([0x0, format_flags]);
+    out.extend(body);
+    out
+}
+
+/// Encode a tag with its frames. Only v2.3 and v2.4 can be written, anything else is
+/// written as v2.3. The tag is written without padding, extended header or footer.
+pub fn encode_tag(tag: &Tag) -> Vec<u8> {
+    let version = match tag.header.version {
+        4 => 4,
+        _ => 3,
+    };
+
+    let frames: Vec<u8> = tag
+        .frames
+        .iter()
+        .flat_map(|x| encode_frame(x, version == 4))
+        .collect();
+
+    let mut out = Vec::with_capacity(frames.len() + 10);
+    out.extend(b"ID3");
+    out.extend([version, 0x0, 0x0]);
+    out.extend(encode_size(frames.len() as u32, true));
+    out.extend(frames);
+    out
+}
+
+/// Number of bytes taken up by the ID3v2 tag at the start of a file's contents, 0 if the
+/// file doesn't start with one
+pub fn leading_tag_size(buf: &[u8]) -> usize {
+    leading_tag_extent(&mut io::Cursor::new(buf)).unwrap_or_default() as usize
+}
+
+/// The size the tag at the start of a file's contents should declare: the frames, followed
+/// for as long as they look like frames whatever the header says, and the zero padding after
+/// them. `None` if the contents don't start with a tag.
+pub fn actual_tag_size(contents: &[u8]) -> Option<u32> {
+    let header = decode_header(contents.get(0..10)?.try_into().unwrap()).ok()?;
+
+    let mut pos = 10;
+    if header.extended {
+        // a bogus size can only be skipped past the size field itself
+        let size_field = contents.get(10..14)?;
+        pos += extended_header_size(size_field, header.version).unwrap_or(4);
+    }
+    while contents.get(pos..).is_some_and(looks_like_frame) {
+        let size_field = &contents[pos + 4..pos + 8];
+        let size = match header.version {
+            4 => byte_int_unsynch(size_field),
+            _ => byte_int(size_field),
+        };
+        pos += 10 + size as usize;
+    }
+    let end = pos.min(contents.len());
+    let padding = contents[end..].iter().take_while(|&&x| x == 0).count();
+
+    Some((end + padding - 10) as u32)
+}
+
+/// Set the size field of the tag at the start of a file's contents to the size it should
+/// declare. Returns the old and new size, which are the same when there was nothing to fix.
+pub fn fix_tag_size(contents: &mut [u8]) -> Option<(u32, u32)> {
+    let old = decode_header(contents.get(0..10)?.try_into().unwrap())
+        .ok()?
+        .size;
+    let new = actual_tag_size(contents)?;
+    contents[6..10].copy_from_slice(&encode_size(new, true));
+    Some((old, new))
+}
+
+/// Replace the tag at the start of a file's contents (or add one if it has none). A file
+/// without one may have its tag appended after the audio instead, which is moved to the
+/// start rather than left behind.
+pub fn rewrite(contents: &[u8], tag: &Tag) -> io::Result<Vec<u8>> {
+    refuse_container(contents)?;
+    let audio = &contents[leading_tag_size(contents)..];
+    let appended = match audio.len() == contents.len() {
+        true => find_appended_tag(&mut io::Cursor::new(contents))
+            .ok()
+            .flatten(),
+        false => None,
+    };
+
+    let mut out = encode_tag(tag);
+    match appended {
+        Some(Range { start, end }) => {
+            out.extend(&audio[..start as usize]);
+            out.extend(&audio[end as usize..]);
+        }
+        None => out.extend(audio),
+    }
+    Ok(out)
+}
+
+/// Replace the tag of a file (or add one if it has none), keeping its audio intact
+pub fn write_tag(path: impl AsRef<Path>, tag: &Tag) -> io::Result<()> {
+    let contents = fs::read(&path)?;
+    fs::write(path, rewrite(&contents, tag)?)
+}
+
+/// What writing a tag changes compared to the tag already in the file
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct WriteSummary {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Frames whose content changed, with their old and new encoded sizes
+    pub changed: Vec<(String, usize, usize)>,
+    pub old_size: u64,
+    pub new_size: u64,
+}
+
+/// Compare the frames of two tags. Frames are matched up by id and by their position among
+/// the frames with the same id.
+pub fn summarize(old: Option<&Tag>, new: &Tag) -> WriteSummary {
+    let v4 = new.header.version == 4;
+    let old_frames = old.map(|x| x.frames.as_slice()).unwrap_or_default();
+    let mut summary = WriteSummary {
+        old_size: old.map(|x| x.total_size()).unwrap_or_default(),
+        new_size: encode_tag(new).len() as u64,
+        ..Default::default()
+    };
+
+    let mut ids: Vec<&str> = old_frames
+        .iter()
+        .chain(&new.frames)
+        .map(|x| x.id())
+        .collect();
+    ids.sort();
+    ids.dedup();
+
+    for id in ids {
+        let before: Vec<&Frame> = old_frames.iter().filter(|x| x.id() == id).collect();
+        let after: Vec<&Frame> = new.frames.iter().filter(|x| x.id() == id).collect();
+
+        for (a, b) in before.iter().zip(&after) {
+            if a != b {
+                let (a, b) = (encode_frame(a, v4), encode_frame(b, v4));
+                summary.changed.push((id.into(), a.len(), b.len()));
+            }
+        }
+        for _ in after.len()..before.len() {
+            summary.removed.push(id.into());
+        }
+        for _ in before.len()..after.len() {
+            summary.added.push(id.into());
+        }
+    }
+
+    summary
+}
+
+impl fmt::Display for WriteSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for id in &self.added {
+            writeln!(f, "+ {}", id)?;
+        }
+        for id in &self.removed {
+            writeln!(f, "- {}", id)?;
+        }
+        for (id, old, new) in &self.changed {
+            writeln!(f, "~ {} ({} -> {} bytes)", id, old, new)?;
+        }
+        write!(f, "Tag size: {} -> {} bytes", self.old_size, self.new_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        parser::{Frame, PictureType},
+        read_file,
+        testutil::{tag, temp_dir, text_frame},
+    };
+
+    #[test]
+    fn copy_tags_round_trip() {
+        let dir = temp_dir("copy-tags");
+        let dest = dir.join("dest.mp3");
+        fs::write(&dest, b"\xFF\xFBaudio").unwrap();
+
+        let source = tag(vec![
+            text_frame("TIT2", "Caf\u{e9}"),
+            text_frame("TPE1", "\u{5b89}\u{5ba4}\u{5948}\u{7f8e}\u{6075}"),
+            Frame::Uslt {
+                text: "la la la".into(),
+                language: "eng".into(),
+                description: "".into(),
+            },
+            Frame::Apic {
+                data: vec![0xFF, 0xD8, 0x0, 0x0, 0xFF, 0xD9],
+                mime_type: "image/jpeg".into(),
+                picture_type: PictureType::CoverFront,
+                description: "".into(),
+            },
+        ]);
+        super::write_tag(&dest, &source).unwrap();
+
+        let copied = read_file(&dest).unwrap();
+        assert_eq!(copied.header.version, 3);
+        assert_eq!(copied.frames.len(), source.frames.len());
+        for (copied, source) in copied.frames.iter().zip(&source.frames) {
+            match (copied, source) {
+                (Frame::Apic { data: a, .. }, Frame::Apic { data: b, .. }) => assert_eq!(a, b),
+                (a, b) => assert_eq!(a, b),
+            }
+        }
+        assert!(fs::read(&dest).unwrap().ends_with(b"\xFF\xFBaudio"));
+    }
+
+    #[test]
+    fn fix_wrong_tag_size() {
+        use crate::testutil::{raw_tag, raw_text_frame};
+
+        let mut contents = raw_tag(&[
+            raw_text_frame("TIT2", "Title"),
+            raw_text_frame("TPE1", "Artist"),
+            vec![0; 20],
+        ]);
+        let size = contents.len() as u32 - 10;
+        contents.extend(b"\xFF\xFB\x90\x64audio");
+
+        for wrong in [[0, 0, 0, 12], [0, 0, 0x7F, 0x7F]] {
+            let mut contents = contents.clone();
+            contents[6..10].copy_from_slice(&wrong);
+
+            let (old, new) = super::fix_tag_size(&mut contents).unwrap();
+
+            assert_ne!(old, size);
+            assert_eq!(new, size);
+            assert_eq!(super::leading_tag_size(&contents), size as usize + 10);
+        }
+
+        let mut right = contents.clone();
+        assert_eq!(super::fix_tag_size(&mut right), Some((size, size)));
+        assert_eq!(right, contents);
+
+        // a v2.3 extended header's size leaves out its own size field
+        let mut extended = contents[..10].to_vec();
+        extended[5] = 0x40;
+        extended[6..10].copy_from_slice(&super::encode_size(size + 10, true));
+        extended.extend([0, 0, 0, 6, 0, 0, 0, 0, 0, 0]);
+        extended.extend(&contents[10..]);
+        let mut fixed = extended.clone();
+        assert_eq!(
+            super::fix_tag_size(&mut fixed),
+            Some((size + 10, size + 10))
+        );
+        assert_eq!(fixed, extended);
+    }
+
+    #[test]
+    fn encrypted_frames_keep_their_flags() {
+        let frame = Frame::Encrypted {
+            id: "TIT2".into(),
+            method: 0x81,
+            group: Some(7),
+            compressed: true,
+            data_length: Some(300),
+            data: b"\x03\xA7\xF1".to_vec(),
+        };
+
+        for v4 in [false, true] {
+            let encoded = super::encode_frame(&frame, v4);
+            assert_ne!(encoded[9], 0);
+            assert_eq!(
+                crate::parser::decode_frames(encoded, v4).unwrap(),
+                std::slice::from_ref(&frame)
+            );
+        }
+    }
+
+    #[test]
+    fn reorder_pictures_last() {
+        let mut frames = vec![
+            Frame::Apic {
+                data: vec![1, 2, 3],
+                mime_type: "image/png".into(),
+                picture_type: PictureType::CoverFront,
+                description: "".into(),
+            },
+            text_frame("TIT2", "Title"),
+            text_frame("COMM", "Comment"),
+            text_frame("TPE1", "Artist"),
+        ];
+
+        super::reorder_frames(&mut frames);
+
+        let ids: Vec<&str> = frames.iter().map(|x| x.id()).collect();
+        assert_eq!(ids, ["TIT2", "TPE1", "COMM", "APIC"]);
+    }
+}
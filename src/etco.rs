@@ -0,0 +1,99 @@
+use crate::{parser::read_counter, sylt::TimestampFormat};
+
+/// Name of an ETCO event type
+pub fn event_name(event: u8) -> &'static str {
+    match event {
+        0x00 => "Padding",
+        0x01 => "End of initial silence",
+        0x02 => "Intro",
+        0x03 => "Main part",
+        0x04 => "Outro",
+        0x05 => "Outro end",
+        0x06 => "Verse",
+        0x07 => "Refrain",
+        0x08 => "Interlude",
+        0x09 => "Theme",
+        0x0A => "Variation",
+        0x0B => "Key change",
+        0x0C => "Time change",
+        0x0D => "Momentary unwanted noise",
+        0x0E => "Sustained noise",
+        0x0F => "Sustained noise end",
+        0x10 => "Intro end",
+        0x11 => "Main part end",
+        0x12 => "Verse end",
+        0x13 => "Refrain end",
+        0x14 => "Theme end",
+        0x15 => "Profanity",
+        0x16 => "Profanity end",
+        0xE0..=0xEF => "Sync",
+        0xFD => "Audio end",
+        0xFE => "Audio file end",
+        _ => "Reserved",
+    }
+}
+
+/// Format a timestamp as mm:ss, or as a frame number when it counts MPEG frames
+pub fn format_timestamp(timestamp: impl Into<u64>, format: TimestampFormat) -> String {
+    let timestamp = timestamp.into();
+    match format {
+        TimestampFormat::Milliseconds => {
+            format!("{:02}:{:02}", timestamp / 60_000, timestamp / 1000 % 60)
+        }
+        TimestampFormat::MpegFrames => format!("frame {}", timestamp),
+    }
+}
+
+/// Decode the body of an ETCO frame: the timestamp format followed by event type and 32 bit
+/// timestamp pairs
+pub fn decode_etco(body: &[u8]) -> Option<(TimestampFormat, Vec<(u8, u32)>)> {
+    let timestamp_format = match body.first()? {
+        1 => TimestampFormat::MpegFrames,
+        2 => TimestampFormat::Milliseconds,
+        _ => return None,
+    };
+
+    let events = body[1..]
+        .chunks_exact(5)
+        .map(|x| (x[0], u32::from_be_bytes(x[1..5].try_into().unwrap())))
+        .collect();
+
+    Some((timestamp_format, events))
+}
+
+/// Decode the body of a POSS frame: the timestamp format followed by the position, a big
+/// endian number of any length
+pub fn decode_poss(body: &[u8]) -> Option<(TimestampFormat, u64)> {
+    let timestamp_format = match body.first()? {
+        1 => TimestampFormat::MpegFrames,
+        2 => TimestampFormat::Milliseconds,
+        _ => return None,
+    };
+
+    Some((timestamp_format, read_counter(&body[1..])))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parser::Frame, sylt::TimestampFormat, testutil::raw_frame};
+
+    #[test]
+    fn decode_two_events() {
+        let mut body = vec![0x02];
+        body.push(0x02);
+        body.extend(5_000u32.to_be_bytes());
+        body.push(0x06);
+        body.extend(83_000u32.to_be_bytes());
+
+        let frames = crate::parser::decode_frames(raw_frame("ETCO", &body), false).unwrap();
+
+        assert_eq!(
+            frames[0],
+            Frame::Etco {
+                timestamp_format: TimestampFormat::Milliseconds,
+                events: vec![(0x02, 5_000), (0x06, 83_000)],
+            }
+        );
+        assert_eq!(frames[0].to_string(), "Intro @ 00:05; Verse @ 01:23");
+    }
+}
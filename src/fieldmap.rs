@@ -0,0 +1,107 @@
+use std::{error::Error, fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::AppError;
+
+/// Which frame feeds each of the fields shown by `info`. Collections tagged by some players
+/// keep the artist in TPE2 or the year in TDRC, this lets them be read the way they were meant.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FieldMap {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub year: String,
+    pub band: String,
+}
+
+impl Default for FieldMap {
+    fn default() -> Self {
+        FieldMap {
+            title: "TIT2".into(),
+            artist: "TPE1".into(),
+            album: "TALB".into(),
+            year: "TYER".into(),
+            band: "TPE2".into(),
+        }
+    }
+}
+
+impl FieldMap {
+    /// Read a map from a TOML file of `field = "FRAME"` lines, fields it leaves out keep
+    /// their default frame
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let map: FieldMap = toml::from_str(&fs::read_to_string(path)?)?;
+        map.validate()?;
+        Ok(map)
+    }
+
+    /// Apply a `field=FRAME` override
+    pub fn set(&mut self, mapping: &str) -> Result<(), Box<dyn Error>> {
+        let (field, id) = mapping
+            .split_once('=')
+            .ok_or_else(|| AppError::new("Field mappings are written as field=FRAME"))?;
+        let id = id.trim().to_string();
+        match field.trim() {
+            "title" => self.title = id,
+            "artist" => self.artist = id,
+            "album" => self.album = id,
+            "year" => self.year = id,
+            "band" => self.band = id,
+            x => return Err(AppError::new(&format!("Unknown field '{}'", x))),
+        }
+        self.validate()
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        for id in [
+            &self.title,
+            &self.artist,
+            &self.album,
+            &self.year,
+            &self.band,
+        ] {
+            if id.len() != 4
+                || !id
+                    .bytes()
+                    .all(|x| x.is_ascii_uppercase() || x.is_ascii_digit())
+            {
+                return Err(AppError::new(&format!("'{}' isn't a frame ID", id)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::FieldMap;
+    use crate::testutil::temp_dir;
+
+    #[test]
+    fn map_from_toml() {
+        let path = temp_dir("field-map").join("map.toml");
+        fs::write(&path, "artist = \"TPE2\"\nyear = \"TDRC\"\n").unwrap();
+
+        let map = FieldMap::from_file(&path).unwrap();
+
+        assert_eq!(map.artist, "TPE2");
+        assert_eq!(map.year, "TDRC");
+        assert_eq!(map.title, "TIT2");
+    }
+
+    #[test]
+    fn map_overrides() {
+        let mut map = FieldMap::default();
+
+        map.set("artist=TPE2").unwrap();
+
+        assert_eq!(map.artist, "TPE2");
+        assert!(map.set("artist").is_err());
+        assert!(map.set("genre=TCON").is_err());
+        assert!(map.set("title=tit2").is_err());
+    }
+}
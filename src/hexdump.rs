@@ -0,0 +1,91 @@
+//! Classic hex dumps: offset, 16 bytes in hex and the same bytes as ASCII on every line
+
+use std::fmt::Write;
+
+const GREY: &str = "\x1b[90m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Hex dump of the bytes, with offsets counted from `base_offset`
+pub fn hexdump(bytes: &[u8], base_offset: u64) -> String {
+    dump(bytes, base_offset, false)
+}
+
+/// Hex dump with null bytes greyed out, printable ASCII in green and everything else in
+/// yellow, for terminals
+pub fn hexdump_colored(bytes: &[u8], base_offset: u64) -> String {
+    dump(bytes, base_offset, true)
+}
+
+/// The bytes in hex on a single line, spaced as in a dump, for labelling a few of them
+pub fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn dump(bytes: &[u8], base_offset: u64, color: bool) -> String {
+    let paint = |byte: u8, text: &str| {
+        if !color {
+            return text.to_string();
+        }
+        let color = match byte {
+            0 => GREY,
+            0x20..=0x7E => GREEN,
+            _ => YELLOW,
+        };
+        format!("{}{}{}", color, text, RESET)
+    };
+
+    let mut out = String::new();
+    for (i, line) in bytes.chunks(16).enumerate() {
+        write!(out, "{:08x}  ", base_offset + i as u64 * 16).unwrap();
+
+        for j in 0..16 {
+            match line.get(j) {
+                Some(&x) => out.push_str(&paint(x, &format!("{:02x}", x))),
+                None => out.push_str("  "),
+            }
+            // an extra space splits the bytes into two groups of eight
+            out.push_str(if j == 7 { "  " } else { " " });
+        }
+
+        out.push_str(" |");
+        for &x in line {
+            let ascii = if x.is_ascii_graphic() || x == b' ' {
+                x as char
+            } else {
+                '.'
+            };
+            out.push_str(&paint(x, &ascii.to_string()));
+        }
+        out.push_str("|\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn dump_format() {
+        let bytes = b"ID3\x04\x00\x00\x00\x00\x00\x0aTIT2\x00\x00\x00\x06\x00\x00";
+
+        assert_eq!(
+            super::hexdump(bytes, 0x20),
+            "00000020  49 44 33 04 00 00 00 00  00 0a 54 49 54 32 00 00  |ID3.......TIT2..|\n\
+             00000030  00 06 00 00                                       |....|\n"
+        );
+        assert_eq!(super::hex_bytes(&bytes[..4]), "49 44 33 04");
+    }
+
+    #[test]
+    fn dump_colored() {
+        let dump = super::hexdump_colored(b"A\0", 0);
+
+        assert!(dump.starts_with("00000000  \x1b[32m41\x1b[0m \x1b[90m00\x1b[0m "));
+        assert!(dump.ends_with("|\x1b[32mA\x1b[0m\x1b[90m.\x1b[0m|\n"));
+    }
+}
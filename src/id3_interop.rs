@@ -0,0 +1,247 @@
+//! Conversions to and from the tags of the `id3` crate, built with the `id3-interop` feature
+
+use std::error::Error;
+
+use id3::{
+    frame::{self as id3_frame, Comment, Lyrics, Picture, Unknown},
+    TagLike, Version,
+};
+
+use crate::{
+    encoder::{encode_frame_body, encode_size},
+    parser::{decode_frames, Content, Frame, Header, PictureType, Tag},
+    AppError,
+};
+
+/// The picture types of the `id3` crate, in the order of their numbers
+const PICTURE_TYPES: [id3_frame::PictureType; 21] = [
+    id3_frame::PictureType::Other,
+    id3_frame::PictureType::Icon,
+    id3_frame::PictureType::OtherIcon,
+    id3_frame::PictureType::CoverFront,
+    id3_frame::PictureType::CoverBack,
+    id3_frame::PictureType::Leaflet,
+    id3_frame::PictureType::Media,
+    id3_frame::PictureType::LeadArtist,
+    id3_frame::PictureType::Artist,
+    id3_frame::PictureType::Conductor,
+    id3_frame::PictureType::Band,
+    id3_frame::PictureType::Composer,
+    id3_frame::PictureType::Lyricist,
+    id3_frame::PictureType::RecordingLocation,
+    id3_frame::PictureType::DuringRecording,
+    id3_frame::PictureType::DuringPerformance,
+    id3_frame::PictureType::ScreenCapture,
+    id3_frame::PictureType::BrightFish,
+    id3_frame::PictureType::Illustration,
+    id3_frame::PictureType::BandLogo,
+    id3_frame::PictureType::PublisherLogo,
+];
+
+/// Text and URL frames that hold nothing but their value, unlike TXXX and WXXX
+fn is_plain_text(id: &str) -> bool {
+    (id.starts_with('T') || id.starts_with('W')) && id != "TXXX" && id != "WXXX"
+}
+
+fn to_id3_frame(frame: &Frame, version: Version) -> id3::Frame {
+    let content = match frame {
+        Frame::Other {
+            id,
+            content: Content::Text(text),
+        } if is_plain_text(id) => match id.starts_with('W') {
+            true => id3::Content::Link(text.clone()),
+            false => id3::Content::Text(text.clone()),
+        },
+        Frame::Other {
+            id,
+            content: Content::TextList(values),
+        } if is_plain_text(id) => id3::Content::new_text_values(values),
+        Frame::Comm {
+            text,
+            language,
+            description,
+        } => id3::Content::Comment(Comment {
+            lang: language.clone(),
+            description: description.clone(),
+            text: text.clone(),
+        }),
+        Frame::Uslt {
+            text,
+            language,
+            description,
+        } => id3::Content::Lyrics(Lyrics {
+            lang: language.clone(),
+            description: description.clone(),
+            text: text.clone(),
+        }),
+        Frame::Apic {
+            data,
+            mime_type,
+            picture_type,
+            description,
+        } => id3::Content::Picture(Picture {
+            mime_type: mime_type.clone(),
+            picture_type: PICTURE_TYPES[*picture_type as usize],
+            description: description.clone(),
+            data: data.clone(),
+        }),
+        // the rest is handed over encoded, for the `id3` crate to make what it can of
+        x => id3::Content::Unknown(Unknown {
+            data: encode_frame_body(x, version == Version::Id3v24),
+            version,
+        }),
+    };
+    id3::Frame::with_content(frame.id(), content)
+}
+
+impl From<&Tag> for id3::Tag {
+    /// Text, URL, comment, lyrics and picture frames are converted to their `id3`
+    /// counterparts, every other frame is passed on as its encoded body
+    fn from(tag: &Tag) -> Self {
+        let version = match tag.header.version {
+            2 => Version::Id3v22,
+            4 => Version::Id3v24,
+            _ => Version::Id3v23,
+        };
+
+        let mut out = id3::Tag::with_version(version);
+        for frame in &tag.frames {
+            out.add_frame(to_id3_frame(frame, version));
+        }
+        out
+    }
+}
+
+impl From<Tag> for id3::Tag {
+    fn from(tag: Tag) -> Self {
+        id3::Tag::from(&tag)
+    }
+}
+
+fn from_id3_frame(frame: &id3::Frame) -> Result<Frame, Box<dyn Error>> {
+    let id = frame.id().to_string();
+    let frame = match frame.content() {
+        id3::Content::Text(text) | id3::Content::Link(text) if is_plain_text(&id) => {
+            // the `id3` crate separates multiple values with nulls
+            let content = match text.contains('\0') {
+                true => Content::TextList(text.split('\0').map(String::from).collect()),
+                false => Content::Text(text.clone()),
+            };
+            Frame::Other { id, content }
+        }
+        id3::Content::Comment(x) => Frame::Comm {
+            text: x.text.clone(),
+            language: x.lang.clone(),
+            description: x.description.clone(),
+        },
+        id3::Content::Lyrics(x) => Frame::Uslt {
+            text: x.text.clone(),
+            language: x.lang.clone(),
+            description: x.description.clone(),
+        },
+        id3::Content::Picture(x) => {
+            let picture_type = u8::from(x.picture_type);
+            Frame::Apic {
+                data: x.data.clone(),
+                mime_type: x.mime_type.clone(),
+                picture_type: PictureType::try_from(picture_type).map_err(|_| {
+                    AppError::new(&format!("Unknown picture type {}", picture_type))
+                })?,
+                description: x.description.clone(),
+            }
+        }
+        // anything else is encoded by the `id3` crate, as v2.4, and decoded again here
+        content => {
+            let body = &content.to_unknown()?.data;
+            let mut raw = id.as_bytes().to_vec();
+            raw.extend(encode_size(body.len() as u32, true));
+            raw.extend([0, 0]);
+            raw.extend(body);
+            decode_frames(raw, true)?
+                .pop()
+                .ok_or_else(|| AppError::new(&format!("{} frame couldn't be decoded", id)))?
+        }
+    };
+    Ok(frame)
+}
+
+impl TryFrom<&id3::Tag> for Tag {
+    type Error = Box<dyn Error>;
+
+    /// Fails on frames this crate can't decode, and on pictures of undefined types
+    fn try_from(tag: &id3::Tag) -> Result<Self, Self::Error> {
+        let version = match tag.version() {
+            Version::Id3v22 => 2,
+            Version::Id3v23 => 3,
+            Version::Id3v24 => 4,
+        };
+
+        Ok(Tag {
+            header: Header {
+                version,
+                revision: 0,
+                unsynchronisation: false,
+                extended: false,
+                experimental: false,
+                footer_present: false,
+                size: 0,
+            },
+            frames: tag.frames().map(from_id3_frame).collect::<Result<_, _>>()?,
+            warnings: Vec::new(),
+            offsets: Vec::new(),
+            sizes: Vec::new(),
+            encodings: Vec::new(),
+            timings: Vec::new(),
+            trailer: Vec::new(),
+        })
+    }
+}
+
+impl TryFrom<id3::Tag> for Tag {
+    type Error = Box<dyn Error>;
+
+    fn try_from(tag: id3::Tag) -> Result<Self, Self::Error> {
+        Tag::try_from(&tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use id3::TagLike;
+
+    use crate::{
+        parser::{Frame, PictureType, Tag},
+        testutil::{tag, text_frame},
+    };
+
+    #[test]
+    fn round_trip_through_id3_crate() {
+        let frames = vec![
+            text_frame("TIT2", "Title"),
+            text_frame("WOAR", "https://example.com"),
+            Frame::Comm {
+                text: "Comment".into(),
+                language: "eng".into(),
+                description: "".into(),
+            },
+            Frame::Apic {
+                data: vec![0xFF, 0xD8, 0xFF, 0xD9],
+                mime_type: "image/jpeg".into(),
+                picture_type: PictureType::CoverBack,
+                description: "Back".into(),
+            },
+            Frame::Other {
+                id: "PCNT".into(),
+                content: crate::parser::Content::Binary(vec![0, 0, 1, 0]),
+            },
+        ];
+
+        let converted = id3::Tag::from(&tag(frames.clone()));
+        assert_eq!(converted.title(), Some("Title"));
+        assert_eq!(converted.pictures().next().unwrap().description, "Back");
+
+        let back = Tag::try_from(converted).unwrap();
+        assert_eq!(back.header.version, 3);
+        assert_eq!(back.frames, frames);
+    }
+}
@@ -0,0 +1,403 @@
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::parser::{decode_str, Encoding, Tag};
+
+pub const ID3V1_SIZE: usize = 128;
+
+/// Genre names indexed by their ID3v1 code, including the Winamp extensions
+pub const GENRES: [&str; 148] = [
+    "Blues",
+    "Classic Rock",
+    "Country",
+    "Dance",
+    "Disco",
+    "Funk",
+    "Grunge",
+    "Hip-Hop",
+    "Jazz",
+    "Metal",
+    "New Age",
+    "Oldies",
+    "Other",
+    "Pop",
+    "R&B",
+    "Rap",
+    "Reggae",
+    "Rock",
+    "Techno",
+    "Industrial",
+    "Alternative",
+    "Ska",
+    "Death Metal",
+    "Pranks",
+    "Soundtrack",
+    "Euro-Techno",
+    "Ambient",
+    "Trip-Hop",
+    "Vocal",
+    "Jazz+Funk",
+    "Fusion",
+    "Trance",
+    "Classical",
+    "Instrumental",
+    "Acid",
+    "House",
+    "Game",
+    "Sound Clip",
+    "Gospel",
+    "Noise",
+    "AlternRock",
+    "Bass",
+    "Soul",
+    "Punk",
+    "Space",
+    "Meditative",
+    "Instrumental Pop",
+    "Instrumental Rock",
+    "Ethnic",
+    "Gothic",
+    "Darkwave",
+    "Techno-Industrial",
+    "Electronic",
+    "Pop-Folk",
+    "Eurodance",
+    "Dream",
+    "Southern Rock",
+    "Comedy",
+    "Cult",
+    "Gangsta",
+    "Top 40",
+    "Christian Rap",
+    "Pop/Funk",
+    "Jungle",
+    "Native American",
+    "Cabaret",
+    "New Wave",
+    "Psychadelic",
+    "Rave",
+    "Showtunes",
+    "Trailer",
+    "Lo-Fi",
+    "Tribal",
+    "Acid Punk",
+    "Acid Jazz",
+    "Polka",
+    "Retro",
+    "Musical",
+    "Rock & Roll",
+    "Hard Rock",
+    "Folk",
+    "Folk-Rock",
+    "National Folk",
+    "Swing",
+    "Fast Fusion",
+    "Bebob",
+    "Latin",
+    "Revival",
+    "Celtic",
+    "Bluegrass",
+    "Avantgarde",
+    "Gothic Rock",
+    "Progressive Rock",
+    "Psychedelic Rock",
+    "Symphonic Rock",
+    "Slow Rock",
+    "Big Band",
+    "Chorus",
+    "Easy Listening",
+    "Acoustic",
+    "Humour",
+    "Speech",
+    "Chanson",
+    "Opera",
+    "Chamber Music",
+    "Sonata",
+    "Symphony",
+    "Booty Bass",
+    "Primus",
+    "Porn Groove",
+    "Satire",
+    "Slow Jam",
+    "Club",
+    "Tango",
+    "Samba",
+    "Folklore",
+    "Ballad",
+    "Power Ballad",
+    "Rhythmic Soul",
+    "Freestyle",
+    "Duet",
+    "Punk Rock",
+    "Drum Solo",
+    "A capella",
+    "Euro-House",
+    "Dance Hall",
+    "Goa",
+    "Drum & Bass",
+    "Club-House",
+    "Hardcore",
+    "Terror",
+    "Indie",
+    "BritPop",
+    "Negerpunk",
+    "Polsk Punk",
+    "Beat",
+    "Christian Gangsta Rap",
+    "Heavy Metal",
+    "Black Metal",
+    "Crossover",
+    "Contemporary Christian",
+    "Christian Rock",
+    "Merengue",
+    "Salsa",
+    "Thrash Metal",
+    "Anime",
+    "JPop",
+    "Synthpop",
+];
+
+/// The fixed size tag found in the last 128 bytes of a file
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Id3v1 {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub year: String,
+    pub comment: String,
+    /// Only present in ID3v1.1 tags
+    pub track: Option<u8>,
+    /// 255 means no genre
+    pub genre: u8,
+}
+
+/// ID3v1 code of a genre name, case insensitively
+pub fn genre_code(name: &str) -> Option<u8> {
+    GENRES
+        .iter()
+        .position(|x| x.eq_ignore_ascii_case(name))
+        .map(|x| x as u8)
+}
+
+/// Resolve a TCON value, which can be a name, a bare code ("17") or a code reference
+/// optionally followed by a refinement ("(17)" or "(17)Rock")
+pub fn parse_genre(tcon: &str) -> Option<u8> {
+    let tcon = tcon.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    if let Ok(code) = tcon.parse() {
+        return Some(code);
+    }
+    if let Some((code, _)) = tcon.strip_prefix('(').and_then(|x| x.split_once(')')) {
+        if let Ok(code) = code.parse() {
+            return Some(code);
+        }
+    }
+    genre_code(tcon)
+}
+
+fn decode_field(buf: &[u8]) -> String {
+    let end = buf.iter().position(|&x| x == 0x0).unwrap_or(buf.len());
+    decode_str(&buf[..end], Encoding::ISO_8859_1)
+        .unwrap_or_default()
+        .trim_end()
+        .to_string()
+}
+
+fn encode_field(buf: &mut [u8], text: &str) {
+    let text = text.trim_end_matches('\0');
+    for (b, c) in buf.iter_mut().zip(text.chars()) {
+        *b = if (c as u32) <= 0xFF { c as u8 } else { b'?' };
+    }
+}
+
+pub fn decode(buf: &[u8; ID3V1_SIZE]) -> Option<Id3v1> {
+    if &buf[0..3] != b"TAG" {
+        return None;
+    }
+
+    // v1.1 steals the last two comment bytes for a zero byte and the track number
+    let (comment, track) = match (buf[125], buf[126]) {
+        (0, track) if track != 0 => (&buf[97..125], Some(track)),
+        _ => (&buf[97..127], None),
+    };
+
+    Some(Id3v1 {
+        title: decode_field(&buf[3..33]),
+        artist: decode_field(&buf[33..63]),
+        album: decode_field(&buf[63..93]),
+        year: decode_field(&buf[93..97]),
+        comment: decode_field(comment),
+        track,
+        genre: buf[127],
+    })
+}
+
+pub fn encode(tag: &Id3v1) -> [u8; ID3V1_SIZE] {
+    let mut buf = [0; ID3V1_SIZE];
+    buf[0..3].copy_from_slice(b"TAG");
+    encode_field(&mut buf[3..33], &tag.title);
+    encode_field(&mut buf[33..63], &tag.artist);
+    encode_field(&mut buf[63..93], &tag.album);
+    encode_field(&mut buf[93..97], &tag.year);
+    match tag.track {
+        Some(track) => {
+            encode_field(&mut buf[97..125], &tag.comment);
+            buf[126] = track;
+        }
+        None => encode_field(&mut buf[97..127], &tag.comment),
+    }
+    buf[127] = tag.genre;
+    buf
+}
+
+/// Derive an ID3v1 tag from the fields of an ID3v2 tag
+pub fn from_tag(tag: &Tag) -> Id3v1 {
+    let text = |id| tag.text(id).unwrap_or_default().to_string();
+
+    Id3v1 {
+        title: text("TIT2"),
+        artist: text("TPE1"),
+        album: text("TALB"),
+        year: tag.year().unwrap_or_default().chars().take(4).collect(),
+        comment: tag.comment(None).unwrap_or_default().to_string(),
+        track: tag.track_number().and_then(|x| x.try_into().ok()),
+        genre: tag.text("TCON").and_then(parse_genre).unwrap_or(255),
+    }
+}
+
+/// Describe where the title, artist and album of an ID3v1 tag disagree with those of the
+/// ID3v2 tag in the same file. Fields missing from either tag aren't compared, and a v1
+/// field that's a prefix of the v2 one filling all of its 30 bytes is taken as truncated.
+pub fn disagreements(v1: &Id3v1, v2: &Tag) -> Vec<String> {
+    [
+        ("title", &v1.title, v2.title()),
+        ("artist", &v1.artist, v2.artist()),
+        ("album", &v1.album, v2.album()),
+    ]
+    .into_iter()
+    .filter_map(|(name, old, new)| {
+        let new = new?.trim_end_matches('\0').trim_end();
+        let truncated = old.len() == 30 && new.starts_with(old.as_str());
+        if old.is_empty() || new.is_empty() || old == new || truncated {
+            return None;
+        }
+        Some(format!("v1 {name} '{old}' != v2 {name} '{new}'"))
+    })
+    .collect()
+}
+
+/// Read the ID3v1 tag at the end of a file, if there is one
+pub fn read_id3v1(file: &mut (impl Read + Seek)) -> io::Result<Option<Id3v1>> {
+    let len = file.seek(SeekFrom::End(0))?;
+    if len < ID3V1_SIZE as u64 {
+        return Ok(None);
+    }
+
+    let mut buf = [0; ID3V1_SIZE];
+    file.seek(SeekFrom::End(-(ID3V1_SIZE as i64)))?;
+    file.read_exact(&mut buf)?;
+    Ok(decode(&buf))
+}
+
+/// Where the ID3v1 tag at the end of a file starts, which is where the tags appended before
+/// it end. Without one, that's the end of the file.
+pub(crate) fn id3v1_start(file: &mut (impl Read + Seek)) -> io::Result<u64> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let size = ID3V1_SIZE as u64;
+    if len >= size && crate::read_at(file, len - size, 3)? == b"TAG" {
+        return Ok(len - size);
+    }
+    Ok(len)
+}
+
+/// Write an ID3v1 tag at the end of a file, replacing the existing one
+pub fn write_id3v1(path: impl AsRef<Path>, tag: &Id3v1) -> io::Result<()> {
+    let mut contents = fs::read(&path)?;
+    crate::container::refuse_container(&contents)?;
+
+    let len = contents.len();
+    if len >= ID3V1_SIZE && &contents[len - ID3V1_SIZE..len - ID3V1_SIZE + 3] == b"TAG" {
+        contents.truncate(len - ID3V1_SIZE);
+    }
+
+    contents.extend(encode(tag));
+    fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::testutil::{tag, temp_dir, text_frame};
+
+    #[test]
+    fn write_id3v1_round_trip() {
+        let dir = temp_dir("write-v1");
+        let path = dir.join("song.mp3");
+        fs::write(&path, b"audio").unwrap();
+
+        let v2 = tag(vec![
+            text_frame("TIT2", "A title that is much longer than thirty bytes"),
+            text_frame("TPE1", "Artist"),
+            text_frame("TALB", "Album"),
+            text_frame("TYER", "1999"),
+            text_frame("TRCK", "3/12"),
+            text_frame("TCON", "(17)"),
+            text_frame("COMM", "Comment"),
+        ]);
+        let v1 = super::from_tag(&v2);
+
+        // writing twice must replace the first tag rather than append another
+        super::write_id3v1(&path, &v1).unwrap();
+        super::write_id3v1(&path, &v1).unwrap();
+
+        let contents = fs::read(&path).unwrap();
+        assert_eq!(contents.len(), 5 + super::ID3V1_SIZE);
+
+        let read = super::read_id3v1(&mut fs::File::open(&path).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            read,
+            super::Id3v1 {
+                title: "A title that is much longer th".into(),
+                artist: "Artist".into(),
+                album: "Album".into(),
+                year: "1999".into(),
+                comment: "Comment".into(),
+                track: Some(3),
+                genre: 17,
+            }
+        );
+    }
+
+    #[test]
+    fn v1_v2_disagreements() {
+        let v2 = tag(vec![
+            text_frame("TIT2", "Foobar"),
+            text_frame("TPE1", "Artist"),
+            text_frame("TALB", "A title that is much longer than thirty bytes"),
+        ]);
+        let v1 = super::Id3v1 {
+            title: "Foo".into(),
+            artist: "Artist".into(),
+            album: "A title that is much longer th".into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            super::disagreements(&v1, &v2),
+            ["v1 title 'Foo' != v2 title 'Foobar'"]
+        );
+    }
+
+    #[test]
+    fn parse_genre_forms() {
+        assert_eq!(super::parse_genre("Rock"), Some(17));
+        assert_eq!(super::parse_genre("(17)Rock"), Some(17));
+        assert_eq!(super::parse_genre("9"), Some(9));
+        assert_eq!(super::parse_genre("Nonexistent"), None);
+    }
+}
@@ -0,0 +1,113 @@
+//! Image dimensions read from the headers of attached pictures, without decoding them
+
+fn be_u16(b: &[u8]) -> u32 {
+    u16::from_be_bytes([b[0], b[1]]) as u32
+}
+
+fn le_u16(b: &[u8]) -> u32 {
+    u16::from_le_bytes([b[0], b[1]]) as u32
+}
+
+fn le_u24(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], 0])
+}
+
+/// Width and height of a JPEG, from its first start of frame segment
+fn jpeg(data: &[u8]) -> Option<(u32, u32)> {
+    let mut pos = 2;
+    loop {
+        // markers may be padded with any number of 0xFF bytes
+        while *data.get(pos)? == 0xFF && *data.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        let marker = data.get(pos..pos + 4)?;
+        if marker[0] != 0xFF {
+            return None;
+        }
+        let length = be_u16(&marker[2..]) as usize;
+
+        match marker[1] {
+            // SOF0 to SOF15, but for DHT, JPG and DAC, which share the range
+            0xC0..=0xCF if !matches!(marker[1], 0xC4 | 0xC8 | 0xCC) => {
+                let sof = data.get(pos + 5..pos + 9)?;
+                return Some((be_u16(&sof[2..]), be_u16(&sof[..2])));
+            }
+            // the image data starts without a frame having been seen
+            0xD9 | 0xDA => return None,
+            // standalone markers have no length
+            0x01 | 0xD0..=0xD7 => pos += 2,
+            _ => pos += 2 + length,
+        }
+    }
+}
+
+/// Width and height of a WebP image, whichever of its three formats it's in
+fn webp(data: &[u8]) -> Option<(u32, u32)> {
+    let chunk = data.get(12..16)?;
+    let body = data.get(20..)?;
+    match chunk {
+        b"VP8 " => {
+            let size = body.get(6..10)?;
+            Some((le_u16(size) & 0x3FFF, le_u16(&size[2..]) & 0x3FFF))
+        }
+        b"VP8L" => {
+            let b = body.get(1..5)?;
+            let bits = u32::from_le_bytes(b.try_into().unwrap());
+            Some(((bits & 0x3FFF) + 1, (bits >> 14 & 0x3FFF) + 1))
+        }
+        b"VP8X" => {
+            let size = body.get(4..10)?;
+            Some((le_u24(size) + 1, le_u24(&size[3..]) + 1))
+        }
+        _ => None,
+    }
+}
+
+/// Width and height of a JPEG, PNG, GIF or WebP image, read from its headers alone
+pub fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    match data {
+        [0xFF, 0xD8, ..] => jpeg(data),
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => {
+            let ihdr = data.get(12..24)?;
+            if &ihdr[..4] != b"IHDR" {
+                return None;
+            }
+            let width = u32::from_be_bytes(ihdr[4..8].try_into().unwrap());
+            let height = u32::from_be_bytes(ihdr[8..12].try_into().unwrap());
+            Some((width, height))
+        }
+        [b'G', b'I', b'F', b'8', ..] => {
+            let size = data.get(6..10)?;
+            Some((le_u16(size), le_u16(&size[2..])))
+        }
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => webp(data),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn jpeg_dimensions() {
+        let mut jpeg = vec![0xFF, 0xD8];
+        // an APP0 segment to skip, then a baseline frame of 1400x1050
+        jpeg.extend([0xFF, 0xE0, 0x00, 0x10]);
+        jpeg.extend(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        jpeg.extend([0xFF, 0xC0, 0x00, 0x11, 0x08, 0x04, 0x1A, 0x05, 0x78, 0x03]);
+        jpeg.extend([0; 9]);
+
+        assert_eq!(super::dimensions(&jpeg), Some((1400, 1050)));
+        assert_eq!(super::dimensions(&jpeg[..20]), None);
+    }
+
+    #[test]
+    fn png_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(500u32.to_be_bytes());
+        png.extend(300u32.to_be_bytes());
+        png.extend([8, 6, 0, 0, 0]);
+
+        assert_eq!(super::dimensions(&png), Some((500, 300)));
+        assert_eq!(super::dimensions(b"\x89PNG\r\n\x1a\n"), None);
+    }
+}
@@ -0,0 +1,176 @@
+use serde_json::{json, Value};
+
+use crate::{
+    fieldmap::FieldMap,
+    image,
+    parser::{Frame, Tag},
+};
+
+/// Metadata of every attached picture, without the image data itself
+pub fn picture_list(tag: &Tag) -> Value {
+    let pictures = tag
+        .pictures()
+        .into_iter()
+        .filter_map(|x| match x {
+            Frame::Apic {
+                data,
+                mime_type,
+                picture_type,
+                description,
+            } => {
+                let mut picture = json!({
+                    "picture_type": picture_type.name(),
+                    "mime_type": mime_type,
+                    "description": description,
+                    "length": data.len(),
+                });
+                if let Some(url) = x.linked_picture_url() {
+                    picture["url"] = url.into();
+                }
+                if let Some((width, height)) = image::dimensions(data) {
+                    picture["width"] = width.into();
+                    picture["height"] = height.into();
+                }
+                Some(picture)
+            }
+            _ => None,
+        })
+        .collect();
+
+    Value::Array(pictures)
+}
+
+/// The fields `info` shows, as one object per file. Fields the tag lacks are left out.
+pub fn info(path: &str, tag: &Tag, lang: Option<&str>, fields: &FieldMap) -> Value {
+    let frame = |id: &str| tag.frames_by_id(id).next().map(|x| x.to_string());
+
+    let mut info = json!({ "path": path });
+    let text = [
+        ("title", frame(&fields.title)),
+        ("artist", frame(&fields.artist)),
+        ("album", frame(&fields.album)),
+        ("year", frame(&fields.year)),
+        ("band", frame(&fields.band)),
+        ("comment", tag.comment(lang).map(|x| x.to_string())),
+        ("key", tag.key().map(|x| x.to_string())),
+        ("encoder", tag.encoder_settings().map(|x| x.to_string())),
+        ("encoded_by", tag.encoded_by().map(|x| x.to_string())),
+    ];
+    for (name, value) in text {
+        if let Some(value) = value {
+            info[name] = value.into();
+        }
+    }
+    let numbers = [
+        ("track", tag.track_number().map(u64::from)),
+        ("track_total", tag.track_total().map(u64::from)),
+        ("bpm", tag.bpm().map(u64::from)),
+        ("play_count", tag.play_count()),
+    ];
+    for (name, value) in numbers {
+        if let Some(value) = value {
+            info[name] = value.into();
+        }
+    }
+    info
+}
+
+/// The whole tag, header and every frame with all its fields. Binary data is base64 encoded.
+pub fn dump(tag: &Tag) -> Value {
+    let mut value = serde_json::to_value(tag).unwrap();
+
+    // typed frames don't store their id, so add it to every frame
+    for (frames, json) in [(&tag.frames, "frames"), (&tag.trailer, "trailer")] {
+        if let Some(values) = value[json].as_array_mut() {
+            for (frame, json) in frames.iter().zip(values) {
+                json["id"] = frame.id().into();
+            }
+        }
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        parser::{Frame, PictureType},
+        testutil::{tag, text_frame},
+    };
+
+    #[test]
+    fn info_json() {
+        let tag = tag(vec![
+            text_frame("TIT2", "Title"),
+            text_frame("TRCK", "3/12"),
+        ]);
+
+        assert_eq!(
+            super::info("a.mp3", &tag, None, &Default::default()),
+            json!({"path": "a.mp3", "title": "Title", "track": 3, "track_total": 12})
+        );
+    }
+
+    #[test]
+    fn picture_list_json() {
+        let tag = tag(vec![
+            Frame::Apic {
+                data: vec![0; 16],
+                mime_type: "image/jpeg".into(),
+                picture_type: PictureType::CoverFront,
+                description: "front".into(),
+            },
+            Frame::Apic {
+                data: vec![0; 8],
+                mime_type: "image/png".into(),
+                picture_type: PictureType::CoverBack,
+                description: "".into(),
+            },
+        ]);
+
+        assert_eq!(
+            super::picture_list(&tag),
+            json!([
+                {
+                    "picture_type": "cover-front",
+                    "mime_type": "image/jpeg",
+                    "description": "front",
+                    "length": 16,
+                },
+                {
+                    "picture_type": "cover-back",
+                    "mime_type": "image/png",
+                    "description": "",
+                    "length": 8,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn dump_json() {
+        let tag = tag(vec![
+            text_frame("TIT2", "Title"),
+            Frame::Apic {
+                data: b"png".to_vec(),
+                mime_type: "image/png".into(),
+                picture_type: PictureType::CoverFront,
+                description: "".into(),
+            },
+        ]);
+
+        let text = super::dump(&tag).to_string();
+        let dump: serde_json::Value = serde_json::from_str(&text).unwrap();
+
+        assert_eq!(dump["header"]["version"], 3);
+        assert_eq!(
+            dump["frames"][0],
+            json!({"type": "other", "id": "TIT2", "content": {"text": "Title"}})
+        );
+        assert_eq!(dump["frames"][1]["id"], "APIC");
+        assert_eq!(dump["frames"][1]["picture_type"], "cover-front");
+        assert_eq!(dump["frames"][1]["data"], "cG5n");
+    }
+}
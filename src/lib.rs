@@ -0,0 +1,769 @@
+use std::{
+    error::Error,
+    fmt::{self},
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    ops::Range,
+    path::Path,
+};
+
+pub mod ape;
+#[cfg(feature = "zip")]
+pub mod archive;
+#[cfg(feature = "async")]
+pub mod async_read;
+pub mod cache;
+pub mod chap;
+pub mod container;
+pub mod diff;
+pub mod downgrade;
+pub mod dupes;
+pub mod encoder;
+pub mod etco;
+pub mod fieldmap;
+pub mod hexdump;
+#[cfg(feature = "id3-interop")]
+pub mod id3_interop;
+pub mod id3v1;
+pub mod image;
+pub mod json;
+pub mod lyrics3;
+pub mod merge;
+pub mod mojibake;
+pub mod mpeg;
+pub mod parser;
+pub mod replaygain;
+pub mod sort;
+pub mod stream;
+pub mod sylt;
+pub mod template;
+#[cfg(test)]
+mod testutil;
+pub mod walk;
+
+use parser::*;
+
+/// What kind of failure an [`AppError`] is, so callers like the command line can tell
+/// them apart without matching on the message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorKind {
+    #[default]
+    Other,
+    /// The file holds no ID3 tag
+    NotId3,
+    /// The tag is there but couldn't be decoded
+    Decode,
+    /// The tag lacks what was asked for, like lyrics or a picture
+    Missing,
+}
+
+#[derive(Debug)]
+pub struct AppError {
+    details: String,
+    kind: ErrorKind,
+}
+
+impl AppError {
+    pub fn new(msg: &str) -> Box<Self> {
+        AppError::with_kind(ErrorKind::Other, msg)
+    }
+
+    pub fn with_kind(kind: ErrorKind, msg: &str) -> Box<Self> {
+        Box::new(AppError {
+            details: msg.into(),
+            kind,
+        })
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl Error for AppError {}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+fn read_header_bytes(file: &mut impl Read) -> Result<[u8; 10], Box<dyn Error>> {
+    let mut tag_headers = [0; 10];
+    match file.read_exact(&mut tag_headers) {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(AppError::with_kind(
+            ErrorKind::NotId3,
+            "File too small to contain an ID3 tag",
+        )),
+        x => Ok(x.map(|_| tag_headers)?),
+    }
+}
+
+/// Whether the bytes right after a tag look like what follows a tag: padding, an MPEG audio
+/// frame, another tag or the end of the file
+fn looks_like_tag_end(buf: &[u8]) -> bool {
+    match buf {
+        [] | [0x0, ..] => true,
+        [0xFF, b, ..] => b & 0xE0 == 0xE0,
+        _ => buf.starts_with(b"TAG") || buf.starts_with(b"ID3") || buf.starts_with(b"APETAGEX"),
+    }
+}
+
+/// Some encoders write the tag size as a plain integer rather than a synchsafe one. When the
+/// synchsafe size ends the tag somewhere odd but the plain size ends it right, the plain size
+/// is the one to believe.
+fn plain_tag_size(
+    file: &mut (impl Read + Seek),
+    size_bytes: &[u8],
+    synchsafe_size: u32,
+) -> io::Result<Option<u32>> {
+    let plain_size = byte_int(size_bytes);
+    if plain_size == synchsafe_size {
+        return Ok(None);
+    }
+
+    let tag_body = file.stream_position()?;
+    let mut peek = |size: u32| -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        file.seek(SeekFrom::Start(tag_body + size as u64))?;
+        file.by_ref().take(8).read_to_end(&mut buf)?;
+        Ok(buf)
+    };
+
+    let synchsafe_end = peek(synchsafe_size)?;
+    let plain_end = peek(plain_size)?;
+    file.seek(SeekFrom::Start(tag_body))?;
+
+    if !looks_like_tag_end(&synchsafe_end) && looks_like_tag_end(&plain_end) {
+        Ok(Some(plain_size))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Number of bytes taken up by the ID3v2 tag at the start of a file, 0 if it doesn't start
+/// with one. The size is read as a plain integer when that's the one that ends the tag right,
+/// so that what follows it is found where it really is.
+pub(crate) fn leading_tag_extent(file: &mut (impl Read + Seek)) -> io::Result<u64> {
+    let len = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+    let mut bytes = [0; 10];
+    if file.read_exact(&mut bytes).is_err() {
+        return Ok(0);
+    }
+    let Ok(header) = decode_header(bytes) else {
+        return Ok(0);
+    };
+
+    let size = plain_tag_size(file, &bytes[6..10], header.size)?.unwrap_or(header.size);
+    let footer = if header.footer_present { 10 } else { 0 };
+    Ok((10 + size as u64 + footer).min(len))
+}
+
+/// How far past the tag to look for the start of the audio
+const AUDIO_SEARCH_WINDOW: u64 = 1 << 20;
+
+/// Byte offset where the MPEG audio stream of a file starts: after the ID3v2 tag, if any,
+/// and any junk between the tag and the first audio frame
+pub fn audio_offset(path: impl AsRef<Path>) -> io::Result<u64> {
+    let mut file = fs::File::open(path)?;
+    let tag_size = leading_tag_extent(&mut file)?;
+
+    let mut audio = Vec::new();
+    file.seek(SeekFrom::Start(tag_size))?;
+    file.take(AUDIO_SEARCH_WINDOW).read_to_end(&mut audio)?;
+
+    match mpeg::first_frame(&audio) {
+        Some(offset) => Ok(tag_size + offset as u64),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "No MPEG audio frames found",
+        )),
+    }
+}
+
+pub fn read_file(path: impl AsRef<Path>) -> Result<Tag, Box<dyn Error>> {
+    read_file_with(path, &ParseOptions::default())
+}
+
+pub fn read_file_with(
+    path: impl AsRef<Path>,
+    options: &ParseOptions,
+) -> Result<Tag, Box<dyn Error>> {
+    read_tag_from(&mut fs::File::open(path)?, options)
+}
+
+/// Bytes read past the end of a tag, enough to tell a tag size that isn't synchsafe
+const TAG_END_PEEK: u64 = 8;
+
+/// How many bytes after its 10 byte header hold the tag, for streams that can only be read
+/// forward. The size read as a plain integer is never smaller than read as a synchsafe one,
+/// so this covers both readings, and a footer.
+pub(crate) fn tag_bytes_after_header(header: &[u8]) -> u64 {
+    byte_int(&header[6..10]) as u64 + 10 + TAG_END_PEEK
+}
+
+/// Read the tag at the start of a stream that can only be read forward. Only the tag is
+/// read, so tags appended at the end of the file (Lyrics3, APE) are left out, while WAV and
+/// AIFF files are read whole to find their ID3 chunk.
+pub fn read_tag_streaming(
+    mut stream: impl Read,
+    options: &ParseOptions,
+) -> Result<Tag, Box<dyn Error>> {
+    let mut buf = Vec::new();
+    stream.by_ref().take(10).read_to_end(&mut buf)?;
+    if container::is_container(&buf) {
+        stream.read_to_end(&mut buf)?;
+    } else if buf.len() == 10 {
+        let size = tag_bytes_after_header(&buf);
+        stream.take(size).read_to_end(&mut buf)?;
+    }
+
+    read_tag_from(&mut io::Cursor::new(buf), options)
+}
+
+/// Read `size` bytes of a file from `offset` on
+pub(crate) fn read_at(
+    file: &mut (impl Read + Seek),
+    offset: u64,
+    size: usize,
+) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut b = vec![0; size];
+    file.read_exact(&mut b)?;
+    Ok(b)
+}
+
+/// Where a tag appended after the audio lies, as some streamers write them, either as the
+/// very last thing in the file or right before an ID3v1 tag
+pub(crate) fn find_appended_tag(file: &mut (impl Read + Seek)) -> io::Result<Option<Range<u64>>> {
+    let end = id3v1::id3v1_start(file)?;
+    appended_tag_ending_at(file, end)
+}
+
+/// Where the appended tag ending at `end` lies, if one does. Such a tag ends in a "3DI"
+/// footer, which tells how far back its header is.
+pub(crate) fn appended_tag_ending_at(
+    file: &mut (impl Read + Seek),
+    end: u64,
+) -> io::Result<Option<Range<u64>>> {
+    if end < 20 {
+        return Ok(None);
+    }
+
+    let mut footer = read_at(file, end - 10, 10)?;
+    if !footer.starts_with(b"3DI") {
+        return Ok(None);
+    }
+    // the footer is a copy of the header, but for its identifier
+    footer[..3].copy_from_slice(b"ID3");
+    let header = match decode_header(footer.try_into().unwrap()) {
+        Ok(header) if header.footer_present => header,
+        _ => return Ok(None),
+    };
+
+    let start = (end - 10).checked_sub(10 + header.size as u64);
+    Ok(start
+        .filter(|&x| read_at(file, x, 3).is_ok_and(|x| x == b"ID3"))
+        .map(|start| start..end))
+}
+
+/// Whether the bytes make a believable ID3v2 header, rather than "ID3" turning up by chance
+fn looks_like_header(buf: &[u8]) -> bool {
+    buf.len() >= 10
+        && buf.starts_with(b"ID3")
+        && (2..=4).contains(&buf[3])
+        && buf[4] != 0xFF
+        && buf[6..10].iter().all(|x| x & 0x80 == 0)
+}
+
+/// Read every tag in a file, such as a stream of tagged segments concatenated together, in
+/// the order they appear. Each tag is read on its own, without the tags at the end of the
+/// file, and frame offsets are from the start of the file. The bytes of a tag aren't
+/// searched for further tags.
+pub fn read_all_tags(
+    path: impl AsRef<Path>,
+    options: &ParseOptions,
+) -> Result<Vec<Tag>, Box<dyn Error>> {
+    let contents = fs::read(path)?;
+    let mut tags = Vec::new();
+
+    let mut pos = 0;
+    while let Some(found) = contents[pos..].windows(3).position(|x| x == b"ID3") {
+        let start = pos + found;
+        if !looks_like_header(&contents[start..]) {
+            pos = start + 1;
+            continue;
+        }
+
+        let end =
+            (start + 10 + tag_bytes_after_header(&contents[start..]) as usize).min(contents.len());
+        match read_tag_from(&mut io::Cursor::new(&contents[start..end]), options) {
+            Ok(mut tag) => {
+                for offset in &mut tag.offsets {
+                    *offset += start as u64;
+                }
+                pos = start + tag.total_size() as usize;
+                tags.push(tag);
+            }
+            Err(_) => pos = start + 1,
+        }
+    }
+
+    Ok(tags)
+}
+
+/// Read the tag of a file already opened, or held in memory
+pub fn read_tag_from(
+    file: &mut (impl Read + Seek),
+    options: &ParseOptions,
+) -> Result<Tag, Box<dyn Error>> {
+    let mut tag_headers = read_header_bytes(file)?;
+
+    // WAV and AIFF files carry the tag in a chunk of their own
+    if container::is_container(&tag_headers) {
+        match container::find_id3_chunk(file)? {
+            Some(offset) => {
+                file.seek(SeekFrom::Start(offset))?;
+                tag_headers = read_header_bytes(file)?;
+            }
+            None => {
+                return Err(AppError::with_kind(
+                    ErrorKind::NotId3,
+                    "No ID3 chunk found in the WAV/AIFF file",
+                ))
+            }
+        }
+    } else if !tag_headers.starts_with(b"ID3") {
+        if let Some(appended) = find_appended_tag(file)? {
+            file.seek(SeekFrom::Start(appended.start))?;
+            tag_headers = read_header_bytes(file)?;
+        }
+    }
+
+    let mut header = decode_header(tag_headers)?;
+    let mut warnings = Vec::new();
+
+    if let Some(size) = plain_tag_size(file, &tag_headers[6..10], header.size)? {
+        warnings.push(Warning::new(
+            None,
+            format!(
+                "tag size isn't synchsafe, read as {} rather than {} bytes",
+                size, header.size
+            ),
+        ));
+        header.size = size;
+    }
+
+    // the extended header is part of the tag size, so only what's left after it holds frames
+    let mut frames_size = header.size as usize;
+
+    if header.extended {
+        let size_field = consume_bytes(file, 4)?;
+
+        // a size below the minimum for the version, or larger than the tag, can't be right,
+        // so only the size field is skipped
+        match extended_header_size(&size_field, header.version) {
+            Some(size) if size <= frames_size => {
+                consume_bytes(file, size - 4)?;
+                frames_size -= size;
+            }
+            _ => {
+                warnings.push(Warning::new(
+                    None,
+                    format!(
+                        "extended header size {} is bogus, ignored",
+                        byte_int(&size_field)
+                    ),
+                ));
+                frames_size = frames_size.saturating_sub(4);
+            }
+        }
+    }
+
+    let frames_start = file.stream_position()?;
+    // a truncated file holds less than the tag claims, decode whatever is there
+    let tag_frames = {
+        let mut tag_frames = Vec::with_capacity(frames_size);
+        file.by_ref()
+            .take(frames_size as u64)
+            .read_to_end(&mut tag_frames)?;
+        tag_frames
+    };
+    // v2.3 unsynchronises the tag as a whole and its frame sizes count the restored bytes, so
+    // it's undone before splitting the frames. v2.4 frames are restored one by one instead.
+    let tag_frames = if header.unsynchronisation && header.version < 4 {
+        resynchronise(&tag_frames)
+    } else {
+        tag_frames
+    };
+
+    let DecodedFrames {
+        frames,
+        warnings: frame_warnings,
+        offsets,
+        sizes,
+        encodings,
+        timings,
+    } = decode_frames_with(tag_frames, header.version == 4, options)?;
+    warnings.extend(frame_warnings);
+    let offsets = offsets
+        .into_iter()
+        .map(|x| frames_start + x as u64)
+        .collect();
+
+    // Lyrics3 only carries lyrics worth surfacing when the ID3v2 tag has none of its own
+    let mut trailer = Vec::new();
+    if !frames.iter().any(|x| matches!(x, Frame::Uslt { .. })) {
+        if let Some(lyrics) = lyrics3::read_lyrics3(file)?.and_then(|x| x.lyrics) {
+            trailer.push(Frame::Uslt {
+                text: lyrics,
+                language: "XXX".into(),
+                description: "Lyrics3".into(),
+            });
+        }
+    }
+
+    // an APE tag left behind by another tagger fills in the basic fields the ID3v2 tag lacks
+    if let Some(ape) = ape::read_ape(file)? {
+        trailer.extend(
+            ape.frames()
+                .into_iter()
+                .filter(|frame| !frames.iter().any(|x| x.id() == frame.id())),
+        );
+    }
+
+    Ok(Tag {
+        header,
+        frames,
+        warnings,
+        offsets,
+        sizes,
+        encodings,
+        timings,
+        trailer,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        encoder::encode_tag,
+        parser::{Content, Frame, PictureType},
+        testutil::{raw_frame, raw_tag, raw_text_frame, tag, temp_dir, text_frame},
+    };
+
+    #[test]
+    fn read_file_too_small() {
+        let dir = temp_dir("too-small");
+        fs::write(dir.join("empty.mp3"), b"").unwrap();
+        fs::write(dir.join("short.mp3"), b"ID3\x03").unwrap();
+
+        for name in ["empty.mp3", "short.mp3"] {
+            let e = super::read_file(dir.join(name)).unwrap_err();
+            assert_eq!(e.to_string(), "File too small to contain an ID3 tag");
+        }
+    }
+
+    #[test]
+    fn read_frame_offsets() {
+        let dir = temp_dir("frame-offsets");
+        let path = dir.join("offsets.mp3");
+        let first = raw_text_frame("TIT2", "Title");
+        let second = raw_text_frame("TPE1", "Artist");
+        fs::write(&path, raw_tag(&[first.clone(), second])).unwrap();
+
+        let tag = super::read_file(&path).unwrap();
+
+        assert_eq!(tag.offsets, [10, 10 + first.len() as u64]);
+    }
+
+    #[test]
+    fn read_frame_sizes() {
+        let path = temp_dir("frame-sizes").join("sizes.mp3");
+        let tag = tag(vec![
+            text_frame("TIT2", "Title"),
+            Frame::Comm {
+                text: "Comment".into(),
+                language: "eng".into(),
+                description: "Note".into(),
+            },
+            Frame::Apic {
+                data: vec![0xFF, 0xD8, 0xFF, 0xD9],
+                mime_type: "image/jpeg".into(),
+                picture_type: PictureType::CoverFront,
+                description: "".into(),
+            },
+        ]);
+        fs::write(&path, encode_tag(&tag)).unwrap();
+
+        let tag = super::read_file(&path).unwrap();
+
+        assert_eq!(tag.sizes.len(), 3);
+        assert!(tag.sizes.iter().all(|x| x.matches()));
+        assert_eq!(tag.sizes[0].declared, 6);
+    }
+
+    #[test]
+    fn read_and_rewrite_plain_tag_size() {
+        let dir = temp_dir("plain-size");
+        let path = dir.join("plain-size.mp3");
+        let frames = [
+            raw_text_frame("TIT2", &"Title ".repeat(50)),
+            raw_text_frame("TPE1", "Artist"),
+        ];
+        let size = frames.concat().len() as u32;
+        assert!(size > 0xFF);
+
+        let mut contents = b"ID3\x03\x00\x00".to_vec();
+        contents.extend(size.to_be_bytes());
+        contents.extend(frames.concat());
+        let audio_start = contents.len() as u64;
+        let mut audio = vec![0xFF, 0xFB, 0x90, 0x00];
+        audio.resize(417, 0);
+        let audio = audio.repeat(3);
+        contents.extend(&audio);
+        fs::write(&path, contents).unwrap();
+
+        let mut tag = super::read_file(&path).unwrap();
+
+        assert_eq!(tag.header.size, size);
+        assert_eq!(tag.artist(), Some("Artist"));
+        assert_eq!(tag.warnings.len(), 1);
+        assert_eq!(super::audio_offset(&path).unwrap(), audio_start);
+
+        // the old tag is replaced whole, none of its tail left in front of the audio
+        tag.set_text("TIT2", "New");
+        crate::encoder::write_tag(&path, &tag).unwrap();
+        let written = fs::read(&path).unwrap();
+        let new_size = crate::encoder::leading_tag_size(&written);
+        assert_eq!(&written[new_size..], audio);
+        assert_eq!(super::read_file(&path).unwrap().title(), Some("New"));
+    }
+
+    #[test]
+    fn count_frames_and_their_bytes() {
+        let path = temp_dir("frame-bytes").join("frame-bytes.mp3");
+        let frames = [
+            raw_text_frame("TIT2", "Title"),
+            raw_text_frame("TPE1", "Artist"),
+        ];
+        let mut contents = raw_tag(&[frames.concat(), vec![0; 100]]);
+        contents.extend([0xFF, 0xFB, 0x90, 0x64]);
+        fs::write(&path, contents).unwrap();
+
+        let tag = super::read_file(&path).unwrap();
+
+        assert_eq!(tag.frame_count(), 2);
+        assert_eq!(tag.frame_bytes(), 16 + 17);
+        assert_eq!(tag.header.size as u64 - tag.frame_bytes(), 100);
+    }
+
+    #[test]
+    fn read_unsynchronised_v3_tag() {
+        let path = temp_dir("unsynch-v3").join("unsynch.mp3");
+        // the size of the first frame counts its three bytes before unsynchronisation
+        let mut mcdi = raw_frame("MCDI", &[0xFF, 0xE0, 0x01]);
+        mcdi.insert(11, 0x00);
+        let mut contents = raw_tag(&[mcdi, raw_text_frame("TIT2", "Title")]);
+        contents[5] |= 0x80; // unsynchronisation flag
+        fs::write(&path, contents).unwrap();
+
+        let tag = super::read_file(&path).unwrap();
+
+        assert!(tag.header.unsynchronisation);
+        assert_eq!(
+            tag.frames[0],
+            Frame::Other {
+                id: "MCDI".into(),
+                content: Content::Binary(vec![0xFF, 0xE0, 0x01]),
+            }
+        );
+        assert_eq!(tag.title(), Some("Title"));
+        assert!(tag.warnings.is_empty());
+    }
+
+    #[test]
+    fn read_bogus_extended_header_size() {
+        let dir = temp_dir("bogus-extended");
+        let path = dir.join("bogus-extended.mp3");
+        let mut contents = raw_tag(&[vec![0; 4], raw_text_frame("TIT2", "Title")]);
+        contents[5] |= 0x40; // extended header flag
+
+        fs::write(&path, contents).unwrap();
+
+        let tag = super::read_file(&path).unwrap();
+
+        assert!(tag.header.extended);
+        assert_eq!(tag.title(), Some("Title"));
+        assert_eq!(tag.warnings.len(), 1);
+    }
+
+    #[test]
+    fn read_extended_header() {
+        let dir = temp_dir("extended");
+        // v2.3 leaves the size field out of the size, v2.4 counts it and makes it synchsafe
+        let cases: [(u8, &[u8]); 3] = [
+            (3, b"\0\0\0\x06\0\0\0\0\0\0"),
+            (3, b"\0\0\0\x0A\0\0\0\0\0\0\x12\x34\x56\x78"),
+            (4, b"\0\0\0\x06\x01\0"),
+        ];
+
+        for (i, (version, extended)) in cases.into_iter().enumerate() {
+            let path = dir.join(format!("extended-{}.mp3", i));
+            let mut contents = raw_tag(&[extended.to_vec(), raw_text_frame("TIT2", "Title")]);
+            contents[3] = version;
+            contents[5] |= 0x40; // extended header flag
+            fs::write(&path, contents).unwrap();
+
+            let tag = super::read_file(&path).unwrap();
+
+            assert_eq!(tag.title(), Some("Title"));
+            assert!(tag.warnings.is_empty(), "{:?}", tag.warnings);
+        }
+    }
+
+    #[test]
+    fn audio_offset_after_tag_and_junk() {
+        let dir = temp_dir("audio-offset");
+        let path = dir.join("audio-offset.mp3");
+        let tag = raw_tag(&[raw_text_frame("TIT2", "Title")]);
+        let frame = {
+            let mut frame = vec![0xFF, 0xFB, 0x90, 0x00];
+            frame.resize(417, 0);
+            frame
+        };
+        let mut contents = tag.clone();
+        contents.extend(b"junk\xFF\xFB\x90");
+        contents.extend(frame.repeat(2));
+        fs::write(&path, contents).unwrap();
+
+        assert_eq!(super::audio_offset(&path).unwrap(), tag.len() as u64 + 7);
+    }
+
+    #[test]
+    fn read_tags_back_to_back() {
+        let path = temp_dir("all-tags").join("segments.mp3");
+        let first = raw_tag(&[raw_text_frame("TIT2", "Part 1")]);
+        let mut contents = b"junk".to_vec();
+        contents.extend(&first);
+        contents.extend(raw_tag(&[
+            raw_text_frame("TIT2", "Part 2"),
+            raw_text_frame("TPE1", "Host"),
+        ]));
+        contents.extend([0xFF, 0xFB, 0x90, 0x64]);
+        contents.extend(b"ID3 in the audio");
+        fs::write(&path, contents).unwrap();
+
+        let tags = super::read_all_tags(&path, &Default::default()).unwrap();
+
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[0].title(), Some("Part 1"));
+        assert_eq!(tags[0].offsets, [4 + 10]);
+        assert_eq!(tags[1].title(), Some("Part 2"));
+        assert_eq!(tags[1].artist(), Some("Host"));
+        assert_eq!(tags[1].offsets[0], 4 + first.len() as u64 + 10);
+    }
+
+    #[test]
+    fn read_tag_appended_after_audio() {
+        let dir = temp_dir("appended-tag");
+        let mut tag = raw_tag(&[raw_text_frame("TIT2", "Live")]);
+        tag[3] = 4;
+        tag[5] = 0x10; // footer present
+        let mut footer = tag[..10].to_vec();
+        footer[..3].copy_from_slice(b"3DI");
+        tag.extend(footer);
+
+        let mut contents = vec![0xFF, 0xFB, 0x90, 0x64];
+        contents.extend([0; 64]);
+        contents.extend(&tag);
+        let tag_len = tag.len();
+        fs::write(dir.join("stream.mp3"), &contents).unwrap();
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, 0);
+        contents.extend(id3v1);
+        fs::write(dir.join("stream-v1.mp3"), &contents).unwrap();
+
+        for name in ["stream.mp3", "stream-v1.mp3"] {
+            let path = dir.join(name);
+            let mut tag = super::read_file(&path).unwrap();
+            assert_eq!(tag.header.version, 4);
+            assert!(tag.header.footer_present);
+            assert_eq!(tag.title(), Some("Live"));
+
+            // writing it back moves the tag to the front instead of leaving the old one behind
+            let before = fs::read(&path).unwrap();
+            tag.set_text("TIT2", "Encore");
+            crate::encoder::write_tag(&path, &tag).unwrap();
+            let after = fs::read(&path).unwrap();
+            assert_eq!(
+                super::read_all_tags(&path, &Default::default())
+                    .unwrap()
+                    .len(),
+                1
+            );
+            assert_eq!(super::read_file(&path).unwrap().title(), Some("Encore"));
+            let kept = [&before[..68], &before[68 + tag_len..]].concat();
+            assert!(after.ends_with(&kept));
+        }
+    }
+
+    #[test]
+    fn ape_fields_are_not_written_back() {
+        use crate::testutil::ape_trailer;
+
+        let path = temp_dir("ape-fallback").join("ape.mp3");
+        let mut contents = raw_tag(&[raw_text_frame("TPE1", "Artist")]);
+        contents.extend([0xFF, 0xFB, 0x90, 0x64]);
+        contents.extend(ape_trailer(&[
+            ("Title", "APE Title"),
+            ("Artist", "APE Artist"),
+        ]));
+        fs::write(&path, contents).unwrap();
+
+        let tag = super::read_file(&path).unwrap();
+
+        assert_eq!(tag.title(), Some("APE Title"));
+        assert_eq!(tag.artist(), Some("Artist"));
+        assert_eq!(tag.frames, [text_frame("TPE1", "Artist")]);
+        assert_eq!(tag.trailer, [text_frame("TIT2", "APE Title")]);
+        let written = encode_tag(&tag);
+        assert!(!written.windows(4).any(|x| x == b"TIT2"));
+    }
+
+    #[test]
+    fn lyrics3_is_not_written_back() {
+        use crate::testutil::lyrics3_block;
+
+        let path = temp_dir("lyrics3-fallback").join("lyrics3.mp3");
+        let mut contents = raw_tag(&[raw_text_frame("TIT2", "Title")]);
+        contents.extend([0xFF, 0xFB, 0x90, 0x64]);
+        contents.extend(lyrics3_block(&[("LYR", "la la la")]));
+        fs::write(&path, contents).unwrap();
+
+        let tag = super::read_file(&path).unwrap();
+
+        assert_eq!(tag.frames, [text_frame("TIT2", "Title")]);
+        assert!(matches!(
+            tag.frames_by_id("USLT").next(),
+            Some(Frame::Uslt { text, .. }) if text == "la la la"
+        ));
+        let written = encode_tag(&tag);
+        assert!(!written.windows(4).any(|x| x == b"USLT"));
+    }
+
+    #[test]
+    fn read_empty_tag() {
+        let dir = temp_dir("empty-tag");
+        let path = dir.join("empty-tag.mp3");
+        fs::write(&path, raw_tag(&[])).unwrap();
+
+        let tag = super::read_file(&path).unwrap();
+
+        assert_eq!(tag.header.size, 0);
+        assert!(tag.frames.is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    io::{self, Read, Seek},
+    ops::Range,
+};
+
+use crate::{
+    id3v1::id3v1_start,
+    parser::{decode_str, Encoding},
+    read_at,
+};
+
+const END_MARKER: &[u8] = b"LYRICS200";
+const BEGIN_MARKER: &[u8] = b"LYRICSBEGIN";
+/// The 6 digit block size and the end marker after the block
+const TRAILER_SIZE: u64 = 6 + END_MARKER.len() as u64;
+
+/// Fields of a Lyrics3 v2 block appended near the end of the file
+#[derive(Debug, Default)]
+pub struct Lyrics3 {
+    pub lyrics: Option<String>,
+    pub info: Option<String>,
+    pub author: Option<String>,
+    pub album: Option<String>,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+}
+
+fn parse_decimal(buf: &[u8]) -> Option<usize> {
+    std::str::from_utf8(buf).ok()?.parse().ok()
+}
+
+fn decode_fields(buf: &[u8]) -> Option<Lyrics3> {
+    let mut lyrics3 = Lyrics3::default();
+    let mut rest = buf.strip_prefix(BEGIN_MARKER)?;
+
+    // each field is a 3 byte id, a 5 digit decimal size and the data itself
+    while rest.len() >= 8 {
+        let id = &rest[0..3];
+        let size = parse_decimal(&rest[3..8])?;
+        let data = rest.get(8..8 + size)?;
+        let value = decode_str(data, Encoding::ISO_8859_1).ok()?;
+
+        match id {
+            b"LYR" => lyrics3.lyrics = Some(value.replace("\r\n", "\n")),
+            b"INF" => lyrics3.info = Some(value),
+            b"AUT" => lyrics3.author = Some(value),
+            b"EAL" => lyrics3.album = Some(value),
+            b"EAR" => lyrics3.artist = Some(value),
+            b"ETT" => lyrics3.title = Some(value),
+            _ => {} // IND, IMG and unknown fields aren't useful to us
+        }
+
+        rest = &rest[8 + size..];
+    }
+
+    Some(lyrics3)
+}
+
+/// Look for a Lyrics3 v2 block at the end of the file, either right before an ID3v1 tag or
+/// as the very last thing in the file. Malformed blocks are treated as absent.
+pub fn read_lyrics3(file: &mut (impl Read + Seek)) -> io::Result<Option<Lyrics3>> {
+    let end = id3v1_start(file)?;
+    let Some(extent) = find_lyrics3(file, end)? else {
+        return Ok(None);
+    };
+
+    let size = extent.end - extent.start - TRAILER_SIZE;
+    let block = read_at(file, extent.start, size as usize)?;
+    Ok(decode_fields(&block))
+}
+
+/// Where the Lyrics3 v2 block ending at `end` lies, trailer included, if one does
+pub(crate) fn find_lyrics3(
+    file: &mut (impl Read + Seek),
+    end: u64,
+) -> io::Result<Option<Range<u64>>> {
+    if end < TRAILER_SIZE {
+        return Ok(None);
+    }
+    let trailer = read_at(file, end - TRAILER_SIZE, TRAILER_SIZE as usize)?;
+    if &trailer[6..] != END_MARKER {
+        return Ok(None);
+    }
+
+    Ok(match parse_decimal(&trailer[..6]) {
+        Some(size) if size as u64 <= end - TRAILER_SIZE => {
+            Some(end - TRAILER_SIZE - size as u64..end)
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::testutil::lyrics3_block;
+
+    #[test]
+    fn parse_lyrics3_before_id3v1() {
+        let mut file = b"fake audio".to_vec();
+        file.extend(lyrics3_block(&[
+            ("IND", "10"),
+            ("LYR", "[00:01]First line\r\nSecond line"),
+            ("ETT", "Title"),
+        ]));
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, 0);
+        file.extend(id3v1);
+
+        let lyrics3 = super::read_lyrics3(&mut Cursor::new(file))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            lyrics3.lyrics.as_deref(),
+            Some("[00:01]First line\nSecond line")
+        );
+        assert_eq!(lyrics3.title.as_deref(), Some("Title"));
+        assert!(lyrics3.artist.is_none());
+    }
+
+    #[test]
+    fn no_lyrics3_block() {
+        let file = b"fake audio with no trailer".to_vec();
+        assert!(super::read_lyrics3(&mut Cursor::new(file))
+            .unwrap()
+            .is_none());
+    }
+}
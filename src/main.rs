@@ -1,216 +1,2910 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
 use std::{
+    env,
     error::Error,
-    fmt::{self},
+    ffi::OsStr,
     fs,
-    io::{Read, Write},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
 };
 
-mod parser;
+#[cfg(feature = "tui")]
+mod browse;
 
-use parser::*;
+use mp3info::{
+    ape,
+    cache::{self, Cache, Fields},
+    chap, diff, downgrade, dupes,
+    encoder::{self, leading_tag_size},
+    etco,
+    fieldmap::FieldMap,
+    hexdump, id3v1, image, json,
+    merge::{self, MergePolicy},
+    mojibake, mpeg,
+    parser::*,
+    read_all_tags, read_file_with,
+    replaygain::{self, db_to_linear},
+    sort::{self, SortField},
+    sylt, template,
+    walk::{expand_globs, parse_duration, scan, ScanSummary, Walk},
+    AppError, ErrorKind,
+};
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  success
+  1  any other error
+  2  file not found, or invalid arguments
+  3  not an ID3 file
+  4  the tag couldn't be decoded
+  5  the requested content is missing, e.g. no lyrics or picture";
 
-#[derive(Debug)]
-pub struct AppError {
-    details: String,
+#[derive(Parser)]
+#[command(version, after_help = EXIT_CODES)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+    /// Decode ISO-8859-1 text as Windows-1252 (smart quotes, dashes, etc.)
+    #[arg(long, global = true)]
+    cp1252: bool,
+    /// Apply Unicode NFC normalization to all decoded text
+    #[arg(long, global = true)]
+    normalize: bool,
+    /// Skip frames that can't be decoded instead of failing, resuming at the next frame
+    #[arg(long, global = true)]
+    recover: bool,
+    /// Where the frames of a tag end and its padding begins
+    #[arg(long, global = true, value_enum, default_value_t = PaddingPolicy::Strict)]
+    padding: PaddingPolicy,
+    /// Print warnings about problems worked around while parsing
+    #[arg(long, short = 'v', global = true)]
+    verbose: bool,
+    /// Only show lyrics and comments in this ISO-639-2 language, e.g. "eng"
+    #[arg(long, global = true)]
+    lang: Option<String>,
+    /// Read info fields from other frames, given as a TOML file of `field = "FRAME"` lines
+    #[arg(long, global = true, value_name = "FILE")]
+    map: Option<PathBuf>,
+    /// Read an info field from another frame, e.g. "artist=TPE2". Applied after --map
+    #[arg(long = "field", global = true, value_name = "FIELD=FRAME")]
+    mappings: Vec<String>,
+    /// Print how long every frame took to decode, and the whole file, on stderr
+    #[arg(long, global = true, hide = true)]
+    profile: bool,
+    /// Don't read the image data of attached pictures, which `info` never does. Not for the
+    /// commands that extract pictures or write tags back.
+    #[arg(long, global = true)]
+    no_art: bool,
+    /// Keep the trailing nulls and whitespace of text values
+    #[arg(long, global = true)]
+    no_trim: bool,
+    /// Only go through files modified within this long when scanning directories, e.g. "7d"
+    #[arg(long, global = true, value_name = "DURATION", value_parser = parse_duration)]
+    modified_within: Option<Duration>,
 }
 
-impl AppError {
-    pub fn new(msg: &str) -> Box<Self> {
-        Box::new(AppError {
-            details: msg.into(),
-        })
+/// Print the time every frame took to decode, then the time taken to read the whole tag
+fn print_profile(
+    out: &mut impl Write,
+    path: &Path,
+    tag: &Tag,
+    elapsed: Duration,
+) -> io::Result<()> {
+    for (frame, time) in tag.frames.iter().zip(&tag.timings) {
+        writeln!(
+            out,
+            "profile: {}: {} {:?}",
+            path.display(),
+            frame.id(),
+            time
+        )?;
     }
+    writeln!(
+        out,
+        "profile: {}: {} frames read in {:?}",
+        path.display(),
+        tag.frames.len(),
+        elapsed
+    )
+}
+
+/// Reads tags the way the command line asked for
+struct TagReader {
+    options: ParseOptions,
+    verbose: bool,
+    lang: Option<String>,
+    fields: FieldMap,
+    modified_within: Option<Duration>,
 }
 
-impl Error for AppError {}
+impl TagReader {
+    /// Walk the MP3 files below a path, leaving out those modified too long ago
+    fn walk(&self, path: impl AsRef<Path>) -> Walk {
+        Walk::new(path).modified_within(self.modified_within)
+    }
+
+    fn read(&self, path: impl AsRef<Path>) -> Result<Tag, Box<dyn Error>> {
+        let tag = self.read_unreported(&path)?;
+        self.report(path.as_ref(), &tag);
+        Ok(tag)
+    }
+
+    /// Read a tag without reporting its warnings, for callers that report them their own way
+    fn read_unreported(&self, path: impl AsRef<Path>) -> Result<Tag, Box<dyn Error>> {
+        let started = Instant::now();
+        let tag = read_file_with(&path, &self.options)?;
+        if self.options.profile {
+            print_profile(
+                &mut io::stderr().lock(),
+                path.as_ref(),
+                &tag,
+                started.elapsed(),
+            )?;
+        }
+        Ok(tag)
+    }
+
+    /// Read the tag of an MP3 file inside a zip archive
+    fn read_zip_entry(&self, archive: &Path, entry: &str) -> Result<Tag, Box<dyn Error>> {
+        let tag = read_zip_entry(archive, entry, &self.options)?;
+        self.report(archive, &tag);
+        Ok(tag)
+    }
+
+    /// The options the fields `info` shows depend on, spelled out so that a cache filled
+    /// with others is left alone
+    fn cache_key(&self) -> String {
+        let FieldMap {
+            title,
+            artist,
+            album,
+            year,
+            band,
+        } = &self.fields;
+        let options = &self.options;
+        let padding = options.padding.to_possible_value().unwrap();
+        format!(
+            "lang={} fields={},{},{},{},{} cp1252={} normalize={} recover={} trim={} padding={}",
+            self.lang.as_deref().unwrap_or_default(),
+            title,
+            artist,
+            album,
+            year,
+            band,
+            options.cp1252,
+            options.normalize,
+            options.recover,
+            options.trim,
+            padding.get_name()
+        )
+    }
+
+    fn report(&self, path: &Path, tag: &Tag) {
+        // said whether verbose or not, as nothing else hints at why the output may be off
+        if let Some(warning) = experimental_warning(tag) {
+            eprintln!("warning: {}: {}", path.display(), warning);
+        }
+        self.report_verbose(path, tag);
+    }
 
-impl fmt::Display for AppError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.details)
+    /// Print the parse warnings of a tag when asked to be verbose
+    fn report_verbose(&self, path: &Path, tag: &Tag) {
+        if self.verbose {
+            for warning in &tag.warnings {
+                eprintln!("warning: {}: {}", path.display(), warning);
+            }
+        }
     }
 }
 
-#[derive(Parser)]
-#[command(version)]
-struct Cli {
-    #[command(subcommand)]
-    command: Commands,
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum InfoFormat {
+    #[default]
+    Text,
+    /// One JSON object per line and file, printed as soon as the file is read
+    Jsonl,
+}
+
+/// How commands that write an ID3v2 tag go about it
+#[derive(Args, Default)]
+struct WriteOptions {
+    /// Show what would change without modifying the file
+    #[arg(long)]
+    dry_run: bool,
+    /// Reorder frames into the recommended order, text frames first and pictures last
+    #[arg(long)]
+    canonical: bool,
+    /// Write an older tag version for players that can't read the newer one, dropping
+    /// frames it has no equivalent for
+    #[arg(long, value_enum)]
+    downgrade_to: Option<TagVersion>,
+    /// Re-read text that looks like UTF-8 stored as ISO-8859-1, e.g. "CafÃ©", as UTF-8
+    #[arg(long)]
+    fix_mojibake: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TagVersion {
+    #[value(name = "2.3")]
+    V2_3,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Display commonly used song metadata
     Info {
+        /// Files to read, wildcards such as "Music/**/*.mp3" are expanded
+        #[arg(required = true)]
+        paths: Vec<String>,
+        /// Display metadata of every MP3 file below the given directories
+        #[arg(long, short = 'r')]
+        recursive: bool,
+        /// Read this MP3 file from inside each of the given zip archives
+        #[arg(long, value_name = "ENTRY", conflicts_with = "recursive")]
+        inner: Option<String>,
+        /// Don't show progress or print the summary of a recursive scan
+        #[arg(long, short = 'q')]
+        quiet: bool,
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        view: InfoView,
+    },
+    /// Exit with an error, naming them on stderr, unless all the given fields are set
+    Require {
+        path: String,
+        /// Fields that must be present and non-empty, e.g. "title,artist,album,year"
+        #[arg(long, required = true, value_enum, value_delimiter = ',')]
+        fields: Vec<InfoField>,
+    },
+    /// Browse the tags of every MP3 file below the given directories in a terminal UI
+    Browse {
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+    /// List every frame of the tag
+    List {
         path: String,
+        /// Only list frames of this kind
+        #[arg(long, short = 'k', value_enum)]
+        kind: Option<FrameKind>,
+        /// Show the byte offset of every frame from the start of the file
+        #[arg(long)]
+        offsets: bool,
+        /// Show the size every frame declares next to the bytes decoding it took up
+        #[arg(long)]
+        verify_sizes: bool,
+        /// List the frames of every tag found anywhere in the file, as in concatenated streams
+        #[arg(long)]
+        all_tags: bool,
     },
+    /// Show the event timing codes (intro, verse, etc.) of a song
+    Events { path: String },
+    /// Show the seeking aids of a tag: the offset to the next tag and the seek point index
+    Seek { path: String },
+    /// Print a CUE sheet with a track for every chapter of a long mix
+    Cuesheet { path: String },
     /// View song lyrics
     Lyrics {
         path: String,
+        /// Save the lyrics to a file instead, as LRC when synchronised lyrics are available
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
     },
     /// Emit picture as binary data
     Picture {
         path: String,
-        #[arg(long, short = 't', default_value_t = PictureType::CoverFront, value_enum)]
-        picture_type: PictureType,
+        /// Picture type to extract [default: $MP3INFO_DEFAULT_PICTURE, or cover-front]
+        #[arg(long, short = 't', value_enum)]
+        picture_type: Option<PictureType>,
         #[arg(short = 'l', long, default_value = "false")]
         list: bool,
+        /// Download pictures that link to an external image instead of embedding it
+        #[arg(long)]
+        fetch: bool,
+        /// Print the picture as a data: URI instead of emitting binary data
+        #[arg(long)]
+        data_uri: bool,
+        /// Output format of --list
+        #[arg(long, default_value_t = OutputFormat::Text, value_enum)]
+        format: OutputFormat,
+        /// Save pictures into this directory instead of emitting them on stdout
+        #[arg(long, short = 'o')]
+        output_dir: Option<PathBuf>,
+        /// File name for saved pictures, with {artist}, {album}, {title}, {year},
+        /// {type}, {stem} (source file name) and {ext} placeholders [default: {stem}.{ext},
+        /// or {stem}-{type}.{ext} with --all]
+        #[arg(long)]
+        name_template: Option<String>,
+        /// Export pictures from every MP3 file below the given directory
+        #[arg(long, short = 'r', requires = "output_dir")]
+        recursive: bool,
+        /// Save every embedded picture into --output-dir rather than one type
+        #[arg(long, requires = "output_dir")]
+        all: bool,
+        /// Bundle every embedded picture into this zip file, each named after its type
+        #[arg(long, value_name = "ZIP")]
+        archive: Option<PathBuf>,
+    },
+    /// Display the length of the audio stream
+    Duration {
+        path: String,
+        /// Count the audio frames of each bitrate, to tell how variable the bitrate is
+        #[arg(long)]
+        bitrate_histogram: bool,
+    },
+    /// Dump the whole tag as JSON, binary data base64 encoded
+    Dump { path: String },
+    /// Show the tag header
+    Header {
+        path: String,
+        /// Print the raw header bytes annotated with what they mean
+        #[arg(long)]
+        hex: bool,
+    },
+    /// Print the value of every frame with the given id, binary frames as a hex dump
+    Get { path: String, id: String },
+    /// Display ReplayGain volume normalization values
+    Replaygain { path: String },
+    /// Set the value of a text frame
+    Set {
+        path: String,
+        id: String,
+        value: String,
+        #[command(flatten)]
+        write: WriteOptions,
+    },
+    /// Strip unwanted frames from a tag
+    Clean {
+        path: String,
+        /// Frames to remove, e.g. "COMM,PRIV,GEOB,TXXX"
+        #[arg(
+            long,
+            value_delimiter = ',',
+            required_unless_present = "keep",
+            conflicts_with = "keep"
+        )]
+        remove: Vec<String>,
+        /// Frames to keep, removing all others
+        #[arg(long, value_delimiter = ',')]
+        keep: Vec<String>,
+        #[command(flatten)]
+        write: WriteOptions,
+    },
+    /// Move files to a path made from their tags, relative to the given directory
+    Rename {
+        /// A file, or with --recursive a directory to rename every MP3 file below
+        path: PathBuf,
+        /// Target path with {artist}, {album}, {title}, {year} and {track} placeholders,
+        /// e.g. "{artist}/{album}/{track:02} {title}.mp3". Directories are created as needed.
+        #[arg(long)]
+        template: String,
+        /// Rename every MP3 file below the given directory
+        #[arg(long, short = 'r')]
+        recursive: bool,
+        /// Show what would be renamed without moving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show how the tag of one file differs from another's, frame by frame
+    Diff { old: String, new: String },
+    /// Find files holding the same audio, even when they're tagged differently
+    Dupes {
+        /// Files, or directories to check every MP3 file below
+        #[arg(required = true)]
+        paths: Vec<String>,
     },
+    /// Report files whose ID3v1 and ID3v2 tags disagree on the title, artist or album
+    Lint {
+        /// Files, or directories to check every MP3 file below
+        #[arg(required = true)]
+        paths: Vec<String>,
+        /// Also report text that looks like UTF-8 stored as ISO-8859-1, e.g. "CafÃ©"
+        #[arg(long)]
+        detect_mojibake: bool,
+    },
+    /// Merge the frames of another file's tag into the tag of a file
+    Merge {
+        base: String,
+        /// File to take the frames from
+        #[arg(long)]
+        from: String,
+        /// Whose frame to keep when both tags have one
+        #[arg(long, default_value_t = MergePolicy::Base, value_enum)]
+        prefer: MergePolicy,
+        #[command(flatten)]
+        write: WriteOptions,
+    },
+    /// Set the tag size in the header to cover the frames and padding the tag actually holds
+    FixSize {
+        path: String,
+        /// Show the sizes without modifying the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write an ID3v1 tag derived from the ID3v2 tag, for players that only read ID3v1
+    WriteV1 {
+        path: String,
+        /// Show what would change without modifying the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Replace the tag of a file with the tag of another, keeping its audio
+    CopyTags {
+        source: String,
+        dest: String,
+        #[command(flatten)]
+        write: WriteOptions,
+    },
+}
+
+impl Commands {
+    /// Whether the command hands on the image data of pictures, by extracting them or by
+    /// writing the tag back, so it can't do without it
+    fn needs_picture_data(&self) -> bool {
+        matches!(
+            self,
+            Commands::Picture { .. }
+                | Commands::Set { .. }
+                | Commands::Merge { .. }
+                | Commands::Clean { .. }
+                | Commands::CopyTags { .. }
+        )
+    }
+
+    /// Expand the wildcards of the files the command reads, for shells that leave them
+    /// alone. Commands taking one file need a pattern matching just one.
+    fn expand_globs(&mut self) -> Result<(), Box<dyn Error>> {
+        match self {
+            Commands::Info { paths, .. }
+            | Commands::Browse { paths }
+            | Commands::Dupes { paths }
+            | Commands::Lint { paths, .. } => {
+                *paths = expand_globs(paths)?
+                    .into_iter()
+                    .map(path_string)
+                    .collect::<Result<_, _>>()?;
+            }
+            Commands::Require { path, .. }
+            | Commands::List { path, .. }
+            | Commands::Events { path }
+            | Commands::Seek { path }
+            | Commands::Cuesheet { path }
+            | Commands::Lyrics { path, .. }
+            | Commands::Picture { path, .. }
+            | Commands::Duration { path, .. }
+            | Commands::Dump { path }
+            | Commands::Header { path, .. }
+            | Commands::Get { path, .. }
+            | Commands::Replaygain { path }
+            | Commands::Set { path, .. }
+            | Commands::Clean { path, .. }
+            | Commands::FixSize { path, .. }
+            | Commands::WriteV1 { path, .. } => *path = expand_glob(path)?,
+            Commands::Rename { path, .. } => {
+                if let Some(arg) = path.to_str() {
+                    *path = expand_glob(arg)?.into();
+                }
+            }
+            Commands::Diff { old: a, new: b }
+            | Commands::Merge {
+                base: a, from: b, ..
+            }
+            | Commands::CopyTags {
+                source: a, dest: b, ..
+            } => {
+                *a = expand_glob(a)?;
+                *b = expand_glob(b)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Expand the wildcards of an argument that names a single file
+fn expand_glob(arg: &str) -> Result<String, Box<dyn Error>> {
+    let mut paths = expand_globs(&[arg])?;
+    if paths.len() > 1 {
+        return Err(AppError::new(&format!(
+            "'{}' matches {} files, but only one can be given here",
+            arg,
+            paths.len()
+        )));
+    }
+    path_string(paths.remove(0))
+}
+
+fn path_string(path: PathBuf) -> Result<String, Box<dyn Error>> {
+    path.into_os_string()
+        .into_string()
+        .map_err(|x| AppError::new(&format!("'{}' isn't valid UTF-8", x.to_string_lossy())).into())
+}
+
+/// The picture type `picture` extracts when none is given, from the value of
+/// MP3INFO_DEFAULT_PICTURE if set
+fn default_picture_type(value: Option<&OsStr>) -> Result<PictureType, Box<dyn Error>> {
+    let Some(value) = value else {
+        return Ok(PictureType::CoverFront);
+    };
+    value
+        .to_str()
+        .and_then(|x| <PictureType as ValueEnum>::from_str(x, true).ok())
+        .ok_or_else(|| {
+            AppError::new(&format!(
+                "MP3INFO_DEFAULT_PICTURE is set to '{}', which isn't a picture type",
+                value.to_string_lossy()
+            ))
+            .into()
+        })
 }
 
-fn read_file(path: &str) -> Result<Tag, Box<dyn Error>> {
-    let mut file = fs::File::open(path)?;
+/// Save the picture of the given type into `output_dir`, or every embedded picture when
+/// no type is given
+fn export_picture(
+    path: &Path,
+    reader: &TagReader,
+    picture_type: Option<PictureType>,
+    output_dir: &Path,
+    name_template: &str,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let tag = reader.read(path)?;
 
-    let tag_headers = {
-        let mut tag_headers = [0; 10];
-        file.read_exact(&mut tag_headers)?;
-        tag_headers
+    let pictures = match picture_type {
+        Some(picture_type) => {
+            let type_name = picture_type.name();
+            match tag.picture(picture_type) {
+                Some(pic) if pic.is_empty_picture() => {
+                    return Err(AppError::new(&format!(
+                        "Attached picture type '{type_name}' present but empty"
+                    )))
+                }
+                Some(pic) => vec![pic],
+                None => {
+                    return Err(AppError::with_kind(
+                        ErrorKind::Missing,
+                        &format!("Attached picture type '{type_name}' not available"),
+                    ))
+                }
+            }
+        }
+        None => {
+            let pictures: Vec<_> = tag
+                .pictures()
+                .into_iter()
+                .filter(|x| !x.is_empty_picture() && x.linked_picture_url().is_none())
+                .collect();
+            if pictures.is_empty() {
+                return Err(AppError::with_kind(
+                    ErrorKind::Missing,
+                    "No pictures to save",
+                ));
+            }
+            pictures
+        }
     };
 
-    let header = decode_header(tag_headers)?;
+    let mut saved: Vec<PathBuf> = Vec::new();
+    for pic in pictures {
+        let Frame::Apic {
+            data,
+            mime_type,
+            picture_type,
+            ..
+        } = pic
+        else {
+            continue;
+        };
+        let name = template::render(
+            name_template,
+            |field| {
+                let value = match field {
+                    "ext" => Some(mime_extension(mime_type).to_string()),
+                    "type" => Some(picture_type.name().to_string()),
+                    "stem" => path.file_stem().map(|x| x.to_string_lossy().into()),
+                    _ => template::tag_field(&tag, field),
+                };
+                value.map(|x| template::sanitize_filename(&x))
+            },
+            "Unknown",
+        );
+
+        // several pictures may share a name, number the ones after the first as the
+        // archive does
+        let mut dest = output_dir.join(&name);
+        let mut n = 1;
+        while saved.contains(&dest) {
+            n += 1;
+            let name = Path::new(&name);
+            let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+            dest = output_dir.join(match name.extension() {
+                Some(ext) => format!("{}-{}.{}", stem, n, ext.to_string_lossy()),
+                None => format!("{}-{}", stem, n),
+            });
+        }
+        fs::write(&dest, data)?;
+        saved.push(dest);
+    }
+    Ok(saved)
+}
+
+#[cfg(feature = "fetch")]
+fn fetch_url(url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut data = Vec::new();
+    ureq::get(url)
+        .call()?
+        .into_reader()
+        .read_to_end(&mut data)?;
+    Ok(data)
+}
 
-    if header.extended {
-        let extended_header_size = consume_bytes(&mut file, 4)?;
-        let extended_header_size = byte_int(&extended_header_size);
+#[cfg(not(feature = "fetch"))]
+fn fetch_url(_url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    Err(AppError::new(
+        "Fetching linked pictures requires building with the `fetch` feature",
+    ))
+}
 
-        let mut extended_header_data = vec![0; extended_header_size as usize - 4]; // minus 4 bytes for the size field
-        file.read_exact(&mut extended_header_data)?; // consume extended header data
+#[cfg(feature = "zip")]
+fn archive_pictures(tag: &Tag, dest: &Path) -> Result<usize, Box<dyn Error>> {
+    if tag.pictures().is_empty() {
+        return Err(AppError::with_kind(
+            ErrorKind::Missing,
+            "No pictures to archive",
+        ));
     }
+    Ok(mp3info::archive::write_pictures(
+        tag,
+        fs::File::create(dest)?,
+    )?)
+}
 
-    let tag_frames = {
-        let mut tag_frames = vec![0; header.size as usize];
-        file.read_exact(&mut tag_frames)?;
-        tag_frames
-    };
+#[cfg(feature = "zip")]
+fn read_zip_entry(
+    archive: &Path,
+    entry: &str,
+    options: &ParseOptions,
+) -> Result<Tag, Box<dyn Error>> {
+    mp3info::archive::read_entry_tag(archive, entry, options)
+}
+
+#[cfg(not(feature = "zip"))]
+fn read_zip_entry(
+    _archive: &Path,
+    _entry: &str,
+    _options: &ParseOptions,
+) -> Result<Tag, Box<dyn Error>> {
+    Err(AppError::new(
+        "Reading files inside zip archives requires building with the `zip` feature",
+    ))
+}
+
+#[cfg(not(feature = "zip"))]
+fn archive_pictures(_tag: &Tag, _dest: &Path) -> Result<usize, Box<dyn Error>> {
+    Err(AppError::new(
+        "Archiving pictures requires building with the `zip` feature",
+    ))
+}
 
-    let frames = decode_frames(tag_frames, header.version == 4)?;
+#[cfg(feature = "tui")]
+fn browse(walks: Vec<Walk>, options: &ParseOptions) -> Result<(), Box<dyn Error>> {
+    browse::browse(walks, options)
+}
 
-    Ok(Tag { header, frames })
+#[cfg(not(feature = "tui"))]
+fn browse(_walks: Vec<Walk>, _options: &ParseOptions) -> Result<(), Box<dyn Error>> {
+    Err(AppError::new(
+        "Browsing requires building with the `tui` feature",
+    ))
 }
 
 fn find_frame_by_id<'a>(f: &'a [Frame], id: &str) -> Option<&'a Frame> {
-    for frame in f {
-        match frame {
-            Frame::Other { id: tid, .. } => {
-                if id == tid {
-                    return Some(frame);
-                }
-            }
-            _ => continue,
+    f.iter().find(|x| x.id() == id)
+}
+
+/// Field shown by `info`
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum InfoField {
+    Title,
+    Artist,
+    Album,
+    Year,
+    Band,
+    Track,
+    Comment,
+    Bpm,
+    Key,
+    Encoder,
+    EncodedBy,
+    PlayCount,
+}
+
+impl InfoField {
+    fn label(self) -> &'static str {
+        match self {
+            InfoField::Title => "Title",
+            InfoField::Artist => "Lead performer",
+            InfoField::Album => "Album",
+            InfoField::Year => "Year",
+            InfoField::Band => "Band",
+            InfoField::Track => "Track",
+            InfoField::Comment => "Comment",
+            InfoField::Bpm => "BPM",
+            InfoField::Key => "Key",
+            InfoField::Encoder => "Encoder",
+            InfoField::EncodedBy => "Encoded by",
+            InfoField::PlayCount => "Play count",
+        }
+    }
+
+    fn value(self, tag: &Tag, lang: Option<&str>, fields: &FieldMap) -> Option<String> {
+        let frame = |id: &str| tag.frames_by_id(id).next().map(|x| x.to_string());
+        match self {
+            InfoField::Title => frame(&fields.title),
+            InfoField::Artist => frame(&fields.artist),
+            InfoField::Album => frame(&fields.album),
+            InfoField::Year => frame(&fields.year),
+            InfoField::Band => frame(&fields.band),
+            InfoField::Track => match (tag.track_number(), tag.track_total()) {
+                (Some(number), Some(total)) => Some(format!("{}/{}", number, total)),
+                (number, _) => number.map(|x| x.to_string()),
+            },
+            InfoField::Comment => tag.comment(lang).map(String::from),
+            InfoField::Bpm => tag.bpm().map(|x| x.to_string()),
+            InfoField::Key => tag.key().map(String::from),
+            InfoField::Encoder => tag.encoder_settings().map(String::from),
+            InfoField::EncodedBy => tag.encoded_by().map(String::from),
+            InfoField::PlayCount => tag.play_count().map(|x| x.to_string()),
         }
     }
-    None
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let cli = Cli::parse();
-    match cli.command {
-        Commands::Info { path } => {
-            let tag = read_file(&path)?;
+/// Which files of a recursive scan are read, and in what order
+#[derive(Args, Default)]
+struct ScanArgs {
+    /// Stop a recursive scan after this many files
+    #[arg(long, requires = "recursive")]
+    limit: Option<usize>,
+    /// Descend at most this many directories below the given ones, 0 for only the files
+    /// directly inside them
+    #[arg(long, requires = "recursive")]
+    max_depth: Option<usize>,
+    /// Sort the files of a recursive scan by these fields, e.g. "artist,album,track".
+    /// Every file is read before anything is printed.
+    #[arg(long, requires = "recursive", value_enum, value_delimiter = ',')]
+    sort: Vec<SortField>,
+    /// Descend into symlinked directories, which a recursive scan skips by default
+    #[arg(long, requires = "recursive")]
+    follow_symlinks: bool,
+    /// Keep the fields of the files scanned in this file, and only read the files that
+    /// changed since on later scans. Only works with the text format. Warnings are only
+    /// reported for the files that are read.
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "recursive",
+        conflicts_with = "sort"
+    )]
+    cache: Option<PathBuf>,
+}
 
-            let title = find_frame_by_id(&tag.frames, "TIT2");
-            let lead_artist = find_frame_by_id(&tag.frames, "TPE1");
-            let album = find_frame_by_id(&tag.frames, "TALB");
-            let band = find_frame_by_id(&tag.frames, "TPE2");
-            let year = find_frame_by_id(&tag.frames, "TYER");
-            let comment = find_frame_by_id(&tag.frames, "COMM");
+impl ScanArgs {
+    fn walk(&self, reader: &TagReader, path: impl AsRef<Path>) -> Walk {
+        reader
+            .walk(path)
+            .max_depth(self.max_depth)
+            .follow_symlinks(self.follow_symlinks)
+    }
+}
 
-            if let Some(x) = title {
-                println!("Title: {}", x);
-            }
-            if let Some(x) = lead_artist {
-                println!("Lead performer: {}", x);
-            }
-            if let Some(x) = album {
-                println!("Album: {}", x);
+/// The fields that are missing from the tag, or only hold whitespace
+fn missing_fields(tag: &Tag, reader: &TagReader, fields: &[InfoField]) -> Vec<InfoField> {
+    fields
+        .iter()
+        .copied()
+        .filter(|x| {
+            x.value(tag, reader.lang.as_deref(), &reader.fields)
+                .is_none_or(|x| {
+                    x.trim_matches(|c: char| c == '\0' || c.is_whitespace())
+                        .is_empty()
+                })
+        })
+        .collect()
+}
+
+/// How `info` presents a tag
+#[derive(Args, Default)]
+struct InfoView {
+    /// Output format
+    #[arg(long, default_value_t = InfoFormat::Text, value_enum)]
+    format: InfoFormat,
+    /// Only show these fields, in this order, e.g. "title,artist"
+    #[arg(long = "fields", value_enum, value_delimiter = ',')]
+    select: Vec<InfoField>,
+    /// Print only the values, without their labels
+    #[arg(long)]
+    bare: bool,
+    /// Print one line per file from this template instead, naming fields as --fields does,
+    /// e.g. "{artist} - {title} ({year})"
+    #[arg(long, visible_alias = "output-template", conflicts_with_all = ["select", "bare"])]
+    template: Option<String>,
+    /// What fields the tag lacks render as in --template
+    #[arg(long, default_value = "", requires = "template")]
+    missing: String,
+}
+
+fn print_info(
+    out: &mut impl Write,
+    tag: &Tag,
+    lang: Option<&str>,
+    fields: &FieldMap,
+    view: &InfoView,
+) -> io::Result<()> {
+    print_fields(out, view, |x| x.value(tag, lang, fields))
+}
+
+/// Every field that is set, by the name of its `--fields` value, for the cache to keep
+fn info_fields(tag: &Tag, reader: &TagReader) -> Fields {
+    InfoField::value_variants()
+        .iter()
+        .filter_map(|field| {
+            let value = field.value(tag, reader.lang.as_deref(), &reader.fields)?;
+            Some((field.to_possible_value()?.get_name().to_string(), value))
+        })
+        .collect()
+}
+
+/// Print the fields `view` selects, as `value` gives them
+fn print_fields(
+    out: &mut impl Write,
+    view: &InfoView,
+    value: impl Fn(InfoField) -> Option<String>,
+) -> io::Result<()> {
+    if let Some(template) = &view.template {
+        let line = template::render(
+            template,
+            |name| value(InfoField::from_str(name, true).ok()?),
+            &view.missing,
+        );
+        return writeln!(out, "{}", line);
+    }
+
+    let all = InfoField::value_variants();
+    let selected = if view.select.is_empty() {
+        all
+    } else {
+        &view.select
+    };
+
+    for &field in selected {
+        if let Some(x) = value(field) {
+            if view.bare {
+                writeln!(out, "{}", x)?;
+            } else {
+                writeln!(out, "{}: {}", field.label(), x)?;
             }
-            if let Some(x) = year {
-                println!("Year: {}", x);
+        }
+    }
+    Ok(())
+}
+
+/// Print the info of one of several files, labelled with its path. Errors are reported
+/// on stderr so the remaining files can still be processed.
+fn print_info_block(
+    out: &mut impl Write,
+    reader: &TagReader,
+    path: &Path,
+    tag: Result<Tag, Box<dyn Error>>,
+    view: &InfoView,
+) -> io::Result<bool> {
+    if view.format == InfoFormat::Jsonl {
+        let ok = tag.is_ok();
+        let path_str = path.display().to_string();
+        let line = match tag {
+            Ok(tag) => {
+                reader.report(path, &tag);
+                json::info(&path_str, &tag, reader.lang.as_deref(), &reader.fields)
             }
-            if let Some(x) = band {
-                println!("Band: {}", x);
+            Err(e) => serde_json::json!({ "path": path_str, "error": e.to_string() }),
+        };
+        writeln!(out, "{}", line)?;
+        return Ok(ok);
+    }
+
+    match tag {
+        // a template makes a line of its own for every file
+        Ok(tag) if view.template.is_some() => {
+            reader.report(path, &tag);
+            print_info(out, &tag, reader.lang.as_deref(), &reader.fields, view)?;
+            Ok(true)
+        }
+        Ok(tag) => {
+            reader.report(path, &tag);
+            writeln!(out, "{}", path.display())?;
+            print_info(out, &tag, reader.lang.as_deref(), &reader.fields, view)?;
+            writeln!(out)?;
+            Ok(true)
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            Ok(false)
+        }
+    }
+}
+
+/// Print the cached fields of one of several files, like [`print_info_block`] does
+fn print_cached_block(
+    out: &mut impl Write,
+    path: &Path,
+    fields: Result<Fields, Box<dyn Error>>,
+    view: &InfoView,
+) -> io::Result<()> {
+    match fields {
+        Ok(fields) => {
+            let heading = view.template.is_none();
+            if heading {
+                writeln!(out, "{}", path.display())?;
             }
-            if let Some(x) = comment {
-                println!("Comment: {}", x);
+            print_fields(out, view, |field| {
+                let name = field.to_possible_value()?;
+                fields
+                    .iter()
+                    .find(|(x, _)| x == name.get_name())
+                    .map(|(_, value)| value.clone())
+            })?;
+            if heading {
+                writeln!(out)?;
             }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            Ok(())
         }
-        Commands::Lyrics { path } => {
-            let tag = read_file(&path)?;
-            let frames = tag.frames;
+    }
+}
 
-            if !frames.iter().any(|x| matches!(&x, Frame::Uslt { .. })) {
-                return Err(AppError::new("Lyrics not available").into());
-            }
+/// A length in bytes, in kilobytes once it's that large
+fn format_length(bytes: usize) -> String {
+    match bytes {
+        0..1024 => format!("{}B", bytes),
+        _ => format!("{}KB", (bytes + 512) / 1024),
+    }
+}
 
-            for frame in frames {
-                match frame {
-                    Frame::Uslt {
-                        text,
-                        language,
-                        description,
-                    } => {
-                        println!("Language: {}", language);
-                        println!("Description: {}", description.trim());
-                        println!("=== \n{}", text);
-                    }
-                    _ => continue,
-                }
-                println!();
-            }
+/// Names of the flags set in a tag header
+fn header_flags(header: &Header) -> String {
+    let flags: Vec<&str> = [
+        (header.unsynchronisation, "unsynchronisation"),
+        (header.extended, "extended header"),
+        (header.experimental, "experimental"),
+        (header.footer_present, "footer"),
+    ]
+    .into_iter()
+    .filter_map(|(set, name)| set.then_some(name))
+    .collect();
+
+    match flags.is_empty() {
+        true => "none".into(),
+        false => flags.join(", "),
+    }
+}
+
+fn synchsafe(bytes: [u8; 4]) -> u32 {
+    bytes.iter().fold(0, |acc, x| acc << 7 | (x & 0x7F) as u32)
+}
+
+/// Print the header bytes (and those of the extended header, if any) next to what they mean
+fn annotate_header(
+    out: &mut impl Write,
+    bytes: &[u8; 10],
+    extended: Option<&[u8]>,
+) -> Result<(), Box<dyn Error>> {
+    let header = decode_header(*bytes)?;
+    let size = [bytes[6], bytes[7], bytes[8], bytes[9]];
+
+    writeln!(
+        out,
+        "{:<12} magic \"{}\"",
+        hexdump::hex_bytes(&bytes[0..3]),
+        String::from_utf8_lossy(&bytes[0..3])
+    )?;
+    writeln!(
+        out,
+        "{:<12} version 2.{}",
+        hexdump::hex_bytes(&bytes[3..4]),
+        header.version
+    )?;
+    writeln!(
+        out,
+        "{:<12} revision {}",
+        hexdump::hex_bytes(&bytes[4..5]),
+        header.revision
+    )?;
+    writeln!(
+        out,
+        "{:<12} flags: {}",
+        hexdump::hex_bytes(&bytes[5..6]),
+        header_flags(&header)
+    )?;
+    writeln!(
+        out,
+        "{:<12} size {} (raw {})",
+        hexdump::hex_bytes(&size),
+        synchsafe(size),
+        u32::from_be_bytes(size)
+    )?;
+
+    if let Some(extended) = extended {
+        let (size, data) = extended.split_at(4.min(extended.len()));
+        writeln!(out, "{:<12} extended header size", hexdump::hex_bytes(size))?;
+        for chunk in data.chunks(4) {
+            writeln!(
+                out,
+                "{:<12} extended header data",
+                hexdump::hex_bytes(chunk)
+            )?;
         }
-        Commands::Picture {
-            path,
-            picture_type,
-            list,
-        } => {
-            let tag = read_file(&path)?;
-            let mut frames_iter = tag.frames.iter();
+    }
 
-            if list {
-                let pics = frames_iter.filter_map(|x| match x {
-                    Frame::Apic {
-                        picture_type: ptype,
-                        ..
-                    } => Some(ptype),
-                    _ => None,
-                });
+    Ok(())
+}
 
-                for pic in pics {
-                    println!("{}", pic.to_possible_value().unwrap().get_name());
-                }
-                return Ok(());
-            }
+/// The USLT frames of a tag like `lyrics`, ordered by language so that files with lyrics in
+/// several languages print the same way whatever order the frames are stored in
+fn sorted_lyrics<'a>(tag: &'a Tag, lang: Option<&'a str>) -> Vec<&'a Frame> {
+    let mut frames: Vec<&Frame> = lyrics(tag, lang).collect();
+    frames.sort_by_key(|x| match x {
+        Frame::Uslt { language, .. } => language.to_lowercase(),
+        _ => String::new(),
+    });
+    frames
+}
 
-            let pic = frames_iter.find(|x| {
-                matches!(x, Frame::Apic {
-                    picture_type: ptype,
-                    ..
-                } if &picture_type == ptype)
-            });
+/// Distinct languages of lyrics frames, in order
+fn lyrics_languages(frames: &[&Frame]) -> Vec<String> {
+    let mut languages: Vec<String> = frames
+        .iter()
+        .filter_map(|x| match x {
+            Frame::Uslt { language, .. } => Some(language.to_lowercase()),
+            _ => None,
+        })
+        .collect();
+    languages.dedup();
+    languages
+}
 
-            match pic {
-                Some(Frame::Apic { data, .. }) => {
-                    eprintln!("Picture length: {}", data.len());
-                    let mut handle = std::io::stdout().lock();
-                    if atty::is(atty::Stream::Stdout) {
-                        println!(
-                            "Binary output not displayed! Pipe stdout into a file to save it."
-                        );
-                    } else {
-                        handle.write_all(data)?;
-                    }
-                    handle.flush()?
-                }
-                _ => {
-                    return Err(AppError::new(&format!(
-                        "Attached picture type '{}' not available",
-                        picture_type.to_possible_value().unwrap().get_name()
-                    ))
-                    .into());
-                }
+fn print_lyrics(out: &mut impl Write, frames: &[&Frame]) -> io::Result<()> {
+    for frame in frames {
+        if let Frame::Uslt {
+            text,
+            language,
+            description,
+        } = frame
+        {
+            writeln!(out, "Language: {}", language)?;
+            writeln!(out, "Description: {}", description)?;
+            writeln!(out, "=== \n{}", text)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// The USLT frames of a tag, only those in the given language if any
+fn lyrics<'a>(tag: &'a Tag, lang: Option<&'a str>) -> impl Iterator<Item = &'a Frame> {
+    tag.frames_by_id("USLT")
+        .filter(move |x| lang.is_none_or(|lang| x.in_language(lang)))
+}
+
+/// Print every frame with the given id, returning how many there were
+fn print_frames(out: &mut impl Write, tag: &Tag, id: &str, color: bool) -> io::Result<usize> {
+    let mut count = 0;
+    for frame in tag.frames_by_id(id) {
+        match frame {
+            Frame::Other {
+                content: Content::Binary(b),
+                ..
+            } if color => write!(out, "{}", hexdump::hexdump_colored(b, 0))?,
+            Frame::Other {
+                content: Content::Binary(b),
+                ..
+            } => write!(out, "{}", hexdump::hexdump(b, 0))?,
+            _ => writeln!(out, "{}", frame)?,
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Experimental tags may be laid out in ways of their own, which the parser can't know of
+fn experimental_warning(tag: &Tag) -> Option<&'static str> {
+    tag.header
+        .experimental
+        .then_some("experimental tag, parsing may be unreliable")
+}
+
+/// Problems worth re-tagging a file for. Files without both an ID3v1 and an ID3v2 tag
+/// have nothing to compare, and an APE tag next to an ID3v2 tag only adds to the clutter.
+fn lint(reader: &TagReader, path: &Path, detect_mojibake: bool) -> Vec<String> {
+    let v1 = fs::File::open(path)
+        .and_then(|mut x| id3v1::read_id3v1(&mut x))
+        .ok()
+        .flatten();
+
+    let ape = fs::File::open(path)
+        .and_then(|mut x| ape::read_ape(&mut x))
+        .ok()
+        .flatten();
+
+    // the experimental flag is one of the problems, rather than a warning on stderr
+    let v2 = reader.read_unreported(path);
+    if let Ok(v2) = &v2 {
+        reader.report_verbose(path, v2);
+    }
+    let mut problems = match (v1, &v2) {
+        (Some(v1), Ok(v2)) => id3v1::disagreements(&v1, v2),
+        _ => Vec::new(),
+    };
+    if let (Some(ape), Ok(_)) = (ape, &v2) {
+        problems.push(format!(
+            "redundant APE tag with {} items alongside the ID3v2 tag",
+            ape.items.len()
+        ));
+    }
+    if let Some(warning) = v2.as_ref().ok().and_then(experimental_warning) {
+        problems.push(warning.into());
+    }
+    if let (true, Ok(v2)) = (detect_mojibake, &v2) {
+        problems.extend(mojibake::find(v2).into_iter().map(|x| {
+            format!(
+                "{} looks like mojibake, \"{}\" is likely \"{}\" (fix with --fix-mojibake)",
+                x.frame_id, x.text, x.fixed
+            )
+        }));
+    }
+    problems
+}
+
+/// Where a file goes according to the rename template, or the fields it lacks for it
+fn rename_target(tag: &Tag, template: &str, root: &Path) -> Result<PathBuf, Vec<String>> {
+    let missing: Vec<String> = template::fields(template)
+        .into_iter()
+        .filter(|x| template::tag_field(tag, x).is_none())
+        .map(String::from)
+        .collect();
+    if !missing.is_empty() {
+        return Err(missing);
+    }
+
+    // slashes in the template separate directories, those in values don't
+    let name = template::render(
+        template,
+        |field| template::tag_field(tag, field).map(|x| template::sanitize_filename(&x)),
+        "",
+    );
+    Ok(root.join(name))
+}
+
+/// Move every file to the path its tag gives it, printing each move. Files that can't be
+/// read, lack a field of the template or would overwrite another file are skipped with a
+/// warning.
+fn rename_files(
+    out: &mut impl Write,
+    reader: &TagReader,
+    files: &[PathBuf],
+    root: &Path,
+    template: &str,
+    dry_run: bool,
+) -> io::Result<()> {
+    for file in files {
+        let target = match reader.read(file) {
+            Ok(tag) => rename_target(&tag, template, root),
+            Err(e) => {
+                eprintln!("{}: {}, skipped", file.display(), e);
+                continue;
+            }
+        };
+        let target = match target {
+            Ok(target) if target == *file => continue,
+            Ok(target) if target.exists() => {
+                eprintln!(
+                    "{}: {} already exists, skipped",
+                    file.display(),
+                    target.display()
+                );
+                continue;
             }
+            Ok(target) => target,
+            Err(missing) => {
+                eprintln!("{}: no {}, skipped", file.display(), missing.join(", "));
+                continue;
+            }
+        };
+
+        writeln!(out, "{} -> {}", file.display(), target.display())?;
+        if !dry_run {
+            if let Some(dir) = target.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::rename(file, &target)?;
         }
     }
+    Ok(())
+}
+
+/// Print every frame of the tag alongside its id
+fn list_frames(
+    out: &mut impl Write,
+    tag: &Tag,
+    kind: Option<FrameKind>,
+    offsets: bool,
+    verify_sizes: bool,
+) -> io::Result<()> {
+    for (i, frame) in tag.frames.iter().chain(&tag.trailer).enumerate() {
+        if kind.is_some_and(|kind| frame.kind() != kind) {
+            continue;
+        }
 
+        if let Some(offset) = tag.offsets.get(i).filter(|_| offsets) {
+            write!(out, "{:#08x} ", offset)?;
+        }
+        write!(out, "{}: {}", frame.id(), frame)?;
+        // frames merged in from other tags have no size of their own
+        if let Some(size) = tag.sizes.get(i).filter(|_| verify_sizes) {
+            write!(
+                out,
+                " [declared {}, decoded {} bytes{}]",
+                size.declared,
+                size.consumed,
+                if size.matches() { "" } else { ", MISMATCH" }
+            )?;
+        }
+        writeln!(out)?;
+    }
     Ok(())
 }
+
+/// Remove the frames listed in `remove`, or with `keep` all but those listed there.
+/// Returns how many frames were removed.
+fn prune_frames(tag: &mut Tag, remove: &[String], keep: &[String]) -> usize {
+    let before = tag.frames.len();
+    tag.frames.retain(|x| {
+        let listed = |ids: &[String]| ids.iter().any(|id| id.eq_ignore_ascii_case(x.id()));
+        if keep.is_empty() {
+            !listed(remove)
+        } else {
+            listed(keep)
+        }
+    });
+    before - tag.frames.len()
+}
+
+/// Write a tag over the tag of a file, or with `dry_run` only report what would change.
+/// Returns the size of the file with the new tag.
+fn save_tag(
+    reader: &TagReader,
+    path: &Path,
+    tag: &Tag,
+    options: &WriteOptions,
+) -> Result<u64, Box<dyn Error>> {
+    let mut tag = tag.clone();
+    // first, while the frames still line up with the encodings they were read with
+    if options.fix_mojibake {
+        mojibake::fix_tag(&mut tag);
+    }
+    if let Some(TagVersion::V2_3) = options.downgrade_to {
+        for warning in downgrade::downgrade_to_v23(&mut tag) {
+            eprintln!("warning: {}: {}", path.display(), warning);
+        }
+    }
+    if options.canonical {
+        encoder::reorder_frames(&mut tag.frames);
+    }
+
+    let contents = fs::read(path)?;
+    let rewritten = encoder::rewrite(&contents, &tag)?;
+
+    if options.dry_run {
+        let old = reader.read(path).ok();
+        eprintln!("Dry run, {} is not modified", path.display());
+        eprintln!("{}", encoder::summarize(old.as_ref(), &tag));
+        return Ok(rewritten.len() as u64);
+    }
+
+    fs::write(path, &rewritten)?;
+    Ok(rewritten.len() as u64)
+}
+
+/// Progress bar for recursive scans, hidden when it'd end up in a pipe or a file. The files
+/// are only counted for a bar that's drawn, as that walks the whole tree.
+fn progress_bar(total: impl FnOnce() -> usize, quiet: bool, stderr_is_tty: bool) -> ProgressBar {
+    if quiet || !stderr_is_tty {
+        return ProgressBar::hidden();
+    }
+
+    let bar = ProgressBar::new(total() as u64);
+    bar.set_style(ProgressStyle::with_template("{bar:40} {pos}/{len} files ({percent}%)").unwrap());
+    bar
+}
+
+/// Number of files a recursive scan of the paths will visit
+fn count_files(
+    reader: &TagReader,
+    paths: &[impl AsRef<Path>],
+    limit: Option<usize>,
+    scan_args: &ScanArgs,
+) -> usize {
+    paths
+        .iter()
+        .flat_map(|x| scan_args.walk(reader, x))
+        .filter(|x| x.is_ok())
+        .take(limit.unwrap_or(usize::MAX))
+        .count()
+}
+
+/// Print the info of every MP3 file below the paths, stopping after `limit` files. With
+/// sort fields, every file is read and sorted first, and only the printing stops early.
+fn print_info_recursive(
+    out: &mut impl Write,
+    reader: &TagReader,
+    paths: &[impl AsRef<Path>],
+    scan_args: &ScanArgs,
+    view: &InfoView,
+    progress: &ProgressBar,
+) -> io::Result<ScanSummary> {
+    let ScanArgs {
+        limit,
+        ref sort,
+        ref cache,
+        ..
+    } = *scan_args;
+    let walk = |path| scan_args.walk(reader, path);
+    let mut summary = ScanSummary::default();
+
+    if let Some(cache_path) = cache {
+        let mut cache = Cache::load(cache_path, &reader.cache_key())?;
+        for path in paths {
+            let remaining = limit.map(|x| x.saturating_sub(summary.scanned));
+            if remaining == Some(0) {
+                break;
+            }
+
+            let extract = |entry: &Path, tag: &Tag| {
+                reader.report(entry, tag);
+                info_fields(tag, reader)
+            };
+            summary += cache::scan_cached(
+                walk(path),
+                &reader.options,
+                &mut cache,
+                remaining,
+                extract,
+                |entry, fields| {
+                    progress.inc(1);
+                    progress.suspend(|| print_cached_block(out, entry, fields, view))
+                },
+            )?;
+        }
+        if reader.verbose {
+            eprintln!(
+                "{} of {} files read from the cache",
+                cache.hits, summary.scanned
+            );
+        }
+        // a limited scan stops before seeing every file
+        if limit.is_none() {
+            cache.prune();
+        }
+        cache.save(cache_path)?;
+        return Ok(summary);
+    }
+
+    if !sort.is_empty() {
+        let mut records = Vec::new();
+        for path in paths {
+            summary += scan(walk(path), &reader.options, None, |entry, tag| {
+                progress.inc(1);
+                records.push((entry.to_path_buf(), tag));
+                Ok(())
+            })?;
+        }
+
+        records.sort_by(|(_, a), (_, b)| sort::compare(a.as_ref().ok(), b.as_ref().ok(), sort));
+        for (entry, tag) in records.into_iter().take(limit.unwrap_or(usize::MAX)) {
+            progress.suspend(|| print_info_block(out, reader, &entry, tag, view))?;
+        }
+        return Ok(summary);
+    }
+
+    for path in paths {
+        let remaining = limit.map(|x| x.saturating_sub(summary.scanned));
+        if remaining == Some(0) {
+            break;
+        }
+
+        summary += scan(walk(path), &reader.options, remaining, |entry, tag| {
+            progress.inc(1);
+            progress.suspend(|| print_info_block(out, reader, entry, tag, view))?;
+            Ok(())
+        })?;
+    }
+    Ok(summary)
+}
+
+/// The exit code telling scripts what kind of error ended the program, as listed in
+/// [`EXIT_CODES`]
+fn exit_code(e: &(dyn Error + 'static)) -> u8 {
+    // errors converted with `?` may be boxed twice
+    let app_error = e
+        .downcast_ref::<AppError>()
+        .or_else(|| e.downcast_ref::<Box<AppError>>().map(|x| x.as_ref()));
+    if let Some(e) = app_error {
+        return match e.kind() {
+            ErrorKind::Other => 1,
+            ErrorKind::NotId3 => 3,
+            ErrorKind::Decode => 4,
+            ErrorKind::Missing => 5,
+        };
+    }
+
+    match e.downcast_ref::<io::Error>().map(|x| x.kind()) {
+        Some(io::ErrorKind::NotFound) => 2,
+        Some(io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof) => 4,
+        _ if e.is::<std::string::FromUtf8Error>() => 4,
+        _ => 1,
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(exit_code(e.as_ref()))
+        }
+    }
+}
+
+fn run(mut cli: Cli) -> Result<(), Box<dyn Error>> {
+    cli.command.expand_globs()?;
+    if cli.no_art && cli.command.needs_picture_data() {
+        return Err(AppError::new(
+            "--no-art can't be used with a command that extracts pictures or writes tags",
+        ));
+    }
+    let mut fields = match &cli.map {
+        Some(path) => FieldMap::from_file(path)?,
+        None => FieldMap::default(),
+    };
+    for mapping in &cli.mappings {
+        fields.set(mapping)?;
+    }
+    let reader = TagReader {
+        options: ParseOptions {
+            cp1252: cli.cp1252,
+            normalize: cli.normalize,
+            recover: cli.recover,
+            profile: cli.profile,
+            padding: cli.padding,
+            skip_picture_data: cli.no_art || matches!(cli.command, Commands::Info { .. }),
+            trim: !cli.no_trim,
+            ..Default::default()
+        },
+        verbose: cli.verbose,
+        lang: cli.lang,
+        fields,
+        modified_within: cli.modified_within,
+    };
+    match cli.command {
+        Commands::Info {
+            paths,
+            recursive,
+            inner,
+            quiet,
+            scan,
+            view,
+        } => {
+            let mut out = io::stdout().lock();
+            let read = |path: &Path| match &inner {
+                Some(entry) => reader.read_zip_entry(path, entry),
+                None => reader.read(path),
+            };
+
+            if scan.cache.is_some() && view.format != InfoFormat::Text {
+                return Err(AppError::new("--cache only works with the text format"));
+            }
+            if view.template.is_some() && view.format != InfoFormat::Text {
+                return Err(AppError::new("--template only works with the text format"));
+            }
+
+            if recursive {
+                // sorting needs every file read, however many get printed
+                let scanned = if scan.sort.is_empty() {
+                    scan.limit
+                } else {
+                    None
+                };
+                let progress = progress_bar(
+                    || count_files(&reader, &paths, scanned, &scan),
+                    quiet,
+                    atty::is(atty::Stream::Stderr),
+                );
+                let summary =
+                    print_info_recursive(&mut out, &reader, &paths, &scan, &view, &progress)?;
+                progress.finish_and_clear();
+
+                if !quiet {
+                    eprintln!("{}", summary);
+                }
+            } else if let ([path], InfoFormat::Text) = (paths.as_slice(), view.format) {
+                let tag = read(path.as_ref())?;
+                print_info(
+                    &mut out,
+                    &tag,
+                    reader.lang.as_deref(),
+                    &reader.fields,
+                    &view,
+                )?;
+            } else {
+                let mut failed = 0;
+                for path in &paths {
+                    let tag = read(path.as_ref());
+                    if !print_info_block(&mut out, &reader, path.as_ref(), tag, &view)? {
+                        failed += 1;
+                    }
+                }
+
+                if failed > 0 {
+                    return Err(AppError::new(&format!(
+                        "{} of {} files couldn't be read",
+                        failed,
+                        paths.len()
+                    ))
+                    .into());
+                }
+            }
+        }
+        Commands::List {
+            path,
+            kind,
+            offsets,
+            verify_sizes,
+            all_tags,
+        } => {
+            let mut out = io::stdout().lock();
+            if !all_tags {
+                let tag = reader.read(&path)?;
+                list_frames(&mut out, &tag, kind, offsets, verify_sizes)?;
+                return Ok(());
+            }
+
+            let tags = read_all_tags(&path, &reader.options)?;
+            if tags.is_empty() {
+                return Err(AppError::with_kind(ErrorKind::NotId3, "No ID3 tags found").into());
+            }
+            for (i, tag) in tags.iter().enumerate() {
+                reader.report(path.as_ref(), tag);
+                writeln!(out, "Tag {} of {}:", i + 1, tags.len())?;
+                list_frames(&mut out, tag, kind, offsets, verify_sizes)?;
+            }
+        }
+        Commands::Events { path } => {
+            let tag = reader.read(&path)?;
+            let Some(Frame::Etco {
+                timestamp_format,
+                events,
+            }) = find_frame_by_id(&tag.frames, "ETCO")
+            else {
+                return Err(AppError::with_kind(
+                    ErrorKind::Missing,
+                    "Event timing codes not available",
+                )
+                .into());
+            };
+
+            for (event, timestamp) in events {
+                println!(
+                    "{} @ {}",
+                    etco::event_name(*event),
+                    etco::format_timestamp(*timestamp, *timestamp_format)
+                );
+            }
+        }
+        Commands::Require { path, fields } => {
+            let tag = reader.read(&path)?;
+            let missing = missing_fields(&tag, &reader, &fields);
+            for field in &missing {
+                eprintln!(
+                    "{}: missing {}",
+                    path,
+                    field.to_possible_value().unwrap().get_name()
+                );
+            }
+            if !missing.is_empty() {
+                return Err(AppError::new(&format!(
+                    "{} of {} required fields missing",
+                    missing.len(),
+                    fields.len()
+                ))
+                .into());
+            }
+        }
+        Commands::Browse { paths } => {
+            let walks = paths.iter().map(|x| reader.walk(x)).collect();
+            browse(walks, &reader.options)?
+        }
+        Commands::Seek { path } => {
+            let tag = reader.read(&path)?;
+            let seek = find_frame_by_id(&tag.frames, "SEEK");
+            let aspi = find_frame_by_id(&tag.frames, "ASPI");
+            let mllt = find_frame_by_id(&tag.frames, "MLLT");
+            if seek.is_none() && aspi.is_none() && mllt.is_none() {
+                return Err(AppError::with_kind(
+                    ErrorKind::Missing,
+                    "Seek information not available",
+                )
+                .into());
+            }
+
+            if let Some(Frame::Seek { offset }) = seek {
+                println!("Next tag: +{} bytes", offset);
+            }
+            if let Some(Frame::Aspi {
+                data_start,
+                data_length,
+                bits_per_point,
+                points,
+            }) = aspi
+            {
+                println!(
+                    "Audio data: {} bytes from offset {}",
+                    data_length, data_start
+                );
+                println!("Seek points: {} of {} bits", points.len(), bits_per_point);
+                for (i, point) in points.iter().enumerate() {
+                    println!("{}: {}", i, point);
+                }
+            }
+            if let Some(Frame::Mllt {
+                frames_between_reference,
+                bytes_between_reference,
+                ms_between_reference,
+                deviations,
+                ..
+            }) = mllt
+            {
+                println!(
+                    "References: every {} frames, {} bytes, {} ms",
+                    frames_between_reference, bytes_between_reference, ms_between_reference
+                );
+                for (i, (bytes, ms)) in deviations.iter().enumerate() {
+                    println!("{}: {:+} bytes, {:+} ms", i, bytes, ms);
+                }
+            }
+        }
+        Commands::Cuesheet { path } => {
+            let tag = reader.read(&path)?;
+            let chapters = chap::chapters(&tag);
+            if chapters.is_empty() {
+                return Err(
+                    AppError::with_kind(ErrorKind::Missing, "Chapters not available").into(),
+                );
+            }
+
+            let file_name = Path::new(&path)
+                .file_name()
+                .map_or(path.clone(), |x| x.to_string_lossy().into());
+            print!("{}", chap::cue_sheet(&file_name, &tag, &chapters));
+        }
+        Commands::Lyrics { path, output } => {
+            let tag = reader.read(&path)?;
+
+            if let Some(output) = output {
+                let synced = tag.frames_by_id("SYLT").find_map(|x| match x {
+                    Frame::Other {
+                        content: Content::Binary(b),
+                        ..
+                    } => sylt::decode_sylt(b)?.to_lrc(),
+                    _ => None,
+                });
+                let plain = lyrics(&tag, reader.lang.as_deref()).find_map(|x| match x {
+                    Frame::Uslt { text, .. } => Some(text.clone()),
+                    _ => None,
+                });
+
+                match synced.or(plain) {
+                    Some(lyrics) => fs::write(&output, lyrics)?,
+                    None => {
+                        return Err(
+                            AppError::with_kind(ErrorKind::Missing, "Lyrics not available").into(),
+                        )
+                    }
+                }
+                eprintln!("Saved {}", output.display());
+                return Ok(());
+            }
+
+            let frames = sorted_lyrics(&tag, reader.lang.as_deref());
+
+            if frames.is_empty() {
+                return Err(AppError::with_kind(ErrorKind::Missing, "Lyrics not available").into());
+            }
+
+            let languages = lyrics_languages(&frames);
+            if languages.len() > 1 {
+                eprintln!(
+                    "Lyrics available in {}, pick one with --lang",
+                    languages.join(", ")
+                );
+            }
+
+            print_lyrics(&mut io::stdout().lock(), &frames)?;
+        }
+        Commands::Picture {
+            path,
+            picture_type,
+            list,
+            fetch,
+            data_uri,
+            format,
+            output_dir,
+            name_template,
+            recursive,
+            all,
+            archive,
+        } => {
+            let picture_type = match picture_type {
+                Some(picture_type) => picture_type,
+                None => default_picture_type(env::var_os("MP3INFO_DEFAULT_PICTURE").as_deref())?,
+            };
+            if let Some(output_dir) = output_dir {
+                fs::create_dir_all(&output_dir)?;
+                let picture_type = (!all).then_some(picture_type);
+                let name_template = name_template.as_deref().unwrap_or(if all {
+                    "{stem}-{type}.{ext}"
+                } else {
+                    "{stem}.{ext}"
+                });
+
+                if !recursive {
+                    let saved = export_picture(
+                        path.as_ref(),
+                        &reader,
+                        picture_type,
+                        &output_dir,
+                        name_template,
+                    )?;
+                    for dest in saved {
+                        eprintln!("Saved {}", dest.display());
+                    }
+                    return Ok(());
+                }
+
+                let progress = progress_bar(
+                    || count_files(&reader, &[&path], None, &ScanArgs::default()),
+                    false,
+                    atty::is(atty::Stream::Stderr),
+                );
+                for entry in reader.walk(&path) {
+                    let entry = entry?;
+                    progress.inc(1);
+                    match export_picture(&entry, &reader, picture_type, &output_dir, name_template)
+                    {
+                        Ok(saved) => progress.suspend(|| {
+                            for dest in saved {
+                                eprintln!("{} -> {}", entry.display(), dest.display())
+                            }
+                        }),
+                        Err(e) => progress.suspend(|| eprintln!("{}: {}", entry.display(), e)),
+                    }
+                }
+                progress.finish_and_clear();
+                return Ok(());
+            }
+
+            let tag = reader.read(&path)?;
+
+            if let Some(archive) = archive {
+                let count = archive_pictures(&tag, &archive)?;
+                eprintln!("Saved {} pictures to {}", count, archive.display());
+                return Ok(());
+            }
+
+            if list {
+                if let OutputFormat::Json = format {
+                    println!("{}", json::picture_list(&tag));
+                    return Ok(());
+                }
+
+                for pic in tag.pictures() {
+                    if let Frame::Apic {
+                        picture_type: ptype,
+                        data,
+                        mime_type,
+                        ..
+                    } = pic
+                    {
+                        let name = ptype.description();
+                        match pic.linked_picture_url() {
+                            Some(_) => println!("{} (linked URL)", name),
+                            None if tag
+                                .truncated_picture()
+                                .is_some_and(|x| std::ptr::eq(x, pic)) =>
+                            {
+                                println!("{} (truncated)", name)
+                            }
+                            None if pic.is_empty_picture() => println!("{} (empty)", name),
+                            None => match image::dimensions(data) {
+                                Some((width, height)) => println!(
+                                    "{} {} {}x{} {}",
+                                    name,
+                                    mime_type,
+                                    width,
+                                    height,
+                                    format_length(data.len())
+                                ),
+                                None => {
+                                    println!("{} {} {}", name, mime_type, format_length(data.len()))
+                                }
+                            },
+                        }
+                    }
+                }
+                return Ok(());
+            }
+
+            match tag.picture(picture_type) {
+                Some(pic) if pic.is_empty_picture() => {
+                    return Err(AppError::new(&format!(
+                        "Attached picture type '{}' present but empty",
+                        picture_type.name()
+                    ))
+                    .into());
+                }
+                Some(pic) if data_uri && pic.linked_picture_url().is_none() => {
+                    println!("{}", pic.data_uri().unwrap());
+                }
+                Some(pic @ Frame::Apic { data, .. }) => {
+                    let fetched;
+                    let data = match pic.linked_picture_url() {
+                        Some(url) if fetch => {
+                            fetched = fetch_url(&url)?;
+                            &fetched
+                        }
+                        Some(url) => {
+                            eprintln!("Picture is linked rather than embedded, pass --fetch to download it");
+                            println!("{}", url);
+                            return Ok(());
+                        }
+                        None => data,
+                    };
+
+                    eprintln!("Picture length: {}", data.len());
+                    let mut handle = std::io::stdout().lock();
+                    if atty::is(atty::Stream::Stdout) {
+                        println!(
+                            "Binary output not displayed! Pipe stdout into a file to save it."
+                        );
+                    } else {
+                        handle.write_all(data)?;
+                    }
+                    handle.flush()?
+                }
+                _ => {
+                    return Err(AppError::with_kind(
+                        ErrorKind::Missing,
+                        &format!(
+                            "Attached picture type '{}' not available",
+                            picture_type.name()
+                        ),
+                    )
+                    .into());
+                }
+            }
+        }
+        Commands::Duration {
+            path,
+            bitrate_histogram,
+        } => {
+            let contents = fs::read(&path)?;
+            let info = mpeg::analyze(&contents[leading_tag_size(&contents)..]);
+
+            if info.frames == 0 {
+                return Err(
+                    AppError::with_kind(ErrorKind::Missing, "No MPEG audio frames found").into(),
+                );
+            }
+
+            let seconds = info.duration.as_secs_f64();
+            println!(
+                "Duration: {}:{:05.2}",
+                (seconds / 60.0) as u64,
+                seconds % 60.0
+            );
+            println!("Frames: {}", info.frames);
+            println!("Bitrate mode: {}", info.bitrate_mode);
+            if let Some(lame) = &info.lame {
+                println!("Encoder: {}", lame.encoder);
+                println!(
+                    "Gapless: {} samples of delay, {} of padding",
+                    lame.delay, lame.padding
+                );
+                if let Some(gain) = lame.radio_gain {
+                    println!("Track gain: {:+.1} dB", gain.gain);
+                }
+                if let Some(gain) = lame.audiophile_gain {
+                    println!("Album gain: {:+.1} dB", gain.gain);
+                }
+            }
+
+            if bitrate_histogram {
+                for (bitrate, frames) in info.bitrates {
+                    println!("{} kbps: {} frames", bitrate, frames);
+                }
+            }
+        }
+        Commands::Dump { path } => {
+            let tag = reader.read(&path)?;
+            println!("{}", json::dump(&tag));
+        }
+        Commands::Header { path, hex } => {
+            let mut file = fs::File::open(&path)?;
+            let mut bytes = [0; 10];
+            file.read_exact(&mut bytes)?;
+            let header = decode_header(bytes)?;
+
+            let mut out = io::stdout().lock();
+            if !hex {
+                writeln!(out, "Version: 2.{}.{}", header.version, header.revision)?;
+                writeln!(out, "Flags: {}", header_flags(&header))?;
+                writeln!(out, "Size: {} bytes", header.size)?;
+                return Ok(());
+            }
+
+            let extended = if header.extended {
+                let mut size = [0; 4];
+                file.read_exact(&mut size)?;
+                // a bogus size shows on its own, without guessing at the data after it
+                let len = extended_header_size(&size, header.version).map_or(0, |x| x - 4);
+                let mut data = size.to_vec();
+                file.take(len as u64).read_to_end(&mut data)?;
+                Some(data)
+            } else {
+                None
+            };
+
+            annotate_header(&mut out, &bytes, extended.as_deref())?;
+        }
+        Commands::Get { path, id } => {
+            let tag = reader.read(&path)?;
+            let color = atty::is(atty::Stream::Stdout);
+            if print_frames(&mut io::stdout().lock(), &tag, &id, color)? == 0 {
+                return Err(AppError::with_kind(
+                    ErrorKind::Missing,
+                    &format!("Frame {} not available", id),
+                )
+                .into());
+            }
+        }
+        Commands::Replaygain { path } => {
+            let tag = reader.read(&path)?;
+            let replaygain = replaygain::from_tag(&tag);
+
+            if replaygain.is_empty() {
+                return Err(AppError::with_kind(
+                    ErrorKind::Missing,
+                    "ReplayGain information not available",
+                )
+                .into());
+            }
+
+            let gains = [
+                ("Track gain", replaygain.track_gain),
+                ("Album gain", replaygain.album_gain),
+            ];
+            let peaks = [
+                ("Track peak", replaygain.track_peak),
+                ("Album peak", replaygain.album_peak),
+            ];
+            for (label, gain) in gains {
+                if let Some(x) = gain {
+                    println!("{}: {:+.2} dB (x{:.4})", label, x, db_to_linear(x));
+                }
+            }
+            for (label, peak) in peaks {
+                if let Some(x) = peak {
+                    println!("{}: {:.6} ({:.2} dBFS)", label, x, 20.0 * x.log10());
+                }
+            }
+            for x in replaygain.rva2 {
+                print!(
+                    "RVA2 {} (channel {}): {:+.2} dB (x{:.4})",
+                    x.identification,
+                    x.channel,
+                    x.gain,
+                    db_to_linear(x.gain)
+                );
+                match x.peak {
+                    Some(peak) => println!(", peak {:.6}", peak),
+                    None => println!(),
+                }
+            }
+        }
+        Commands::Set {
+            path,
+            id,
+            value,
+            write,
+        } => {
+            let mut tag = reader.read(&path)?;
+            tag.set_text(&id, &value);
+            save_tag(&reader, path.as_ref(), &tag, &write)?;
+        }
+        Commands::Merge {
+            base,
+            from,
+            prefer,
+            write,
+        } => {
+            let mut tag = reader.read(&base)?;
+            let from_tag = reader.read(&from)?;
+            merge::merge(&mut tag, &from_tag, prefer);
+            save_tag(&reader, base.as_ref(), &tag, &write)?;
+        }
+        Commands::Clean {
+            path,
+            remove,
+            keep,
+            write,
+        } => {
+            let mut tag = reader.read(&path)?;
+            let removed = prune_frames(&mut tag, &remove, &keep);
+            let old_size = fs::metadata(&path)?.len();
+            let new_size = save_tag(&reader, path.as_ref(), &tag, &write)?;
+            eprintln!(
+                "Removed {} frames, saving {} bytes",
+                removed,
+                old_size as i64 - new_size as i64
+            );
+        }
+        Commands::Rename {
+            path,
+            template,
+            recursive,
+            dry_run,
+        } => {
+            let files = if recursive {
+                reader.walk(&path).collect::<io::Result<Vec<_>>>()?
+            } else {
+                vec![path.clone()]
+            };
+            let root = if path.is_dir() {
+                path.as_path()
+            } else {
+                path.parent().unwrap_or(Path::new(""))
+            };
+            rename_files(
+                &mut io::stdout().lock(),
+                &reader,
+                &files,
+                root,
+                &template,
+                dry_run,
+            )?;
+        }
+        Commands::Diff { old, new } => {
+            let (old, new) = (reader.read(&old)?, reader.read(&new)?);
+            let changes = diff::diff_tags(&old, &new);
+            if changes.is_empty() {
+                eprintln!("No differences");
+            }
+            for change in changes {
+                println!("{}", change);
+            }
+        }
+        Commands::Dupes { paths } => {
+            let mut files = Vec::new();
+            for path in paths {
+                files.extend(reader.walk(path).collect::<io::Result<Vec<_>>>()?);
+            }
+
+            let (groups, errors) = dupes::find_duplicates(files);
+            for (path, e) in errors {
+                eprintln!("{}: {}", path.display(), e);
+            }
+
+            let mut out = io::stdout().lock();
+            for (i, group) in groups.iter().enumerate() {
+                // a blank line between groups, as fdupes does
+                if i > 0 {
+                    writeln!(out)?;
+                }
+                for path in group {
+                    writeln!(out, "{}", path.display())?;
+                }
+            }
+        }
+        Commands::Lint {
+            paths,
+            detect_mojibake,
+        } => {
+            let mut out = io::stdout().lock();
+            for path in &paths {
+                for entry in reader.walk(path) {
+                    let entry = entry?;
+                    for problem in lint(&reader, &entry, detect_mojibake) {
+                        writeln!(out, "{}: {}", entry.display(), problem)?;
+                    }
+                }
+            }
+        }
+        Commands::FixSize { path, dry_run } => {
+            let mut contents = fs::read(&path)?;
+            let Some((old, new)) = encoder::fix_tag_size(&mut contents) else {
+                return Err(AppError::with_kind(ErrorKind::NotId3, "No ID3v2 tag found").into());
+            };
+
+            if old == new {
+                eprintln!("Tag size of {} bytes is already right", old);
+            } else if dry_run {
+                eprintln!("Would fix tag size: {} -> {} bytes", old, new);
+            } else {
+                fs::write(&path, contents)?;
+                eprintln!("Fixed tag size: {} -> {} bytes", old, new);
+            }
+        }
+        Commands::WriteV1 { path, dry_run } => {
+            let tag = reader.read(&path)?;
+            let v1 = id3v1::from_tag(&tag);
+            if dry_run {
+                eprintln!("Would write ID3v1 tag: {} - {}", v1.artist, v1.title);
+                return Ok(());
+            }
+            id3v1::write_id3v1(&path, &v1)?;
+            eprintln!("Wrote ID3v1 tag: {} - {}", v1.artist, v1.title);
+        }
+        Commands::CopyTags {
+            source,
+            dest,
+            write,
+        } => {
+            let tag = reader.read(&source)?;
+            save_tag(&reader, dest.as_ref(), &tag, &write)?;
+            if !write.dry_run {
+                eprintln!("Copied {} frames to {}", tag.frames.len(), dest);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use mp3info::{
+        encoder::encode_tag,
+        fieldmap::FieldMap,
+        parser::{Content, Frame, Header, ParseOptions, PictureType, Tag},
+    };
+
+    use super::{InfoField, InfoFormat, InfoView, ScanArgs, TagReader};
+
+    fn reader() -> TagReader {
+        TagReader {
+            options: ParseOptions::default(),
+            verbose: false,
+            lang: None,
+            fields: FieldMap::default(),
+            modified_within: None,
+        }
+    }
+
+    /// Write an MP3 file with a v2.3 tag holding the given text frames
+    fn write_file(name: &str, frames: &[(&str, &str)]) -> std::path::PathBuf {
+        write_frames(
+            name,
+            frames
+                .iter()
+                .map(|(id, text)| Frame::Other {
+                    id: id.to_string(),
+                    content: Content::Text(text.to_string()),
+                })
+                .collect(),
+        )
+    }
+
+    /// Write an MP3 file with a v2.3 tag holding the given frames
+    fn write_frames(name: &str, frames: Vec<Frame>) -> std::path::PathBuf {
+        let tag = Tag {
+            header: Header {
+                version: 3,
+                revision: 0,
+                unsynchronisation: false,
+                extended: false,
+                experimental: false,
+                footer_present: false,
+                size: 0,
+            },
+            frames,
+            warnings: Vec::new(),
+            offsets: Vec::new(),
+            sizes: Vec::new(),
+            encodings: Vec::new(),
+            timings: Vec::new(),
+            trailer: Vec::new(),
+        };
+
+        let dir = std::env::temp_dir().join(format!("mp3info-cli-{}", std::process::id()));
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, encode_tag(&tag)).unwrap();
+        path
+    }
+
+    fn exit_code_of(args: &[&str]) -> u8 {
+        let cli = <super::Cli as clap::Parser>::parse_from(args);
+        super::exit_code(super::run(cli).unwrap_err().as_ref())
+    }
+
+    #[test]
+    fn exit_codes() {
+        let no_lyrics = write_file("no-lyrics.mp3", &[("TIT2", "Title")]);
+        let no_lyrics = no_lyrics.to_str().unwrap();
+        assert_eq!(exit_code_of(&["mp3info", "lyrics", no_lyrics]), 5);
+
+        let missing = write_file("missing.mp3", &[]).with_file_name("not-there.mp3");
+        assert_eq!(
+            exit_code_of(&["mp3info", "lyrics", missing.to_str().unwrap()]),
+            2
+        );
+
+        let not_id3 = no_lyrics.replace("no-lyrics", "not-id3");
+        fs::write(&not_id3, [0xFF; 64]).unwrap();
+        assert_eq!(exit_code_of(&["mp3info", "lyrics", &not_id3]), 3);
+    }
+
+    #[test]
+    fn lint_experimental_tag() {
+        let path = write_file("experimental.mp3", &[("TIT2", "Title")]);
+        let mut contents = fs::read(&path).unwrap();
+        contents[5] |= 0x20; // experimental flag
+        fs::write(&path, contents).unwrap();
+
+        assert_eq!(
+            super::lint(&reader(), &path, false),
+            ["experimental tag, parsing may be unreliable"]
+        );
+    }
+
+    #[test]
+    fn info_for_several_paths() {
+        let a = write_file("info-a.mp3", &[("TIT2", "First")]);
+        let b = write_file("info-b.mp3", &[("TIT2", "Second")]);
+        let mut out = Vec::new();
+
+        for path in [&a, &b] {
+            let tag = reader().read(path);
+            assert!(
+                super::print_info_block(&mut out, &reader(), path, tag, &InfoView::default())
+                    .unwrap()
+            );
+        }
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "{}\nTitle: First\n\n{}\nTitle: Second\n\n",
+                a.display(),
+                b.display()
+            )
+        );
+    }
+
+    #[test]
+    fn get_composer_frame() {
+        let path = write_file("get.mp3", &[("TIT2", "Title"), ("TCOM", "Composer")]);
+        let tag = reader().read(path).unwrap();
+        let mut out = Vec::new();
+
+        assert_eq!(
+            super::print_frames(&mut out, &tag, "TCOM", false).unwrap(),
+            1
+        );
+        assert_eq!(out, b"Composer\n");
+        assert_eq!(
+            super::print_frames(&mut out, &tag, "TPE1", false).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn info_shows_bpm_and_key() {
+        let path = write_file("bpm.mp3", &[("TBPM", "174"), ("TKEY", "Am")]);
+        let tag = reader().read(path).unwrap();
+        let mut out = Vec::new();
+
+        super::print_info(
+            &mut out,
+            &tag,
+            None,
+            &FieldMap::default(),
+            &InfoView::default(),
+        )
+        .unwrap();
+
+        assert_eq!(out, b"BPM: 174\nKey: Am\n");
+    }
+
+    #[test]
+    fn info_artist_from_band() {
+        let path = write_file("field-map.mp3", &[("TPE1", "Feat"), ("TPE2", "Band")]);
+        let tag = reader().read(path).unwrap();
+        let mut fields = FieldMap::default();
+        fields.set("artist=TPE2").unwrap();
+        let mut out = Vec::new();
+
+        super::print_info(&mut out, &tag, None, &fields, &InfoView::default()).unwrap();
+
+        assert_eq!(out, b"Lead performer: Band\nBand: Band\n");
+    }
+
+    #[test]
+    fn bare_info_fields() {
+        let path = write_file(
+            "bare.mp3",
+            &[("TIT2", "New Title"), ("TPE1", "Artist"), ("TALB", "Album")],
+        );
+        let tag = reader().read(path).unwrap();
+        let view = |select: Vec<InfoField>, bare| InfoView {
+            select,
+            bare,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+
+        super::print_info(
+            &mut out,
+            &tag,
+            None,
+            &FieldMap::default(),
+            &view(vec![InfoField::Title], true),
+        )
+        .unwrap();
+        assert_eq!(out, b"New Title\n");
+
+        out.clear();
+        super::print_info(
+            &mut out,
+            &tag,
+            None,
+            &FieldMap::default(),
+            &view(vec![InfoField::Album, InfoField::Title], false),
+        )
+        .unwrap();
+        assert_eq!(out, b"Album: Album\nTitle: New Title\n");
+    }
+
+    #[test]
+    fn info_from_template() {
+        let path = write_file(
+            "template.mp3",
+            &[("TIT2", "Song"), ("TPE1", "Artist"), ("TRCK", "3/12")],
+        );
+        let tag = reader().read(path).unwrap();
+        let view = InfoView {
+            template: Some("{artist} \u{2013} {title} ({year}) #{track}".into()),
+            missing: "?".into(),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+
+        super::print_info(&mut out, &tag, None, &FieldMap::default(), &view).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Artist \u{2013} Song (?) #3/12\n"
+        );
+    }
+
+    #[test]
+    fn require_missing_year() {
+        let path = write_file(
+            "require.mp3",
+            &[("TIT2", "Title"), ("TPE1", "Artist"), ("TALB", " ")],
+        );
+        let tag = reader().read(path).unwrap();
+        let fields = [
+            InfoField::Title,
+            InfoField::Artist,
+            InfoField::Album,
+            InfoField::Year,
+        ];
+
+        let missing = super::missing_fields(&tag, &reader(), &fields);
+
+        assert!(missing == [InfoField::Album, InfoField::Year]);
+        assert!(super::missing_fields(&tag, &reader(), &fields[..2]).is_empty());
+
+        let cli = <super::Cli as clap::Parser>::try_parse_from([
+            "mp3info",
+            "require",
+            "require.mp3",
+            "--fields",
+            "title,year",
+        ]);
+        assert!(cli.is_ok());
+    }
+
+    #[test]
+    fn clean_removes_priv() {
+        let private = Frame::Other {
+            id: "PRIV".into(),
+            content: Content::Binary(b"owner\0junk".to_vec()),
+        };
+        let path = write_frames(
+            "clean.mp3",
+            vec![
+                Frame::Other {
+                    id: "TIT2".into(),
+                    content: Content::Text("Title".into()),
+                },
+                private.clone(),
+                private,
+            ],
+        );
+        let size = fs::metadata(&path).unwrap().len();
+        let mut tag = reader().read(&path).unwrap();
+
+        assert_eq!(super::prune_frames(&mut tag, &["priv".into()], &[]), 2);
+        let new_size =
+            super::save_tag(&reader(), &path, &tag, &super::WriteOptions::default()).unwrap();
+
+        let mut tag = reader().read(&path).unwrap();
+        assert_eq!(tag.frames_by_id("PRIV").count(), 0);
+        assert_eq!(tag.title(), Some("Title"));
+        assert!(new_size < size);
+
+        assert_eq!(super::prune_frames(&mut tag, &[], &["TPE1".into()]), 1);
+        assert!(tag.frames.is_empty());
+    }
+
+    #[test]
+    fn every_command_expands_globs() {
+        let path = write_file("globs/a.mp3", &[("TIT2", "A")]);
+        let dir = path.parent().unwrap();
+        fs::copy(&path, dir.join("b.mp3")).unwrap();
+        let parse = |args: &[&str]| {
+            let mut cli = <super::Cli as clap::Parser>::try_parse_from(
+                ["mp3info"].iter().chain(args).map(|x| x.to_string()),
+            )
+            .unwrap();
+            cli.command.expand_globs().map(|_| cli.command)
+        };
+        let pattern = format!("{}/*.mp3", dir.display());
+
+        let Ok(super::Commands::Lint { paths, .. }) = parse(&["lint", &pattern]) else {
+            panic!("lint paths not expanded");
+        };
+        assert_eq!(
+            paths,
+            [
+                path.display().to_string(),
+                dir.join("b.mp3").display().to_string()
+            ]
+        );
+
+        let single = format!("{}/a*.mp3", dir.display());
+        let Ok(super::Commands::Dump { path: dumped }) = parse(&["dump", &single]) else {
+            panic!("dump path not expanded");
+        };
+        assert_eq!(dumped, path.display().to_string());
+
+        let e = parse(&["dump", &pattern]).err().unwrap();
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "'{}' matches 2 files, but only one can be given here",
+                pattern
+            )
+        );
+    }
+
+    #[test]
+    fn rename_dry_run() {
+        let path = write_file(
+            "rename.mp3",
+            &[
+                ("TPE1", "AC/DC"),
+                ("TALB", "Back in Black"),
+                ("TRCK", "6/10"),
+                ("TIT2", "Back in Black"),
+            ],
+        );
+        let untitled = write_file("untitled.mp3", &[("TPE1", "AC/DC")]);
+        let root = path.parent().unwrap();
+
+        let mut out = Vec::new();
+        super::rename_files(
+            &mut out,
+            &reader(),
+            &[path.clone(), untitled.clone()],
+            root,
+            "{artist}/{album}/{track:02} {title}.mp3",
+            true,
+        )
+        .unwrap();
+
+        let target = root.join("AC_DC/Back in Black/06 Back in Black.mp3");
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{} -> {}\n", path.display(), target.display())
+        );
+        assert!(path.exists());
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn profile_every_frame() {
+        let path = write_file(
+            "profile.mp3",
+            &[("TIT2", "Title"), ("TPE1", "Artist"), ("TALB", "Album")],
+        );
+        let options = ParseOptions {
+            profile: true,
+            ..Default::default()
+        };
+        let tag = mp3info::read_file_with(&path, &options).unwrap();
+
+        let mut out = Vec::new();
+        super::print_profile(&mut out, &path, &tag, Duration::from_millis(2)).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 4);
+        for (line, id) in lines.iter().zip(["TIT2", "TPE1", "TALB"]) {
+            assert!(line.starts_with(&format!("profile: {}: {} ", path.display(), id)));
+        }
+        assert!(lines[3].ends_with(": 3 frames read in 2ms"));
+    }
+
+    #[test]
+    fn info_shows_encoder() {
+        let path = write_file(
+            "encoder.mp3",
+            &[("TSSE", "LAME 3.100"), ("TENC", "Someone")],
+        );
+        let tag = reader().read(path).unwrap();
+        let mut out = Vec::new();
+
+        super::print_info(
+            &mut out,
+            &tag,
+            None,
+            &FieldMap::default(),
+            &InfoView::default(),
+        )
+        .unwrap();
+
+        assert_eq!(out, b"Encoder: LAME 3.100\nEncoded by: Someone\n");
+    }
+
+    #[test]
+    fn find_typed_frame_by_id() {
+        let frames = [
+            Frame::Other {
+                id: "TIT2".into(),
+                content: Content::Text("Title".into()),
+            },
+            Frame::Uslt {
+                text: "Lyrics".into(),
+                language: "eng".into(),
+                description: "".into(),
+            },
+        ];
+
+        assert!(matches!(
+            super::find_frame_by_id(&frames, "USLT"),
+            Some(Frame::Uslt { text, .. }) if text == "Lyrics"
+        ));
+        assert!(super::find_frame_by_id(&frames, "APIC").is_none());
+    }
+
+    #[test]
+    fn dry_run_set_keeps_file() {
+        let path = write_file("dry-run.mp3", &[("TIT2", "Old title")]);
+        let before = fs::read(&path).unwrap();
+
+        let mut tag = reader().read(&path).unwrap();
+        tag.set_text("TIT2", "New title");
+        let dry_run = super::WriteOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        super::save_tag(&reader(), &path, &tag, &dry_run).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), before);
+
+        super::save_tag(&reader(), &path, &tag, &Default::default()).unwrap();
+        assert_eq!(reader().read(&path).unwrap().title(), Some("New title"));
+    }
+
+    #[test]
+    fn no_progress_when_piped() {
+        let counted = std::cell::Cell::new(false);
+        let count = || {
+            counted.set(true);
+            10
+        };
+        let progress = super::progress_bar(count, false, false);
+        progress.inc(5);
+        assert!(progress.is_hidden());
+
+        assert!(super::progress_bar(count, true, true).is_hidden());
+        // a hidden bar doesn't walk the tree to count the files
+        assert!(!counted.get());
+    }
+
+    #[test]
+    fn empty_picture_is_not_exported() {
+        let path = write_frames(
+            "empty-picture.mp3",
+            vec![Frame::Apic {
+                data: Vec::new(),
+                mime_type: "image/jpeg".into(),
+                picture_type: PictureType::CoverFront,
+                description: "".into(),
+            }],
+        );
+        let output_dir = path.with_extension("d");
+        fs::create_dir_all(&output_dir).unwrap();
+
+        let e = super::export_picture(
+            &path,
+            &reader(),
+            Some(PictureType::CoverFront),
+            &output_dir,
+            "{stem}.{ext}",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            e.to_string(),
+            "Attached picture type 'cover-front' present but empty"
+        );
+        assert_eq!(fs::read_dir(&output_dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn export_every_picture() {
+        let apic = |picture_type, mime_type: &str| Frame::Apic {
+            data: vec![1, 2, 3],
+            mime_type: mime_type.into(),
+            picture_type,
+            description: "".into(),
+        };
+        let path = write_frames(
+            "every-picture.mp3",
+            vec![
+                apic(PictureType::CoverFront, "image/jpeg"),
+                apic(PictureType::CoverBack, "image/png"),
+                apic(PictureType::Other, "image/png"),
+                apic(PictureType::Other, "image/png"),
+            ],
+        );
+        let output_dir = path.with_extension("d");
+        fs::create_dir_all(&output_dir).unwrap();
+
+        let saved =
+            super::export_picture(&path, &reader(), None, &output_dir, "{type}.{ext}").unwrap();
+
+        let names: Vec<_> = saved
+            .iter()
+            .map(|x| x.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "cover-front.jpg",
+                "cover-back.png",
+                "other.png",
+                "other-2.png"
+            ]
+        );
+        assert_eq!(fs::read_dir(&output_dir).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn recursive_info_stops_at_limit() {
+        let dir = write_file("limit/a.mp3", &[("TIT2", "A")]);
+        let dir = dir.parent().unwrap();
+        for name in ["b.mp3", "c.mp3", "d.mp3"] {
+            fs::copy(dir.join("a.mp3"), dir.join(name)).unwrap();
+        }
+
+        let mut out = Vec::new();
+        let summary = super::print_info_recursive(
+            &mut out,
+            &reader(),
+            &[dir, dir],
+            &ScanArgs {
+                limit: Some(2),
+                ..Default::default()
+            },
+            &InfoView::default(),
+            &indicatif::ProgressBar::hidden(),
+        )
+        .unwrap();
+
+        assert_eq!(summary.scanned, 2);
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("Title: A").count(), 2);
+    }
+
+    #[test]
+    fn recursive_info_jsonl() {
+        let dir = write_file("jsonl/a.mp3", &[("TIT2", "A")]);
+        let dir = dir.parent().unwrap();
+        fs::write(dir.join("b.mp3"), b"not a tag").unwrap();
+
+        let mut out = Vec::new();
+        super::print_info_recursive(
+            &mut out,
+            &reader(),
+            &[dir],
+            &ScanArgs::default(),
+            &InfoView {
+                format: InfoFormat::Jsonl,
+                ..Default::default()
+            },
+            &indicatif::ProgressBar::hidden(),
+        )
+        .unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|x| serde_json::from_str(x).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().any(|x| x["title"] == "A"));
+        assert!(lines.iter().any(|x| x["error"].is_string()));
+    }
+
+    #[test]
+    fn annotated_header() {
+        let mut out = Vec::new();
+        super::annotate_header(&mut out, b"ID3\x04\x00\x80\x00\x00\x02\x01", None).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "49 44 33     magic \"ID3\"\n\
+             04           version 2.4\n\
+             00           revision 0\n\
+             80           flags: unsynchronisation\n\
+             00 00 02 01  size 257 (raw 513)\n"
+        );
+    }
+
+    #[test]
+    fn lyrics_in_language() {
+        let uslt = |language: &str, text: &str| Frame::Uslt {
+            text: text.into(),
+            language: language.into(),
+            description: "".into(),
+        };
+        let path = write_frames(
+            "lyrics-lang.mp3",
+            vec![uslt("eng", "Hello"), uslt("deu", "Hallo")],
+        );
+        let tag = reader().read(&path).unwrap();
+
+        let german: Vec<String> = super::lyrics(&tag, Some("DEU"))
+            .map(|x| x.to_string())
+            .collect();
+        assert_eq!(german, ["Hallo"]);
+        assert_eq!(super::lyrics(&tag, None).count(), 2);
+    }
+
+    #[test]
+    fn lyrics_in_three_languages() {
+        let uslt = |language: &str, text: &str| Frame::Uslt {
+            text: text.into(),
+            language: language.into(),
+            description: "".into(),
+        };
+        let path = write_frames(
+            "lyrics-three.mp3",
+            vec![
+                uslt("fra", "Bonjour"),
+                uslt("eng", "Hello"),
+                uslt("deu", "Hallo"),
+            ],
+        );
+        let tag = reader().read(&path).unwrap();
+
+        let frames = super::sorted_lyrics(&tag, None);
+        let mut out = Vec::new();
+        super::print_lyrics(&mut out, &frames).unwrap();
+
+        assert_eq!(super::lyrics_languages(&frames), ["deu", "eng", "fra"]);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Language: deu\nDescription: \n=== \nHallo\n\n\
+             Language: eng\nDescription: \n=== \nHello\n\n\
+             Language: fra\nDescription: \n=== \nBonjour\n\n"
+        );
+    }
+
+    #[test]
+    fn recursive_info_sorted() {
+        write_file(
+            "sorted/2.mp3",
+            &[("TPE1", "B"), ("TRCK", "10"), ("TIT2", "Fourth")],
+        );
+        write_file("sorted/3.mp3", &[("TPE1", "a"), ("TIT2", "First")]);
+        write_file("sorted/4.mp3", &[("TIT2", "Last")]);
+        let dir = write_file(
+            "sorted/1.mp3",
+            &[("TPE1", "B"), ("TRCK", "2"), ("TIT2", "Third")],
+        );
+        let dir = dir.parent().unwrap();
+
+        let mut out = Vec::new();
+        super::print_info_recursive(
+            &mut out,
+            &reader(),
+            &[dir],
+            &ScanArgs {
+                limit: Some(3),
+                sort: vec![super::SortField::Artist, super::SortField::Track],
+                ..Default::default()
+            },
+            &InfoView::default(),
+            &indicatif::ProgressBar::hidden(),
+        )
+        .unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let titles: Vec<&str> = out
+            .lines()
+            .filter_map(|x| x.strip_prefix("Title: "))
+            .collect();
+        assert_eq!(titles, ["First", "Third", "Fourth"]);
+    }
+
+    #[test]
+    fn default_picture_type_from_env() {
+        use clap::Parser;
+        use std::ffi::OsStr;
+
+        let picture_type = |args: &[&str]| match super::Cli::parse_from(args).command {
+            super::Commands::Picture { picture_type, .. } => picture_type,
+            _ => unreachable!(),
+        };
+
+        // the flag wins over the variable, which is only looked at without it
+        assert_eq!(picture_type(&["mp3info", "picture", "song.mp3"]), None);
+        assert_eq!(
+            picture_type(&["mp3info", "picture", "song.mp3", "-t", "cover-back"]),
+            Some(PictureType::CoverBack)
+        );
+
+        let default = |value: Option<&str>| super::default_picture_type(value.map(OsStr::new));
+        assert_eq!(default(Some("artist")).unwrap(), PictureType::Artist);
+        assert_eq!(default(None).unwrap(), PictureType::CoverFront);
+        assert_eq!(
+            default(Some("poster")).unwrap_err().to_string(),
+            "MP3INFO_DEFAULT_PICTURE is set to 'poster', which isn't a picture type"
+        );
+    }
+}
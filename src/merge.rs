@@ -0,0 +1,148 @@
+use crate::parser::{Content, Frame, Tag};
+
+/// Which frame wins when both tags have one that can only occur once
+#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keep the frame of the tag merged into
+    Base,
+    /// Take the frame of the tag merged from
+    From,
+    /// Combine the values of text frames, keep the base frame of anything else
+    KeepBoth,
+}
+
+/// What makes a frame unique within a tag. Pictures are unique per picture type, lyrics and
+/// comments per language and description, user defined text per description.
+fn frame_key(frame: &Frame) -> (&str, String) {
+    match frame {
+        Frame::Apic { picture_type, .. } => ("APIC", format!("{:?}", picture_type)),
+        Frame::Uslt {
+            language,
+            description,
+            ..
+        } => ("USLT", format!("{}\0{}", language, description)),
+        Frame::Comm {
+            language,
+            description,
+            ..
+        } => ("COMM", format!("{}\0{}", language, description)),
+        Frame::Other {
+            id,
+            content: Content::Text(text),
+        } if id == "TXXX" => (id, text.split('\0').next().unwrap_or_default().into()),
+        _ => (frame.id(), String::new()),
+    }
+}
+
+fn text_values(frame: &Frame) -> Option<Vec<String>> {
+    match frame {
+        Frame::Other {
+            content: Content::Text(x),
+            ..
+        } => Some(vec![x.clone()]),
+        Frame::Other {
+            content: Content::TextList(x),
+            ..
+        } => Some(x.clone()),
+        _ => None,
+    }
+}
+
+/// Combine the values of two text frames, leaving out duplicates
+fn combine(base: &Frame, from: &Frame) -> Option<Frame> {
+    let mut values = text_values(base)?;
+    for value in text_values(from)? {
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+
+    Some(Frame::Other {
+        id: base.id().into(),
+        content: match values.len() {
+            1 => Content::Text(values.remove(0)),
+            _ => Content::TextList(values),
+        },
+    })
+}
+
+/// Merge the frames of `from` into `base`. Frames `base` doesn't have are added, conflicts
+/// are resolved by the policy.
+pub fn merge(base: &mut Tag, from: &Tag, policy: MergePolicy) {
+    for frame in &from.frames {
+        let key = frame_key(frame);
+        let Some(existing) = base.frames.iter_mut().find(|x| frame_key(x) == key) else {
+            base.frames.push(frame.clone());
+            continue;
+        };
+
+        match policy {
+            MergePolicy::Base => {}
+            MergePolicy::From => *existing = frame.clone(),
+            MergePolicy::KeepBoth => {
+                let multi_valued = frame.id().starts_with('T') && frame.id() != "TXXX";
+                if let Some(combined) = combine(existing, frame).filter(|_| multi_valued) {
+                    *existing = combined;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MergePolicy;
+    use crate::{
+        parser::{Content, Frame, PictureType},
+        testutil::{tag, text_frame},
+    };
+
+    fn cover(data: &[u8]) -> Frame {
+        Frame::Apic {
+            data: data.to_vec(),
+            mime_type: "image/jpeg".into(),
+            picture_type: PictureType::CoverFront,
+            description: "".into(),
+        }
+    }
+
+    #[test]
+    fn merge_art_into_text() {
+        let mut base = tag(vec![
+            text_frame("TIT2", "Title"),
+            text_frame("TPE1", "Artist"),
+        ]);
+        let art = tag(vec![cover(b"front"), text_frame("TPE1", "Other artist")]);
+
+        super::merge(&mut base, &art, MergePolicy::Base);
+
+        assert_eq!(base.title(), Some("Title"));
+        assert_eq!(base.artist(), Some("Artist"));
+        assert_eq!(
+            base.picture(PictureType::CoverFront),
+            Some(&cover(b"front"))
+        );
+    }
+
+    #[test]
+    fn merge_policies() {
+        let base = tag(vec![text_frame("TPE1", "A"), cover(b"old")]);
+        let from = tag(vec![text_frame("TPE1", "B"), cover(b"new")]);
+
+        let mut merged = base.clone();
+        super::merge(&mut merged, &from, MergePolicy::From);
+        assert_eq!(merged.artist(), Some("B"));
+        assert_eq!(merged.pictures(), [&cover(b"new")]);
+
+        let mut merged = base.clone();
+        super::merge(&mut merged, &from, MergePolicy::KeepBoth);
+        assert_eq!(
+            merged.frames[0],
+            Frame::Other {
+                id: "TPE1".into(),
+                content: Content::TextList(vec!["A".into(), "B".into()]),
+            }
+        );
+        assert_eq!(merged.pictures(), [&cover(b"old")]);
+    }
+}
@@ -0,0 +1,163 @@
+//! Spotting UTF-8 text that was stored as ISO-8859-1, and so decodes to mojibake like "CafÃ©"
+
+use crate::parser::{Content, Encoding, Frame, Tag};
+
+/// Text frame whose value looks double encoded, and what it likely should say
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mojibake {
+    pub frame_id: String,
+    pub text: String,
+    pub fixed: String,
+}
+
+/// Control characters outside ASCII, which is what UTF-8 continuation bytes read as latin-1
+/// often turn into
+fn control_chars(text: &str) -> usize {
+    text.chars()
+        .filter(|x| x.is_control() && !x.is_ascii())
+        .count()
+}
+
+/// Read the text back as the ISO-8859-1 bytes it was decoded from, and those bytes as UTF-8.
+/// Only text with non-ASCII characters that makes valid UTF-8 this way, with fewer control
+/// characters than before or none at all, counts as mojibake.
+pub fn fix(text: &str) -> Option<String> {
+    let bytes = text
+        .chars()
+        .map(|x| u8::try_from(x as u32).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    if bytes.is_ascii() {
+        return None;
+    }
+
+    String::from_utf8(bytes).ok().filter(|x| {
+        let left = control_chars(x);
+        left == 0 || left < control_chars(text)
+    })
+}
+
+/// The texts of a frame that mojibake may hide in
+fn texts(frame: &Frame) -> Vec<&String> {
+    match frame {
+        Frame::Other {
+            content: Content::Text(text),
+            ..
+        } => vec![text],
+        Frame::Other {
+            content: Content::TextList(values),
+            ..
+        } => values.iter().collect(),
+        Frame::Comm {
+            text, description, ..
+        }
+        | Frame::Uslt {
+            text, description, ..
+        } => vec![text, description],
+        _ => Vec::new(),
+    }
+}
+
+/// Like [`texts`], to change them
+fn texts_mut(frame: &mut Frame) -> Vec<&mut String> {
+    match frame {
+        Frame::Other {
+            content: Content::Text(text),
+            ..
+        } => vec![text],
+        Frame::Other {
+            content: Content::TextList(values),
+            ..
+        } => values.iter_mut().collect(),
+        Frame::Comm {
+            text, description, ..
+        }
+        | Frame::Uslt {
+            text, description, ..
+        } => vec![text, description],
+        _ => Vec::new(),
+    }
+}
+
+/// Whether the frame was read as latin-1, the only encoding mojibake comes out of. Frames
+/// stored as UTF-8 or UTF-16 hold whatever they were given.
+fn read_as_latin1(tag: &Tag, index: usize) -> bool {
+    matches!(tag.encodings.get(index), Some(Some(Encoding::ISO_8859_1)))
+}
+
+/// Every text of the tag's latin-1 frames that looks like mojibake
+pub fn find(tag: &Tag) -> Vec<Mojibake> {
+    let mut found = Vec::new();
+    for (i, frame) in tag.frames.iter().enumerate() {
+        if !read_as_latin1(tag, i) {
+            continue;
+        }
+        for text in texts(frame) {
+            if let Some(fixed) = fix(text) {
+                found.push(Mojibake {
+                    frame_id: frame.id().to_string(),
+                    text: text.clone(),
+                    fixed,
+                });
+            }
+        }
+    }
+    found
+}
+
+/// Replace every text of the tag's latin-1 frames that looks like mojibake with what it
+/// likely should say. Returns how many texts were fixed.
+pub fn fix_tag(tag: &mut Tag) -> usize {
+    let latin1: Vec<bool> = (0..tag.frames.len())
+        .map(|i| read_as_latin1(tag, i))
+        .collect();
+    let mut count = 0;
+    for (frame, _) in tag.frames.iter_mut().zip(latin1).filter(|(_, x)| *x) {
+        for text in texts_mut(frame) {
+            if let Some(fixed) = fix(text) {
+                *text = fixed;
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parser::Encoding,
+        testutil::{tag, text_frame},
+    };
+
+    #[test]
+    fn find_and_fix_double_encoded_text() {
+        let mut tag = tag(vec![
+            text_frame("TIT2", "CafÃ© MÃ¼ller"),
+            text_frame("TPE1", "Café Müller"),
+            text_frame("TALB", "Plain ASCII"),
+            text_frame("TCOM", "Caf\u{00C3}"),
+            // as UTF-8 it holds what it was given, however odd
+            text_frame("TEXT", "CafÃ©"),
+        ]);
+        tag.encodings = vec![Some(Encoding::ISO_8859_1); 4];
+        tag.encodings.push(Some(Encoding::UTF_8));
+
+        let found = super::find(&tag);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].frame_id, "TIT2");
+        assert_eq!(found[0].fixed, "Café Müller");
+
+        assert_eq!(super::fix_tag(&mut tag), 1);
+        assert_eq!(tag.title(), Some("Café Müller"));
+        assert_eq!(tag.artist(), Some("Café Müller"));
+        assert_eq!(tag.text("TEXT"), Some("CafÃ©"));
+    }
+
+    #[test]
+    fn control_characters_left_over() {
+        // a C1 control that survives the fix is no sign of mojibake
+        assert_eq!(super::fix("Â\u{85}"), None);
+        assert_eq!(super::fix("â\u{80}\u{94}"), Some("—".into()));
+    }
+}
@@ -0,0 +1,418 @@
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use crate::replaygain::{decode_rgad_adjustment, RgadAdjustment};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MpegVersion {
+    Mpeg1,
+    Mpeg2,
+    Mpeg25,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Layer1,
+    Layer2,
+    Layer3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelMode {
+    Stereo,
+    JointStereo,
+    DualChannel,
+    Mono,
+}
+
+/// Header of a single MPEG audio frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub version: MpegVersion,
+    pub layer: Layer,
+    /// Bitrate in kbps
+    pub bitrate: u32,
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    pub padding: bool,
+    pub channel_mode: ChannelMode,
+}
+
+const BITRATES_V1_L1: [u32; 14] = [
+    32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+];
+const BITRATES_V1_L2: [u32; 14] = [
+    32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+];
+const BITRATES_V1_L3: [u32; 14] = [
+    32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+const BITRATES_V2_L1: [u32; 14] = [
+    32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+];
+const BITRATES_V2_L23: [u32; 14] = [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+impl FrameHeader {
+    /// Parse the 4 header bytes of a frame, `None` if they aren't a valid header.
+    /// Free format streams (bitrate index 0) aren't supported.
+    pub fn parse(b: [u8; 4]) -> Option<Self> {
+        if b[0] != 0xFF || b[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+
+        let version = match (b[1] >> 3) & 0b11 {
+            0b00 => MpegVersion::Mpeg25,
+            0b10 => MpegVersion::Mpeg2,
+            0b11 => MpegVersion::Mpeg1,
+            _ => return None,
+        };
+        let layer = match (b[1] >> 1) & 0b11 {
+            0b01 => Layer::Layer3,
+            0b10 => Layer::Layer2,
+            0b11 => Layer::Layer1,
+            _ => return None,
+        };
+
+        let bitrate_index = (b[2] >> 4) as usize;
+        if bitrate_index == 0 || bitrate_index == 15 {
+            return None;
+        }
+        let bitrates = match (version, layer) {
+            (MpegVersion::Mpeg1, Layer::Layer1) => &BITRATES_V1_L1,
+            (MpegVersion::Mpeg1, Layer::Layer2) => &BITRATES_V1_L2,
+            (MpegVersion::Mpeg1, Layer::Layer3) => &BITRATES_V1_L3,
+            (_, Layer::Layer1) => &BITRATES_V2_L1,
+            (_, _) => &BITRATES_V2_L23,
+        };
+
+        let sample_rate = match ((b[2] >> 2) & 0b11, version) {
+            (0b11, _) => return None,
+            (i, MpegVersion::Mpeg1) => [44100, 48000, 32000][i as usize],
+            (i, MpegVersion::Mpeg2) => [22050, 24000, 16000][i as usize],
+            (i, MpegVersion::Mpeg25) => [11025, 12000, 8000][i as usize],
+        };
+
+        let channel_mode = match b[3] >> 6 {
+            0b00 => ChannelMode::Stereo,
+            0b01 => ChannelMode::JointStereo,
+            0b10 => ChannelMode::DualChannel,
+            _ => ChannelMode::Mono,
+        };
+
+        Some(FrameHeader {
+            version,
+            layer,
+            bitrate: bitrates[bitrate_index - 1],
+            sample_rate,
+            padding: b[2] & 0b10 != 0,
+            channel_mode,
+        })
+    }
+
+    /// Length of the whole frame in bytes, header included
+    pub fn frame_len(&self) -> usize {
+        let bitrate = self.bitrate as usize * 1000;
+        let sample_rate = self.sample_rate as usize;
+        let padding = self.padding as usize;
+
+        match (self.layer, self.version) {
+            (Layer::Layer1, _) => (12 * bitrate / sample_rate + padding) * 4,
+            (Layer::Layer3, MpegVersion::Mpeg2 | MpegVersion::Mpeg25) => {
+                72 * bitrate / sample_rate + padding
+            }
+            _ => 144 * bitrate / sample_rate + padding,
+        }
+    }
+
+    /// Number of audio samples (per channel) in the frame
+    pub fn samples(&self) -> u32 {
+        match (self.layer, self.version) {
+            (Layer::Layer1, _) => 384,
+            (Layer::Layer3, MpegVersion::Mpeg2 | MpegVersion::Mpeg25) => 576,
+            _ => 1152,
+        }
+    }
+}
+
+/// Iterator over the frames of an MPEG audio stream, yielding each frame's offset and
+/// header. Bytes that don't start a valid frame are skipped until the stream syncs again.
+pub struct AudioFrames<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> AudioFrames<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        AudioFrames { buf, pos: 0 }
+    }
+}
+
+impl Iterator for AudioFrames<'_> {
+    type Item = (usize, FrameHeader);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos + 4 <= self.buf.len() {
+            let b = self.buf[self.pos..self.pos + 4].try_into().unwrap();
+            match FrameHeader::parse(b) {
+                Some(header) if self.pos + header.frame_len() <= self.buf.len() => {
+                    let offset = self.pos;
+                    self.pos += header.frame_len();
+                    return Some((offset, header));
+                }
+                _ => self.pos += 1,
+            }
+        }
+        None
+    }
+}
+
+/// Offset of the first frame of the stream, skipping leading junk. A lone sync word is
+/// easily found in junk, so a frame only counts when another one follows right after it or
+/// it ends the buffer.
+pub fn first_frame(buf: &[u8]) -> Option<usize> {
+    let header_at =
+        |offset: usize| FrameHeader::parse(buf.get(offset..offset + 4)?.try_into().unwrap());
+
+    (0..buf.len()).find(|&offset| match header_at(offset) {
+        Some(header) => {
+            let next = offset + header.frame_len();
+            next == buf.len() || header_at(next).is_some()
+        }
+        None => false,
+    })
+}
+
+/// How the bitrate of a stream is allocated
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BitrateMode {
+    /// Constant bitrate
+    #[default]
+    Cbr,
+    /// Variable bitrate
+    Vbr,
+    /// Average bitrate, a VBR stream aiming at a target bitrate
+    Abr,
+}
+
+impl fmt::Display for BitrateMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitrateMode::Cbr => write!(f, "CBR"),
+            BitrateMode::Vbr => write!(f, "VBR"),
+            BitrateMode::Abr => write!(f, "ABR"),
+        }
+    }
+}
+
+/// Offset of the Xing header within a frame, after the frame header and side information
+fn xing_offset(header: &FrameHeader) -> usize {
+    match (header.version, header.channel_mode) {
+        (MpegVersion::Mpeg1, ChannelMode::Mono) => 4 + 17,
+        (MpegVersion::Mpeg1, _) => 4 + 32,
+        (_, ChannelMode::Mono) => 4 + 9,
+        (_, _) => 4 + 17,
+    }
+}
+
+/// The Xing or Info header of a frame and whatever follows it, with the offset of the
+/// LAME extension within it
+fn xing_header<'a>(frame: &'a [u8], header: &FrameHeader) -> Option<(&'a [u8], usize)> {
+    let xing = frame.get(xing_offset(header)..)?;
+    if !matches!(xing.get(0..4)?, b"Xing" | b"Info") {
+        return None;
+    }
+
+    // frames, bytes, table of contents and quality fields are each optional
+    let flags = u32::from_be_bytes(xing.get(4..8)?.try_into().unwrap());
+    let lame = 8 + [(0x1, 4), (0x2, 4), (0x4, 100), (0x8, 4)]
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, len)| len)
+        .sum::<usize>();
+    Some((xing, lame))
+}
+
+/// Bitrate mode announced by a Xing, Info or VBRI header in the first frame of a stream.
+/// Encoders write "Info" for CBR streams. The VBR method of a LAME extension following the
+/// Xing header tells ABR apart from VBR.
+pub fn vbr_header_mode(frame: &[u8], header: &FrameHeader) -> Option<BitrateMode> {
+    if frame.get(36..40) == Some(b"VBRI") {
+        return Some(BitrateMode::Vbr);
+    }
+
+    let (xing, lame) = xing_header(frame, header)?;
+    if xing.starts_with(b"Info") {
+        return Some(BitrateMode::Cbr);
+    }
+
+    match xing.get(lame..lame + 10) {
+        Some(ext) if ext.starts_with(b"LAME") && matches!(ext[9] & 0x0F, 2 | 9) => {
+            Some(BitrateMode::Abr)
+        }
+        _ => Some(BitrateMode::Vbr),
+    }
+}
+
+/// Fields of the LAME extension that follows the Xing or Info header of LAME encoded files
+#[derive(Debug, Clone, PartialEq)]
+pub struct LameTag {
+    /// Encoder version, e.g. "LAME3.100"
+    pub encoder: String,
+    /// 1 CBR, 2 ABR, 3 to 6 VBR, 8 and 9 two pass CBR and ABR
+    pub vbr_method: u8,
+    /// Lowpass filter frequency in Hz, 0 if unknown
+    pub lowpass: u32,
+    /// Peak signal amplitude, 1.0 being full scale
+    pub peak: f32,
+    pub radio_gain: Option<RgadAdjustment>,
+    pub audiophile_gain: Option<RgadAdjustment>,
+    /// Samples the encoder added at the start of the stream, for gapless playback
+    pub delay: u16,
+    /// Samples the encoder added at the end of the stream, for gapless playback
+    pub padding: u16,
+}
+
+/// Decode the LAME extension of a frame's Xing or Info header
+pub fn lame_tag(frame: &[u8], header: &FrameHeader) -> Option<LameTag> {
+    let (xing, lame) = xing_header(frame, header)?;
+    let ext = xing.get(lame..lame + 36)?;
+    if !ext.starts_with(b"LAME") {
+        return None;
+    }
+
+    // a gain with no name code was never set
+    let gain = |at: usize| {
+        Some(decode_rgad_adjustment(u16::from_be_bytes([
+            ext[at],
+            ext[at + 1],
+        ])))
+        .filter(|x| x.name != 0)
+    };
+    Some(LameTag {
+        encoder: String::from_utf8_lossy(&ext[0..9])
+            .trim_end_matches(['\0', ' '])
+            .to_string(),
+        vbr_method: ext[9] & 0x0F,
+        lowpass: ext[10] as u32 * 100,
+        // a fixed point number with 23 fractional bits
+        peak: u32::from_be_bytes(ext[11..15].try_into().unwrap()) as f32 / (1 << 23) as f32,
+        radio_gain: gain(15),
+        audiophile_gain: gain(17),
+        delay: (ext[21] as u16) << 4 | (ext[22] >> 4) as u16,
+        padding: (ext[22] as u16 & 0x0F) << 8 | ext[23] as u16,
+    })
+}
+
+/// Summary of an MPEG audio stream
+#[derive(Debug, Default)]
+pub struct StreamInfo {
+    pub frames: usize,
+    pub duration: Duration,
+    /// Number of frames per bitrate (in kbps)
+    pub bitrates: BTreeMap<u32, usize>,
+    /// From the VBR header if there is one, otherwise from whether the bitrate varies
+    pub bitrate_mode: BitrateMode,
+    /// LAME extension of the first frame's Xing or Info header
+    pub lame: Option<LameTag>,
+}
+
+/// Scan every frame of an MPEG audio stream
+pub fn analyze(buf: &[u8]) -> StreamInfo {
+    let mut info = StreamInfo::default();
+    let mut seconds = 0f64;
+
+    let mut announced = None;
+
+    for (offset, header) in AudioFrames::new(buf) {
+        if info.frames == 0 {
+            let frame = &buf[offset..offset + header.frame_len()];
+            announced = vbr_header_mode(frame, &header);
+            info.lame = lame_tag(frame, &header);
+        }
+
+        info.frames += 1;
+        *info.bitrates.entry(header.bitrate).or_default() += 1;
+        seconds += header.samples() as f64 / header.sample_rate as f64;
+    }
+
+    info.duration = Duration::from_secs_f64(seconds);
+    info.bitrate_mode = announced.unwrap_or(match info.bitrates.len() {
+        0 | 1 => BitrateMode::Cbr,
+        _ => BitrateMode::Vbr,
+    });
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    /// MPEG-1 layer III frames at 44.1kHz with the given bitrate index
+    fn frames(bitrate_index: u8, count: usize) -> Vec<u8> {
+        let header = [0xFF, 0xFB, bitrate_index << 4, 0x00];
+        let len = super::FrameHeader::parse(header).unwrap().frame_len();
+        let mut frame = header.to_vec();
+        frame.resize(len, 0);
+        frame.repeat(count)
+    }
+
+    #[test]
+    fn bitrate_histogram() {
+        let mut stream = frames(0x9, 3); // 128 kbps
+        stream.extend(b"junk");
+        stream.extend(frames(0x5, 2)); // 64 kbps
+
+        let info = super::analyze(&stream);
+
+        assert_eq!(info.frames, 5);
+        assert_eq!(
+            info.bitrates.into_iter().collect::<Vec<_>>(),
+            [(64, 2), (128, 3)]
+        );
+        assert_eq!(info.duration.as_millis(), 5 * 1152 * 1000 / 44100);
+    }
+
+    #[test]
+    fn bitrate_modes() {
+        use super::BitrateMode;
+
+        assert_eq!(
+            super::analyze(&frames(0x9, 4)).bitrate_mode,
+            BitrateMode::Cbr
+        );
+
+        let mut stream = frames(0x9, 2);
+        stream.extend(frames(0xB, 2));
+        assert_eq!(super::analyze(&stream).bitrate_mode, BitrateMode::Vbr);
+
+        // a Xing header in the first frame, with a LAME extension announcing ABR
+        let mut stream = frames(0x9, 4);
+        stream[36..44].copy_from_slice(b"Xing\0\0\0\0");
+        assert_eq!(super::analyze(&stream).bitrate_mode, BitrateMode::Vbr);
+        stream[44..54].copy_from_slice(b"LAME3.100\x02");
+        assert_eq!(super::analyze(&stream).bitrate_mode, BitrateMode::Abr);
+    }
+
+    #[test]
+    fn decode_lame_tag() {
+        let mut stream = frames(0x9, 2);
+        stream[36..44].copy_from_slice(b"Info\0\0\0\0");
+        let mut ext = b"LAME3.100".to_vec();
+        ext.extend([0x01, 0xC3]); // CBR, 19.5kHz lowpass
+        ext.extend((1u32 << 22).to_be_bytes()); // peak of 0.5
+        ext.extend([0x2E, 0x41]); // radio gain set automatically, -6.5 dB
+        ext.extend([0x00, 0x00]); // no audiophile gain
+        ext.extend([0x00, 0x80]);
+        ext.extend([0x24, 0x06, 0x3C]); // 576 samples of delay, 1596 of padding
+        ext.resize(36, 0);
+        stream[44..80].copy_from_slice(&ext);
+
+        let lame = super::analyze(&stream).lame.unwrap();
+
+        assert_eq!(lame.encoder, "LAME3.100");
+        assert_eq!(lame.vbr_method, 1);
+        assert_eq!(lame.lowpass, 19500);
+        assert_eq!(lame.peak, 0.5);
+        assert_eq!(lame.radio_gain.unwrap().gain, -6.5);
+        assert_eq!(lame.radio_gain.unwrap().originator, 3);
+        assert!(lame.audiophile_gain.is_none());
+        assert_eq!((lame.delay, lame.padding), (576, 1596));
+    }
+}
@@ -1,19 +1,32 @@
 use std::{
     borrow::Cow,
+    collections::HashMap,
     error::Error,
     fmt::{self, Debug, Display},
     io::{self, BufRead, Read},
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
+use base64::{prelude::BASE64_STANDARD, Engine};
 use encoding::{
-    all::{ISO_8859_1, UTF_16BE, UTF_16LE, UTF_8},
+    all::{ISO_8859_1, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252},
     DecoderTrap, Encoding as EncodingLib,
 };
 
-use crate::AppError;
+use serde::{Serialize, Serializer};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{
+    encoder::encode_size,
+    etco,
+    replaygain::{self, RgadAdjustment},
+    sylt::TimestampFormat,
+    AppError, ErrorKind,
+};
 
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub enum Encoding {
     ISO_8859_1 = 0x00,
     UTF_16 = 0x01,
@@ -29,15 +42,26 @@ impl TryFrom<u8> for Encoding {
             1 => Ok(Encoding::UTF_16),
             2 => Ok(Encoding::UTF_16BE),
             3 => Ok(Encoding::UTF_8),
-            _ => Err(AppError::new("parsing encoding from byte failed")),
+            _ => Err(AppError::with_kind(
+                ErrorKind::Decode,
+                "parsing encoding from byte failed",
+            )),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Content {
     Text(String),
-    Binary(Vec<u8>),
+    /// Multiple values of a v2.4 text frame, which are separated by null terminators
+    TextList(Vec<String>),
+    Binary(#[serde(serialize_with = "base64")] Vec<u8>),
+}
+
+/// Serialize binary data as a base64 string
+fn base64<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&BASE64_STANDARD.encode(data))
 }
 
 impl From<Vec<u8>> for Content {
@@ -46,7 +70,8 @@ impl From<Vec<u8>> for Content {
     }
 }
 
-#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum PictureType {
     Other = 0,
     Icon = 1,
@@ -71,14 +96,108 @@ pub enum PictureType {
     PublisherLogo = 20,
 }
 
+/// Every picture type in the order of its byte, with its name on the command line and the
+/// description the spec gives it
+const PICTURE_TYPES: [(PictureType, &str, &str); 21] = [
+    (PictureType::Other, "other", "Other"),
+    (
+        PictureType::Icon,
+        "icon",
+        "32x32 pixels file icon (PNG only)",
+    ),
+    (PictureType::IconOther, "icon-other", "Other file icon"),
+    (PictureType::CoverFront, "cover-front", "Cover (front)"),
+    (PictureType::CoverBack, "cover-back", "Cover (back)"),
+    (PictureType::Leaflet, "leaflet", "Leaflet page"),
+    (PictureType::Media, "media", "Media (e.g. label side of CD)"),
+    (
+        PictureType::LeadArtist,
+        "lead-artist",
+        "Lead artist/lead performer/soloist",
+    ),
+    (PictureType::Artist, "artist", "Artist/performer"),
+    (PictureType::Conductor, "conductor", "Conductor"),
+    (PictureType::Band, "band", "Band/Orchestra"),
+    (PictureType::Composer, "composer", "Composer"),
+    (PictureType::Lyricist, "lyricist", "Lyricist/text writer"),
+    (
+        PictureType::RecordingLocation,
+        "recording-location",
+        "Recording Location",
+    ),
+    (
+        PictureType::DuringRecording,
+        "during-recording",
+        "During recording",
+    ),
+    (
+        PictureType::DuringPerformance,
+        "during-performance",
+        "During performance",
+    ),
+    (
+        PictureType::ScreenCapture,
+        "screen-capture",
+        "Movie/video screen capture",
+    ),
+    (
+        PictureType::BrightFish,
+        "bright-fish",
+        "A bright coloured fish",
+    ),
+    (PictureType::Illustration, "illustration", "Illustration"),
+    (PictureType::BandLogo, "band-logo", "Band/artist logotype"),
+    (
+        PictureType::PublisherLogo,
+        "publisher-logo",
+        "Publisher/Studio logotype",
+    ),
+];
+
+impl PictureType {
+    /// Picture type of an APIC frame's picture type byte, if it's a known one
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        PICTURE_TYPES.get(byte as usize).map(|x| x.0)
+    }
+
+    pub fn as_byte(self) -> u8 {
+        self as u8
+    }
+
+    /// Name as given on the command line and in file names, e.g. "cover-front"
+    pub fn name(self) -> &'static str {
+        PICTURE_TYPES[self as usize].1
+    }
+
+    /// Description from the spec, e.g. "Cover (front)"
+    pub fn description(self) -> &'static str {
+        PICTURE_TYPES[self as usize].2
+    }
+}
+
 impl TryFrom<u8> for PictureType {
     type Error = ();
     fn try_from(value: u8) -> Result<Self, Self::Error> {
-        unsafe { std::mem::transmute(value) }
+        PictureType::from_byte(value).ok_or(())
     }
 }
 
-#[derive(Debug)]
+/// Broad category of what a frame holds
+#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq, Eq)]
+pub enum FrameKind {
+    Text,
+    Url,
+    Picture,
+    Lyrics,
+    Comment,
+    Identifier,
+    Commercial,
+    Timing,
+    Binary,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Frame {
     /// Unsynchronised lyrics/text transcription
     Uslt {
@@ -86,40 +205,340 @@ pub enum Frame {
         language: String,
         description: String,
     },
+    /// Comment
+    Comm {
+        text: String,
+        language: String,
+        description: String,
+    },
     /// Attached picture
     Apic {
+        #[serde(serialize_with = "base64")]
         data: Vec<u8>,
+        mime_type: String,
         picture_type: PictureType,
         description: String,
     },
+    /// Ownership, what was paid for the file and to whom
+    Owne {
+        price: String,
+        /// Date of purchase as YYYYMMDD
+        date: String,
+        seller: String,
+    },
+    /// Commercial offer for the file
+    Comr {
+        price: String,
+        /// Date the price is valid until as YYYYMMDD
+        valid_until: String,
+        contact_url: String,
+        received_as: u8,
+        seller: String,
+        description: String,
+        logo_mime_type: String,
+        #[serde(serialize_with = "base64")]
+        logo: Vec<u8>,
+    },
+    /// Event timing codes, event types with the time they happen at
+    Etco {
+        timestamp_format: TimestampFormat,
+        events: Vec<(u8, u32)>,
+    },
+    /// Legacy ReplayGain adjustment, from before TXXX frames were used for it
+    Rgad {
+        peak: f32,
+        radio: RgadAdjustment,
+        audiophile: RgadAdjustment,
+    },
+    /// Registration of an encryption method, which encrypted frames refer to by its symbol
+    Encr {
+        owner: String,
+        method: u8,
+        data: Vec<u8>,
+    },
+    /// Position synchronisation, how far into the audio a stream currently is
+    Poss {
+        timestamp_format: TimestampFormat,
+        position: u64,
+    },
+    /// Minimum offset from the end of this tag to the next one
+    Seek {
+        offset: u32,
+    },
+    /// Audio seek point index, offsets of evenly spaced points in time within the audio data,
+    /// as fractions of its length
+    Aspi {
+        data_start: u32,
+        data_length: u32,
+        bits_per_point: u8,
+        points: Vec<u16>,
+    },
+    /// MPEG location lookup table, how far every reference point deviates from where the
+    /// fixed spacing between references puts it
+    Mllt {
+        frames_between_reference: u16,
+        bytes_between_reference: u32,
+        ms_between_reference: u32,
+        bits_for_bytes_deviation: u8,
+        bits_for_ms_deviation: u8,
+        /// Bytes and milliseconds deviation of every reference
+        deviations: Vec<(u32, u32)>,
+    },
+    /// A frame encrypted with a method registered by an ENCR frame. Without the key there's no
+    /// telling what it holds, so it's kept as stored, along with what its format flags say.
+    Encrypted {
+        id: String,
+        /// Symbol of the ENCR frame registering the method
+        method: u8,
+        /// Group the frame belongs to, if any
+        group: Option<u8>,
+        /// Whether the data was compressed before it was encrypted
+        compressed: bool,
+        /// Size of the frame once decrypted and decompressed, when given
+        data_length: Option<u32>,
+        #[serde(serialize_with = "base64")]
+        data: Vec<u8>,
+    },
     Other {
         id: String,
         content: Content,
     },
 }
 
+impl Frame {
+    /// Frame id, which typed variants don't store themselves
+    pub fn id(&self) -> &str {
+        match self {
+            Frame::Uslt { .. } => "USLT",
+            Frame::Comm { .. } => "COMM",
+            Frame::Apic { .. } => "APIC",
+            Frame::Owne { .. } => "OWNE",
+            Frame::Comr { .. } => "COMR",
+            Frame::Etco { .. } => "ETCO",
+            Frame::Rgad { .. } => "RGAD",
+            Frame::Encr { .. } => "ENCR",
+            Frame::Poss { .. } => "POSS",
+            Frame::Seek { .. } => "SEEK",
+            Frame::Aspi { .. } => "ASPI",
+            Frame::Mllt { .. } => "MLLT",
+            Frame::Encrypted { id, .. } | Frame::Other { id, .. } => id,
+        }
+    }
+
+    /// Classify the frame by its content, going by the frame id for untyped frames
+    pub fn kind(&self) -> FrameKind {
+        match self {
+            Frame::Uslt { .. } => FrameKind::Lyrics,
+            Frame::Comm { .. } => FrameKind::Comment,
+            Frame::Apic { .. } => FrameKind::Picture,
+            Frame::Owne { .. } | Frame::Comr { .. } => FrameKind::Commercial,
+            Frame::Rgad { .. } | Frame::Encr { .. } | Frame::Encrypted { .. } => FrameKind::Binary,
+            Frame::Etco { .. }
+            | Frame::Poss { .. }
+            | Frame::Seek { .. }
+            | Frame::Aspi { .. }
+            | Frame::Mllt { .. } => FrameKind::Timing,
+            Frame::Other { id, .. } => match id.as_str() {
+                "COMM" => FrameKind::Comment,
+                "SYLT" => FrameKind::Lyrics,
+                "UFID" | "TSRC" | "MCDI" => FrameKind::Identifier,
+                "USER" | "WCOM" | "WPAY" => FrameKind::Commercial,
+                _ if id.starts_with('T') => FrameKind::Text,
+                _ if id.starts_with('W') => FrameKind::Url,
+                _ => FrameKind::Binary,
+            },
+        }
+    }
+
+    /// Whether this is a lyrics or comment frame in the given ISO-639-2 language, compared
+    /// case-insensitively. Other frames have no language, so match any.
+    pub fn in_language(&self, lang: &str) -> bool {
+        match self {
+            Frame::Uslt { language, .. } | Frame::Comm { language, .. } => {
+                language.eq_ignore_ascii_case(lang)
+            }
+            _ => true,
+        }
+    }
+
+    /// An embedded picture as a `data:` URI, e.g. for use in HTML. A bare mime type like
+    /// "jpeg" is taken to be an image type.
+    pub fn data_uri(&self) -> Option<String> {
+        match self {
+            Frame::Apic {
+                data, mime_type, ..
+            } if self.linked_picture_url().is_none() => {
+                let mime_type = match mime_type.contains('/') {
+                    true => mime_type.to_lowercase(),
+                    false => format!("image/{}", mime_type.to_lowercase()),
+                };
+                Some(format!(
+                    "data:{};base64,{}",
+                    mime_type,
+                    BASE64_STANDARD.encode(data)
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether this is an attached picture with a header but no image data, which broken
+    /// taggers sometimes write
+    pub fn is_empty_picture(&self) -> bool {
+        matches!(self, Frame::Apic { data, .. } if data.is_empty())
+    }
+
+    /// URL of an attached picture that links to an external image (mime type "-->")
+    /// instead of embedding the image data
+    pub fn linked_picture_url(&self) -> Option<String> {
+        match self {
+            Frame::Apic {
+                data, mime_type, ..
+            } if mime_type == "-->" => Some(
+                decode_str(data, Encoding::ISO_8859_1)
+                    .unwrap_or_default()
+                    .trim_end_matches('\0')
+                    .to_string(),
+            ),
+            _ => None,
+        }
+    }
+}
+
 impl Display for Frame {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Frame::Other { content, .. } => {
-                    match content {
-                        Content::Text(txt) => txt,
-                        Content::Binary(_) => "(binary data)",
-                    }
-                }
-                Frame::Uslt { text, .. } => {
-                    text
-                }
-                Frame::Apic { .. } => "(pic)",
+        match self {
+            Frame::Other { content, .. } => match content {
+                Content::Text(txt) => write!(f, "{}", txt),
+                Content::TextList(values) => write!(f, "{}", values.join("; ")),
+                Content::Binary(b) => write!(f, "(binary {} bytes)", b.len()),
+            },
+            Frame::Uslt { text, .. } | Frame::Comm { text, .. } => write!(f, "{}", text),
+            Frame::Apic { .. } => write!(f, "(pic)"),
+            Frame::Owne {
+                price,
+                date,
+                seller,
+            } => write!(f, "{} on {} from {}", price, date, seller),
+            Frame::Comr {
+                price,
+                valid_until,
+                seller,
+                ..
+            } => write!(f, "{} until {} from {}", price, valid_until, seller),
+            Frame::Etco {
+                timestamp_format,
+                events,
+            } => {
+                let events: Vec<String> = events
+                    .iter()
+                    .map(|(event, timestamp)| {
+                        format!(
+                            "{} @ {}",
+                            etco::event_name(*event),
+                            etco::format_timestamp(*timestamp, *timestamp_format)
+                        )
+                    })
+                    .collect();
+                write!(f, "{}", events.join("; "))
             }
-        )
+            Frame::Rgad {
+                peak,
+                radio,
+                audiophile,
+            } => write!(
+                f,
+                "peak {:.6}, radio {:+.1} dB, audiophile {:+.1} dB",
+                peak, radio.gain, audiophile.gain
+            ),
+            Frame::Encr { owner, method, .. } => {
+                write!(f, "method {} registered by {}", method, owner)
+            }
+            Frame::Poss {
+                timestamp_format,
+                position,
+            } => write!(
+                f,
+                "position {}",
+                etco::format_timestamp(*position, *timestamp_format)
+            ),
+            Frame::Seek { offset } => write!(f, "next tag at +{} bytes", offset),
+            Frame::Aspi { points, .. } => write!(f, "{} seek points", points.len()),
+            Frame::Mllt {
+                frames_between_reference,
+                deviations,
+                ..
+            } => write!(
+                f,
+                "{} references every {} frames",
+                deviations.len(),
+                frames_between_reference
+            ),
+            Frame::Encrypted { method, data, .. } => {
+                write!(
+                    f,
+                    "(encrypted with method {}, {} bytes)",
+                    method,
+                    data.len()
+                )
+            }
+        }
+    }
+}
+
+/// Where the frames of a tag end and its padding begins
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PaddingPolicy {
+    /// Stop at the first frame id made of four zero bytes
+    #[default]
+    Strict,
+    /// Look past zero bytes for more frames, until the end of the tag
+    ScanToEnd,
+    /// As the spec has it: a zero byte where a frame id should be starts the padding, and
+    /// everything after it must be zero
+    Spec,
+}
+
+/// Knobs for decoding tags that deviate from the spec in common ways
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Decode ISO-8859-1 text as Windows-1252, which is what most "latin-1" taggers write
+    pub cp1252: bool,
+    /// Apply Unicode NFC normalization to decoded text, so that precomposed and
+    /// decomposed spellings of the same name compare equal
+    pub normalize: bool,
+    /// Skip over frames that can't be decoded, resuming at the next valid looking frame
+    pub recover: bool,
+    /// Time how long every frame takes to decode
+    pub profile: bool,
+    /// How to tell the padding from the frames
+    pub padding: PaddingPolicy,
+    /// Leave the image data out of attached pictures, for callers that only need their type
+    /// and mime type. The length of the image is still in the frame's size.
+    pub skip_picture_data: bool,
+    /// Trim trailing nulls and whitespace off text values, on by default
+    pub trim: bool,
+    /// How the frames are decoded, by their id. Custom decoders can be registered for
+    /// proprietary frames.
+    pub decoders: FrameDecoders,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            cp1252: false,
+            normalize: false,
+            recover: false,
+            profile: false,
+            padding: PaddingPolicy::default(),
+            skip_picture_data: false,
+            trim: true,
+            decoders: FrameDecoders::default(),
+        }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Header {
     pub version: u8,
     pub revision: u8,
@@ -130,10 +549,295 @@ pub struct Header {
     pub size: u32,
 }
 
-#[derive(Debug)]
+/// A recoverable oddity found while parsing a tag
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Warning {
+    pub frame_id: Option<String>,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(frame_id: Option<&str>, message: impl Into<String>) -> Self {
+        Warning {
+            frame_id: frame_id.map(|x| x.into()),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.frame_id {
+            Some(id) => write!(f, "{}: {}", id, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Tag {
     pub header: Header,
     pub frames: Vec<Frame>,
+    pub warnings: Vec<Warning>,
+    /// Byte offset from the start of the file of every frame as it was read, empty for tags
+    /// that weren't read from a file
+    pub offsets: Vec<u64>,
+    /// Declared and decoded size of every frame as it was read, empty for tags that weren't
+    /// read from a file
+    pub sizes: Vec<FrameSize>,
+    /// Text encoding of every frame as it was read, for the frames with an encoding byte
+    #[serde(skip)]
+    pub encodings: Vec<Option<Encoding>>,
+    /// How long every frame took to decode, only measured when profiling
+    #[serde(skip)]
+    pub timings: Vec<Duration>,
+    /// Frames filled in from the tags at the end of the file for what the ID3v2 tag lacks.
+    /// The accessors fall back to them, but they're never written back as ID3v2 frames.
+    pub trailer: Vec<Frame>,
+}
+
+/// Size of a frame's body as declared in its header, and the number of bytes decoding it
+/// actually took up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FrameSize {
+    pub declared: usize,
+    pub consumed: usize,
+}
+
+impl FrameSize {
+    pub fn matches(&self) -> bool {
+        self.declared == self.consumed
+    }
+
+    /// Whether the frame was cut short by the end of the tag
+    pub fn truncated(&self) -> bool {
+        self.consumed < self.declared
+    }
+}
+
+impl Tag {
+    /// Number of bytes the tag occupies on disk, i.e. the offset where the audio starts for a
+    /// tag at the beginning of the file. The extended header and padding are already counted
+    /// in the header's size field, only the header itself and the optional footer are not.
+    pub fn total_size(&self) -> u64 {
+        let footer = if self.header.footer_present { 10 } else { 0 };
+        10 + self.header.size as u64 + footer
+    }
+
+    /// Number of frames read from the tag, leaving out the ones filled in from Lyrics3 or APE
+    /// tags
+    pub fn frame_count(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// Number of bytes the frames read from the tag occupy, headers included. Whatever else
+    /// the header's size field counts is padding and the extended header, room a tag written
+    /// back in place can grow into.
+    pub fn frame_bytes(&self) -> u64 {
+        self.sizes.iter().map(|x| 10 + x.consumed as u64).sum()
+    }
+
+    /// Decoded text of the first text frame with the given id. For frames with multiple
+    /// values, this is the first value.
+    pub fn text(&self, id: &str) -> Option<&str> {
+        self.frames
+            .iter()
+            .chain(&self.trailer)
+            .find_map(|x| match x {
+                Frame::Other { id: tid, content } if tid == id => match content {
+                    Content::Text(txt) => Some(txt.as_str()),
+                    Content::TextList(values) => values.first().map(|x| x.as_str()),
+                    Content::Binary(_) => None,
+                },
+                _ => None,
+            })
+    }
+
+    /// Text of the first comment, or of the first comment in the given language
+    pub fn comment(&self, lang: Option<&str>) -> Option<&str> {
+        self.frames
+            .iter()
+            .chain(&self.trailer)
+            .find_map(|x| match x {
+                Frame::Comm { text, .. } if lang.is_none_or(|lang| x.in_language(lang)) => {
+                    Some(text.as_str())
+                }
+                // set as plain text, so without a language
+                Frame::Other {
+                    id,
+                    content: Content::Text(text),
+                } if id == "COMM" && lang.is_none() => Some(text.as_str()),
+                _ => None,
+            })
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.text("TIT2")
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        self.text("TPE1")
+    }
+
+    pub fn album(&self) -> Option<&str> {
+        self.text("TALB")
+    }
+
+    /// TYER only exists in v2.3, v2.4 replaced it with the TDRC timestamp
+    pub fn year(&self) -> Option<&str> {
+        self.text("TYER").or_else(|| self.text("TDRC"))
+    }
+
+    /// Value of the first TXXX frame with the given description, compared case insensitively
+    pub fn user_text(&self, description: &str) -> Option<&str> {
+        self.frames_by_id("TXXX").find_map(|x| match x {
+            Frame::Other {
+                content: Content::Text(text),
+                ..
+            } => match text.split_once('\0') {
+                Some((desc, value)) if desc.eq_ignore_ascii_case(description) => Some(value),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    pub fn track_number(&self) -> Option<u32> {
+        parse_track(self.text("TRCK")?).map(|(number, _)| number)
+    }
+
+    /// Track total from TRCK, or from the TXXX frames some taggers store it in separately
+    pub fn track_total(&self) -> Option<u32> {
+        self.text("TRCK")
+            .and_then(parse_track)
+            .and_then(|(_, total)| total)
+            .or_else(|| {
+                ["TRACKTOTAL", "TOTALTRACKS"]
+                    .into_iter()
+                    .find_map(|x| self.user_text(x)?.trim_matches(trim_pattern).parse().ok())
+            })
+    }
+
+    /// Beats per minute, if TBPM holds a valid integer
+    pub fn bpm(&self) -> Option<u32> {
+        self.text("TBPM")?.trim_matches(trim_pattern).parse().ok()
+    }
+
+    /// Initial musical key, e.g. "Am"
+    pub fn key(&self) -> Option<&str> {
+        self.text("TKEY")
+    }
+
+    /// Software and settings the audio was encoded with, e.g. "LAME 3.100 -V2"
+    pub fn encoder_settings(&self) -> Option<&str> {
+        self.text("TSSE")
+    }
+
+    /// Person or organisation that encoded the file
+    pub fn encoded_by(&self) -> Option<&str> {
+        self.text("TENC")
+    }
+
+    /// How often the file was played, from PCNT or else from the counter of a POPM frame
+    pub fn play_count(&self) -> Option<u64> {
+        let counter = |id, skip: fn(&[u8]) -> Option<&[u8]>| {
+            self.frames_by_id(id).find_map(|x| match x {
+                Frame::Other {
+                    content: Content::Binary(b),
+                    ..
+                } => skip(b).filter(|x| !x.is_empty()).map(read_counter),
+                _ => None,
+            })
+        };
+
+        counter("PCNT", |b| Some(b)).or_else(|| {
+            // POPM is an email address and a rating byte ahead of the counter
+            counter("POPM", |b| {
+                let (_, rest) = take_terminated(b, Encoding::ISO_8859_1);
+                rest.get(1..)
+            })
+        })
+    }
+
+    /// Replace the first frame with the given id by a text frame, or add one
+    pub fn set_text(&mut self, id: &str, value: &str) {
+        let frame = Frame::Other {
+            id: id.into(),
+            content: Content::Text(value.into()),
+        };
+        match self.frames.iter_mut().find(|x| x.id() == id) {
+            Some(x) => *x = frame,
+            None => self.frames.push(frame),
+        }
+    }
+
+    /// All frames with the given id, in file order, followed by those filled in from the
+    /// trailing tags
+    pub fn frames_by_id<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a Frame> + 'a {
+        self.frames
+            .iter()
+            .chain(&self.trailer)
+            .filter(move |x| x.id() == id)
+    }
+
+    /// The picture cut short by the end of a truncated file
+    pub fn truncated_picture(&self) -> Option<&Frame> {
+        self.frames
+            .iter()
+            .zip(&self.sizes)
+            .find(|(frame, size)| size.truncated() && matches!(frame, Frame::Apic { .. }))
+            .map(|(frame, _)| frame)
+    }
+
+    /// All attached picture frames, in file order
+    pub fn pictures(&self) -> Vec<&Frame> {
+        self.frames
+            .iter()
+            .filter(|x| matches!(x, Frame::Apic { .. }))
+            .collect()
+    }
+
+    /// The first attached picture of the given type
+    pub fn picture(&self, picture_type: PictureType) -> Option<&Frame> {
+        self.frames.iter().find(|x| {
+            matches!(x, Frame::Apic {
+                picture_type: ptype,
+                ..
+            } if &picture_type == ptype)
+        })
+    }
+}
+
+fn trim_pattern(c: char) -> bool {
+    c == '\0' || c.is_whitespace()
+}
+
+/// Parse a track (or part of a set) value like "3", "3/12" or " 03 / 12 " into its number
+/// and optional total
+pub fn parse_track(value: &str) -> Option<(u32, Option<u32>)> {
+    let value = value.trim_matches(trim_pattern);
+    match value.split_once('/') {
+        Some((number, total)) => Some((
+            number.trim().parse().ok()?,
+            total.trim_matches(trim_pattern).parse().ok(),
+        )),
+        None => Some((value.parse().ok()?, None)),
+    }
+}
+
+/// File extension commonly used for a picture's mime type
+pub fn mime_extension(mime_type: &str) -> &str {
+    match mime_type.to_ascii_lowercase().as_str() {
+        "image/jpeg" | "image/jpg" | "jpg" => "jpg",
+        "image/png" | "png" => "png",
+        "image/gif" => "gif",
+        "image/bmp" => "bmp",
+        "image/webp" => "webp",
+        _ => match mime_type.split_once('/') {
+            Some((_, subtype)) if !subtype.is_empty() => subtype,
+            _ => "bin",
+        },
+    }
 }
 
 pub(crate) fn is_bit_set(flag: u8, index: u8) -> bool {
@@ -144,6 +848,30 @@ pub(crate) fn byte_int(buf: &[u8]) -> u32 {
     u32::from_be_bytes(buf.try_into().unwrap())
 }
 
+/// Read a big endian counter of any length, as used by PCNT and POPM, saturating at
+/// u64::MAX
+pub fn read_counter(buf: &[u8]) -> u64 {
+    buf.iter().fold(0u64, |acc, &x| {
+        acc.checked_mul(256)
+            .and_then(|acc| acc.checked_add(x as u64))
+            .unwrap_or(u64::MAX)
+    })
+}
+
+/// Undo unsynchronisation, which put a zero byte after every 0xFF so that nothing in the tag
+/// could pass for an MPEG sync word
+pub fn resynchronise(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut after_ff = false;
+    for &x in data {
+        if !(after_ff && x == 0) {
+            out.push(x);
+        }
+        after_ff = x == 0xFF;
+    }
+    out
+}
+
 pub(crate) fn byte_int_unsynch(buf: &[u8]) -> u32 {
     let be_int = byte_int(buf);
     be_int & 0xFF | (be_int & 0xFF00) >> 1 | (be_int & 0xFF_0000) >> 2 | (be_int & 0xFF00_0000) >> 3
@@ -181,20 +909,42 @@ fn consume_null_terminated_str_bytes(
 pub(crate) fn decode_str(buf: &[u8], encoding: Encoding) -> Result<String, Cow<'static, str>> {
     match encoding {
         Encoding::UTF_8 => UTF_8.decode(buf, DecoderTrap::Strict),
-        Encoding::UTF_16 => UTF_16LE.decode(buf, DecoderTrap::Strict),
+        // every UTF-16 string carries its own byte order mark
+        Encoding::UTF_16 => match buf {
+            [0xFE, 0xFF, rest @ ..] => UTF_16BE.decode(rest, DecoderTrap::Strict),
+            [0xFF, 0xFE, rest @ ..] => UTF_16LE.decode(rest, DecoderTrap::Strict),
+            _ => UTF_16LE.decode(buf, DecoderTrap::Strict),
+        },
         Encoding::UTF_16BE => UTF_16BE.decode(buf, DecoderTrap::Strict),
         Encoding::ISO_8859_1 => ISO_8859_1.decode(buf, DecoderTrap::Strict),
     }
 }
 
-fn consume_utf16_str_bytes(buf: &mut impl Read) -> Vec<u8> {
+/// Read the 3 byte language of a lyrics or comment frame. Broken taggers leave it null or
+/// fill it with garbage, which is read as "XXX" (unknown language) instead of failing.
+fn consume_language(buf: &mut impl Read, context: &mut FrameContext) -> io::Result<String> {
+    let b = consume_bytes(buf, 3)?;
+    // latin-1 maps every byte to a char, so nothing is lost in the warning either
+    let language: String = b.iter().map(|&x| x as char).collect();
+    if b.iter().all(u8::is_ascii_alphabetic) {
+        return Ok(language);
+    }
+
+    context.warn(format!(
+        "language {:?} isn't a language code, read as XXX",
+        language
+    ));
+    Ok("XXX".into())
+}
+
+fn consume_utf16_str_bytes(buf: &mut impl BufRead) -> Vec<u8> {
     let mut strbuf: Vec<u8> = Vec::new();
 
     let mut last_byte = None;
     for b in buf.bytes() {
         let b = b.unwrap();
         match last_byte {
-            Some(last_byte) if strbuf.len() % 2 != 0 && last_byte == 0x0 && b == 0x0 => {
+            Some(last_byte) if !strbuf.len().is_multiple_of(2) && last_byte == 0x0 && b == 0x0 => {
                 strbuf.push(b);
                 break;
             }
@@ -208,22 +958,157 @@ fn consume_utf16_str_bytes(buf: &mut impl Read) -> Vec<u8> {
     strbuf
 }
 
-pub(crate) fn read_text_from_buf(
-    buf: &mut impl Read,
-    size: usize,
+/// Decode frame text, honouring the parse options
+pub(crate) fn decode_text(
+    buf: &[u8],
     encoding: Encoding,
+    options: &ParseOptions,
 ) -> Result<String, Cow<'static, str>> {
-    let b = consume_bytes(buf, size).expect("couldn't consume bytes");
-    decode_str(&b, encoding)
+    let text = match encoding {
+        Encoding::ISO_8859_1 if options.cp1252 => WINDOWS_1252.decode(buf, DecoderTrap::Strict),
+        _ => decode_str(buf, encoding),
+    }?;
+
+    Ok(if options.normalize {
+        text.nfc().collect()
+    } else {
+        text
+    })
 }
 
-pub fn decode_header(buf: [u8; 10]) -> Result<Header, Box<dyn Error>> {
-    let magic_str = String::from_utf8(buf[0..3].into())?;
-    match magic_str {
-        ref str if str == "ID3" => {}
-        _ => return Err(AppError::new("ID3 tag should be at the start of file!")),
+/// Trim off the trailing nulls and whitespace that fixed width sources and sloppy encoders
+/// leave in text values, unless the options say otherwise
+fn trim_value(mut text: String, options: &ParseOptions) -> String {
+    if options.trim {
+        let end = text
+            .trim_end_matches(|x: char| x == '\0' || x.is_whitespace())
+            .len();
+        text.truncate(end);
+    }
+    text
+}
+
+/// Split a null terminated string off the front of a buffer, returning the string without
+/// its terminator and the rest of the buffer. Without a terminator, the whole buffer is the
+/// string.
+pub(crate) fn take_terminated(buf: &[u8], encoding: Encoding) -> (&[u8], &[u8]) {
+    let end = match encoding {
+        Encoding::UTF_8 | Encoding::ISO_8859_1 => buf.iter().position(|&x| x == 0x0),
+        Encoding::UTF_16 | Encoding::UTF_16BE => buf
+            .chunks_exact(2)
+            .position(|x| x == [0x0, 0x0])
+            .map(|x| x * 2),
     };
 
+    match end {
+        Some(end) => {
+            let terminator = match encoding {
+                Encoding::UTF_8 | Encoding::ISO_8859_1 => 1,
+                Encoding::UTF_16 | Encoding::UTF_16BE => 2,
+            };
+            (&buf[..end], &buf[end + terminator..])
+        }
+        None => (buf, &[]),
+    }
+}
+
+/// Split a buffer of null terminated strings into the individual strings, without the
+/// terminators. UTF-16 terminators are only recognised on code unit boundaries.
+pub(crate) fn split_terminated(buf: &[u8], encoding: Encoding) -> Vec<&[u8]> {
+    let mut values = Vec::new();
+    let mut start = 0;
+
+    match encoding {
+        Encoding::UTF_8 | Encoding::ISO_8859_1 => {
+            for (i, b) in buf.iter().enumerate() {
+                if *b == 0x0 {
+                    values.push(&buf[start..i]);
+                    start = i + 1;
+                }
+            }
+        }
+        Encoding::UTF_16 | Encoding::UTF_16BE => {
+            for i in (0..buf.len().saturating_sub(1)).step_by(2) {
+                if buf[i] == 0x0 && buf[i + 1] == 0x0 {
+                    values.push(&buf[start..i]);
+                    start = i + 2;
+                }
+            }
+        }
+    }
+
+    // anything after the last terminator is a value too, unless the buffer ended with one
+    if start < buf.len() || values.is_empty() {
+        values.push(&buf[start..]);
+    }
+
+    values
+}
+
+/// Split a fixed length field off the front of a buffer, or as much of it as there is
+fn take_fixed(buf: &[u8], len: usize) -> (&[u8], &[u8]) {
+    buf.split_at(len.min(buf.len()))
+}
+
+/// Decode the body of an OWNE frame, after the encoding byte
+fn decode_owne(
+    buf: &[u8],
+    encoding: Encoding,
+    options: &ParseOptions,
+) -> Result<Frame, Box<dyn Error>> {
+    let (price, rest) = take_terminated(buf, Encoding::ISO_8859_1);
+    let (date, seller) = take_fixed(rest, 8);
+
+    Ok(Frame::Owne {
+        price: decode_str(price, Encoding::ISO_8859_1)?,
+        date: decode_str(date, Encoding::ISO_8859_1)?,
+        seller: decode_text(seller, encoding, options)?,
+    })
+}
+
+/// Decode the body of a COMR frame, after the encoding byte
+fn decode_comr(
+    buf: &[u8],
+    encoding: Encoding,
+    options: &ParseOptions,
+) -> Result<Frame, Box<dyn Error>> {
+    let (price, rest) = take_terminated(buf, Encoding::ISO_8859_1);
+    let (valid_until, rest) = take_fixed(rest, 8);
+    let (contact_url, rest) = take_terminated(rest, Encoding::ISO_8859_1);
+    let (received_as, rest) = take_fixed(rest, 1);
+    let (seller, rest) = take_terminated(rest, encoding);
+    let (description, rest) = take_terminated(rest, encoding);
+    let (logo_mime_type, logo) = take_terminated(rest, Encoding::ISO_8859_1);
+
+    Ok(Frame::Comr {
+        price: decode_str(price, Encoding::ISO_8859_1)?,
+        valid_until: decode_str(valid_until, Encoding::ISO_8859_1)?,
+        contact_url: decode_str(contact_url, Encoding::ISO_8859_1)?,
+        received_as: received_as.first().copied().unwrap_or_default(),
+        seller: decode_text(seller, encoding, options)?,
+        description: decode_text(description, encoding, options)?,
+        logo_mime_type: decode_str(logo_mime_type, Encoding::ISO_8859_1)?,
+        logo: logo.to_vec(),
+    })
+}
+
+/// Whether the body of a frame starts with a text encoding byte. Only frames holding text
+/// do; URL frames other than WXXX are always latin-1.
+pub(crate) fn has_encoding_byte(id: &str) -> bool {
+    matches!(
+        id,
+        "WXXX" | "IPLS" | "COMM" | "USLT" | "SYLT" | "APIC" | "GEOB" | "USER" | "OWNE" | "COMR"
+    ) || id.starts_with('T')
+}
+
+pub fn decode_header(buf: [u8; 10]) -> Result<Header, Box<dyn Error>> {
+    if &buf[0..3] != b"ID3" {
+        return Err(AppError::with_kind(
+            ErrorKind::NotId3,
+            "ID3 tag should be at the start of file!",
+        ));
+    }
+
     let version = buf[3];
 
     let revision = buf[4];
@@ -253,178 +1138,795 @@ pub fn decode_extended_header(_buf: Vec<u8>) -> Result<(), Box<dyn Error>> {
     todo!();
 }
 
-pub fn decode_frames(buf: Vec<u8>, v4: bool) -> Result<Vec<Frame>, Box<dyn Error>> {
-    let mut buf = io::Cursor::new(buf);
-    let mut frames: Vec<Frame> = Vec::new();
+/// Number of bytes the extended header takes up, its size field included, read from that
+/// size field. v2.3 leaves the field out of the size, which is 6 bytes or 10 with a CRC;
+/// v2.4 counts it, in a synchsafe size of at least 6. `None` for a size that can't be right.
+pub fn extended_header_size(size_field: &[u8], version: u8) -> Option<usize> {
+    let size_field = size_field.get(..4)?;
+    match version {
+        4 => {
+            let size = byte_int_unsynch(size_field) as usize;
+            let synchsafe = size_field.iter().all(|x| x & 0x80 == 0);
+            (synchsafe && size >= 6).then_some(size)
+        }
+        _ => match byte_int(size_field) {
+            size @ (6 | 10) => Some(size as usize + 4),
+            _ => None,
+        },
+    }
+}
 
-    loop {
-        let id = {
-            let b = consume_bytes(&mut buf, 4)?;
-            String::from_utf8(b).unwrap_or("INVALID".into())
-        };
+/// Decode the body of an MLLT frame: the spacing of references in frames, bytes and
+/// milliseconds, the size of the deviations in bits and the deviations themselves, packed
+/// back to back
+fn decode_mllt(buf: &[u8]) -> Option<Frame> {
+    let header = buf.get(..10)?;
+    let bytes_bits = header[8];
+    let ms_bits = header[9];
+    if bytes_bits > 32 || ms_bits > 32 || bytes_bits as u32 + ms_bits as u32 == 0 {
+        return None;
+    }
 
-        let size = {
-            let b = consume_bytes(&mut buf, 4)?;
-            if v4 {
-                byte_int_unsynch(&b) as usize
-            } else {
-                byte_int(&b) as usize
-            }
-        };
+    let packed = &buf[10..];
+    let mut bit = 0;
+    let mut read_bits = |count: u8| {
+        let mut value = 0u32;
+        for _ in 0..count {
+            let byte = packed[bit / 8];
+            value = value << 1 | (byte >> (7 - bit % 8) & 1) as u32;
+            bit += 1;
+        }
+        value
+    };
 
-        let _flags = consume_bytes(&mut buf, 2)?; // TODO: actually parse flags
+    let entry_bits = (bytes_bits + ms_bits) as usize;
+    let deviations = (0..packed.len() * 8 / entry_bits)
+        .map(|_| (read_bits(bytes_bits), read_bits(ms_bits)))
+        .collect();
 
-        let encoding = {
-            match id.as_str() {
-                "RVAD" | "RVA2" | "SYLT" => Encoding::UTF_8,
-                _ => {
-                    let b = consume_bytes(&mut buf, 1)?;
-                    Encoding::try_from(b[0])?
-                }
-            }
-        };
+    Some(Frame::Mllt {
+        frames_between_reference: u16::from_be_bytes([header[0], header[1]]),
+        bytes_between_reference: u32::from_be_bytes([0, header[2], header[3], header[4]]),
+        ms_between_reference: u32::from_be_bytes([0, header[5], header[6], header[7]]),
+        bits_for_bytes_deviation: bytes_bits,
+        bits_for_ms_deviation: ms_bits,
+        deviations,
+    })
+}
 
-        let size = if size > 0 { size - 1 } else { size }; // minus 1 byte for encoding;
+/// Decode the body of an ASPI frame: data start and length, the number of points, their
+/// size in bits (8 or 16) and the points themselves
+fn decode_aspi(buf: &[u8]) -> Option<Frame> {
+    let count = u16::from_be_bytes(buf.get(8..10)?.try_into().unwrap()) as usize;
+    let bits_per_point = *buf.get(10)?;
+    let points = buf.get(11..)?;
 
-        let frame = match id.as_str() {
-            "TXXX" => {
-                let description_bytes = consume_null_terminated_str_bytes(&mut buf, encoding)?;
-                let description = decode_str(&description_bytes, encoding)?;
-                let value = {
-                    let b = consume_bytes(&mut buf, size - description_bytes.len())?;
-                    decode_str(&b, encoding)?
-                };
-                Frame::Other {
-                    id,
-                    content: Content::Text(format!("{description}{value}")),
-                }
-            }
-            "USLT" => {
-                let language = {
-                    let b = consume_bytes(&mut buf, 3)?;
-                    decode_str(&b, Encoding::UTF_8)?
-                };
+    let points: Vec<u16> = match bits_per_point {
+        8 => points.get(..count)?.iter().map(|&x| x as u16).collect(),
+        16 => points
+            .get(..count * 2)?
+            .chunks_exact(2)
+            .map(|x| u16::from_be_bytes([x[0], x[1]]))
+            .collect(),
+        _ => return None,
+    };
 
-                let description_bytes = consume_null_terminated_str_bytes(&mut buf, encoding)?;
-                let description = decode_str(&description_bytes, encoding)?;
-
-                let value = {
-                    let b = consume_bytes(
-                        &mut buf,
-                        (size + 1)
-                            - (1 // encoding bytes
-                            + 3 // language bytes
-                            + description_bytes.len()),
-                    )?;
-                    decode_str(&b, encoding)?
-                };
+    Some(Frame::Aspi {
+        data_start: byte_int(&buf[0..4]),
+        data_length: byte_int(&buf[4..8]),
+        bits_per_point,
+        points,
+    })
+}
 
-                Frame::Uslt {
-                    text: value,
-                    language,
-                    description,
-                }
-            }
-            "COMM" => {
-                let _language = {
-                    let b = consume_bytes(&mut buf, 3)?;
-                    decode_str(&b, Encoding::UTF_8)?
-                };
+/// Read the null terminated description at the start of a frame. One that isn't terminated
+/// takes up the rest of the frame.
+fn consume_description(
+    buf: &mut io::Cursor<&[u8]>,
+    encoding: Encoding,
+    context: &mut FrameContext,
+) -> io::Result<Vec<u8>> {
+    let description = consume_null_terminated_str_bytes(buf, encoding)?;
+    let terminator: &[u8] = match encoding {
+        Encoding::UTF_8 | Encoding::ISO_8859_1 => &[0],
+        Encoding::UTF_16 | Encoding::UTF_16BE => &[0, 0],
+    };
+    if !description.ends_with(terminator) {
+        context.warn("description isn't terminated before the end of the frame");
+    }
+    Ok(description)
+}
 
-                let description_bytes = match encoding {
-                    Encoding::UTF_8 | Encoding::ISO_8859_1 => consume_c_str_bytes(&mut buf)?,
-                    Encoding::UTF_16 | Encoding::UTF_16BE => consume_utf16_str_bytes(&mut buf),
-                };
+/// Decode a description read by `consume_description`, without its terminator. That's part
+/// of the frame's layout rather than the text, so it goes whether or not values are trimmed.
+fn decode_description(
+    bytes: &[u8],
+    encoding: Encoding,
+    options: &ParseOptions,
+) -> Result<String, Box<dyn Error>> {
+    let mut description = decode_text(bytes, encoding, options)?;
+    if description.ends_with('\0') {
+        description.pop();
+    }
+    Ok(description)
+}
 
-                let _description = decode_str(&description_bytes, encoding)?;
-
-                let value = {
-                    let b = consume_bytes(
-                        &mut buf,
-                        (size + 1)
-                            - (1 // encoding bytes
-                            + 3 // language bytes
-                            + description_bytes.len()),
-                    )?;
-                    decode_str(&b, encoding)?
-                };
+/// Whatever is left of a frame body after the cursor
+fn rest_of<'b>(buf: &io::Cursor<&'b [u8]>) -> &'b [u8] {
+    let body = *buf.get_ref();
+    &body[(buf.position() as usize).min(body.len())..]
+}
 
-                Frame::Other {
-                    id,
-                    content: Content::Text(value),
-                }
-            }
-            "APIC" => {
-                let mime_type = consume_c_str(&mut buf)?;
-                let picture_type = consume_bytes(&mut buf, 1)?[0];
+/// What a frame decoder gets to know about a frame, besides its body
+pub struct FrameContext<'a> {
+    pub id: &'a str,
+    pub v4: bool,
+    pub options: &'a ParseOptions,
+    /// Size in the frame header, larger than the body when the frame is cut short
+    pub declared_size: usize,
+    warnings: &'a mut Vec<Warning>,
+}
 
-                let description_bytes = match encoding {
-                    Encoding::UTF_8 | Encoding::ISO_8859_1 => consume_c_str_bytes(&mut buf)?,
-                    Encoding::UTF_16 | Encoding::UTF_16BE => consume_utf16_str_bytes(&mut buf),
-                };
+impl FrameContext<'_> {
+    /// Note an oddity of the frame that was worked around
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.warnings.push(Warning::new(Some(self.id), message));
+    }
+}
 
-                let description = decode_str(&description_bytes, encoding)?;
+/// Decodes the body of a frame, once it's been resynchronised and is known not to be
+/// encrypted
+pub type FrameDecoder =
+    Arc<dyn Fn(&mut FrameContext, &[u8]) -> Result<Frame, Box<dyn Error>> + Send + Sync>;
 
-                let picture = consume_bytes(
-                    &mut buf,
-                    (size + 1)
-                        - (2 // 1 byte for encoding & picture type each
-                        + mime_type.len() + description_bytes.len()),
-                )?;
+type DecoderFn = fn(&mut FrameContext, &[u8]) -> Result<Frame, Box<dyn Error>>;
 
-                Frame::Apic {
-                    data: picture,
-                    description,
-                    picture_type: picture_type.try_into().unwrap(), // unsafe code
-                }
-            }
-            "RVAD" | "RVA2" => {
-                let b = consume_bytes(&mut buf, size + 1)?; // discard the additional byte for now
-                Frame::Other {
-                    id,
-                    content: Content::Binary(b),
-                }
+/// The decoders of the frames this crate knows of
+const BUILTIN_DECODERS: [(&str, DecoderFn); 14] = [
+    ("ETCO", decode_etco_frame),
+    ("RGAD", decode_rgad_frame),
+    ("ENCR", decode_encr_frame),
+    ("POSS", decode_poss_frame),
+    ("SEEK", decode_seek_frame),
+    ("ASPI", decode_aspi_frame),
+    ("MLLT", decode_mllt_frame),
+    ("TXXX", decode_txxx_frame),
+    ("USLT", decode_uslt_frame),
+    ("COMM", decode_comm_frame),
+    ("APIC", decode_apic_frame),
+    ("OWNE", decode_owne_frame),
+    ("COMR", decode_comr_frame),
+    ("SYLT", decode_sylt_frame),
+];
+
+/// Frame decoders by frame id. The default set covers every frame this crate knows of, and
+/// decoders registered for proprietary frames, or to replace a built-in one, take precedence.
+/// Frames without a decoder are decoded as text when they have an encoding byte, and kept as
+/// binary otherwise.
+#[derive(Clone)]
+pub struct FrameDecoders {
+    by_id: HashMap<String, FrameDecoder>,
+}
+
+impl FrameDecoders {
+    /// No decoders at all, leaving every frame to the fallback
+    pub fn empty() -> Self {
+        FrameDecoders {
+            by_id: HashMap::new(),
+        }
+    }
+
+    /// Decode frames of this id with `decoder`, in place of any decoder they had
+    pub fn register(
+        &mut self,
+        id: &str,
+        decoder: impl Fn(&mut FrameContext, &[u8]) -> Result<Frame, Box<dyn Error>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> &mut Self {
+        self.by_id.insert(id.into(), Arc::new(decoder));
+        self
+    }
+
+    pub fn get(&self, id: &str) -> Option<&FrameDecoder> {
+        self.by_id.get(id)
+    }
+}
+
+impl Default for FrameDecoders {
+    fn default() -> Self {
+        let mut decoders = FrameDecoders::empty();
+        for (id, decoder) in BUILTIN_DECODERS {
+            decoders.register(id, decoder);
+        }
+        decoders
+    }
+}
+
+impl Debug for FrameDecoders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ids: Vec<&String> = self.by_id.keys().collect();
+        ids.sort();
+        f.debug_tuple("FrameDecoders").field(&ids).finish()
+    }
+}
+
+/// The body of a frame as is, for frames that can't be made sense of
+fn binary_frame(context: &FrameContext, body: &[u8]) -> Frame {
+    Frame::Other {
+        id: context.id.into(),
+        content: Content::Binary(body.to_vec()),
+    }
+}
+
+/// The decoded frame, or the body as is with a warning saying why it couldn't be decoded
+fn or_binary(
+    context: &mut FrameContext,
+    body: &[u8],
+    frame: Option<Frame>,
+    warning: &str,
+) -> Result<Frame, Box<dyn Error>> {
+    Ok(frame.unwrap_or_else(|| {
+        context.warn(warning);
+        binary_frame(context, body)
+    }))
+}
+
+fn decode_etco_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    let frame = etco::decode_etco(body).map(|(timestamp_format, events)| Frame::Etco {
+        timestamp_format,
+        events,
+    });
+    or_binary(context, body, frame, "unknown timestamp format")
+}
+
+fn decode_rgad_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    let frame = replaygain::decode_rgad(body);
+    or_binary(context, body, frame, "replay gain adjustment too short")
+}
+
+fn decode_encr_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    let (owner, rest) = take_terminated(body, Encoding::ISO_8859_1);
+    match rest.split_first() {
+        Some((&method, data)) if body.len() >= 2 => Ok(Frame::Encr {
+            owner: decode_str(owner, Encoding::ISO_8859_1)?,
+            method,
+            data: data.to_vec(),
+        }),
+        _ => Ok(binary_frame(context, body)),
+    }
+}
+
+fn decode_poss_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    let frame = etco::decode_poss(body).map(|(timestamp_format, position)| Frame::Poss {
+        timestamp_format,
+        position,
+    });
+    or_binary(context, body, frame, "unknown timestamp format")
+}
+
+fn decode_seek_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    match body.get(0..4) {
+        Some(offset) => Ok(Frame::Seek {
+            offset: byte_int(offset),
+        }),
+        None => Ok(binary_frame(context, body)),
+    }
+}
+
+fn decode_aspi_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    or_binary(
+        context,
+        body,
+        decode_aspi(body),
+        "seek point index too short",
+    )
+}
+
+fn decode_mllt_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    or_binary(
+        context,
+        body,
+        decode_mllt(body),
+        "invalid location lookup table",
+    )
+}
+
+/// Split the encoding byte off a frame body, falling back to latin-1 for an invalid one
+fn split_encoding<'b>(context: &mut FrameContext, body: &'b [u8]) -> (Encoding, &'b [u8]) {
+    let Some((&byte, rest)) = body.split_first() else {
+        return (Encoding::ISO_8859_1, body);
+    };
+    let encoding = Encoding::try_from(byte).unwrap_or_else(|_| {
+        context.warn(format!(
+            "invalid encoding byte {:#04x} defaulted to latin-1",
+            byte
+        ));
+        Encoding::ISO_8859_1
+    });
+    (encoding, rest)
+}
+
+fn decode_txxx_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    let (encoding, rest) = split_encoding(context, body);
+    let mut buf = io::Cursor::new(rest);
+    let description_bytes = consume_description(&mut buf, encoding, context)?;
+    let description = decode_text(&description_bytes, encoding, context.options)?;
+    // only the value is trimmed, as the description keeps its terminator to tell them apart
+    let value = trim_value(
+        decode_text(rest_of(&buf), encoding, context.options)?,
+        context.options,
+    );
+
+    Ok(Frame::Other {
+        id: context.id.into(),
+        content: Content::Text(format!("{description}{value}")),
+    })
+}
+
+fn decode_uslt_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    let (encoding, rest) = split_encoding(context, body);
+    let mut buf = io::Cursor::new(rest);
+    let language = consume_language(&mut buf, context)?;
+
+    let description_bytes = consume_description(&mut buf, encoding, context)?;
+    let description = decode_description(&description_bytes, encoding, context.options)?;
+    let value = decode_text(rest_of(&buf), encoding, context.options)?;
+
+    Ok(Frame::Uslt {
+        text: trim_value(value, context.options),
+        language,
+        description: trim_value(description, context.options),
+    })
+}
+
+fn decode_comm_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    let (encoding, rest) = split_encoding(context, body);
+    let mut buf = io::Cursor::new(rest);
+    let language = consume_language(&mut buf, context)?;
+
+    let description_bytes = consume_description(&mut buf, encoding, context)?;
+    let description = decode_description(&description_bytes, encoding, context.options)?;
+    let value = decode_text(rest_of(&buf), encoding, context.options)?;
+
+    Ok(Frame::Comm {
+        text: trim_value(value, context.options),
+        language,
+        description: trim_value(description, context.options),
+    })
+}
+
+fn decode_apic_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    let (encoding, rest) = split_encoding(context, body);
+    let mut buf = io::Cursor::new(rest);
+    let mime_type = consume_c_str(&mut buf)?;
+    let picture_type = consume_bytes(&mut buf, 1)?[0];
+
+    let description_bytes = consume_description(&mut buf, encoding, context)?;
+    let description = decode_description(&description_bytes, encoding, context.options)?;
+
+    let header_size = 2 // 1 byte for encoding & picture type each
+        + mime_type.len() + description_bytes.len();
+    let data = rest_of(&buf);
+    // a linked picture's data is its URL, which is worth keeping
+    let picture = if context.options.skip_picture_data && mime_type.trim_end_matches('\0') != "-->"
+    {
+        Vec::new()
+    } else {
+        data.to_vec()
+    };
+
+    if context.declared_size > body.len() {
+        context.warn(format!(
+            "picture truncated, {} of {} bytes",
+            data.len(),
+            context.declared_size.saturating_sub(header_size)
+        ));
+    }
+
+    let picture_type = picture_type.try_into().unwrap_or_else(|_| {
+        context.warn(format!(
+            "unknown picture type {} coerced to Other",
+            picture_type
+        ));
+        PictureType::Other
+    });
+
+    Ok(Frame::Apic {
+        data: picture,
+        mime_type: mime_type.trim_end_matches('\0').into(),
+        description,
+        picture_type,
+    })
+}
+
+fn decode_owne_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    let (encoding, rest) = split_encoding(context, body);
+    decode_owne(rest, encoding, context.options)
+}
+
+fn decode_comr_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    let (encoding, rest) = split_encoding(context, body);
+    decode_comr(rest, encoding, context.options)
+}
+
+fn decode_sylt_frame(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    // kept whole, encoding byte included, for sylt::decode_sylt to decode
+    let (encoding, rest) = split_encoding(context, body);
+    let mut b = vec![encoding as u8];
+    b.extend(rest);
+    Ok(Frame::Other {
+        id: context.id.into(),
+        content: Content::Binary(b),
+    })
+}
+
+/// Decode a frame no decoder is registered for: text frames by their encoding byte, URL
+/// frames as latin-1 and anything else as binary
+fn decode_unregistered(context: &mut FrameContext, body: &[u8]) -> Result<Frame, Box<dyn Error>> {
+    let id = context.id.to_string();
+    if !has_encoding_byte(&id) {
+        // URL frames are always latin-1
+        return Ok(match id.starts_with('W') {
+            true => Frame::Other {
+                id,
+                content: Content::Text(decode_str(body, Encoding::ISO_8859_1)?),
+            },
+            false => binary_frame(context, body),
+        });
+    }
+
+    let (encoding, rest) = split_encoding(context, body);
+    let options = context.options;
+    let content = if context.v4 && id.starts_with('T') {
+        let mut values = split_terminated(rest, encoding)
+            .into_iter()
+            .map(|x| decode_text(x, encoding, options).map(|x| trim_value(x, options)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.len() == 1 {
+            Content::Text(values.remove(0))
+        } else {
+            Content::TextList(values)
+        }
+    } else {
+        Content::Text(trim_value(decode_text(rest, encoding, options)?, options))
+    };
+
+    Ok(Frame::Other { id, content })
+}
+
+/// Split the bytes the format flags add off the start of an encrypted frame. The flags moved
+/// about between versions, and so did the order of the bytes they add.
+fn decode_encrypted(id: String, format_flags: u8, body: &[u8], v4: bool) -> Option<Frame> {
+    let flag = |v3_bit: u8, v4_bit: u8| format_flags & if v4 { v4_bit } else { v3_bit } != 0;
+    let compressed = flag(0x80, 0x08);
+    let grouped = flag(0x20, 0x40);
+    let mut rest = body;
+    let mut take = |n: usize| {
+        let (taken, after) = (rest.get(..n)?, rest.get(n..)?);
+        rest = after;
+        Some(taken)
+    };
+
+    let (group, method, data_length) = if v4 {
+        let group = if grouped { Some(take(1)?[0]) } else { None };
+        let method = take(1)?[0];
+        let data_length = match format_flags & 0x01 != 0 {
+            true => Some(byte_int_unsynch(take(4)?)),
+            false => None,
+        };
+        (group, method, data_length)
+    } else {
+        let data_length = if compressed {
+            Some(byte_int(take(4)?))
+        } else {
+            None
+        };
+        let method = take(1)?[0];
+        let group = if grouped { Some(take(1)?[0]) } else { None };
+        (group, method, data_length)
+    };
+
+    Some(Frame::Encrypted {
+        id,
+        method,
+        group,
+        compressed,
+        data_length,
+        data: rest.to_vec(),
+    })
+}
+
+/// Decode the frame at the current position of the buffer
+fn decode_frame(
+    buf: &mut io::Cursor<Vec<u8>>,
+    v4: bool,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Frame, Box<dyn Error>> {
+    let start = buf.position() as usize;
+    let id = {
+        let b = consume_bytes(buf, 4)?;
+        String::from_utf8(b).unwrap_or("INVALID".into())
+    };
+
+    let size = {
+        let b = consume_bytes(buf, 4)?;
+        if v4 {
+            byte_int_unsynch(&b) as usize
+        } else {
+            byte_int(&b) as usize
+        }
+    };
+
+    let flags = consume_bytes(buf, 2)?;
+    let encrypted = flags[1] & if v4 { 0x04 } else { 0x40 } != 0;
+
+    let remaining = buf.get_ref().len() - buf.position() as usize;
+    let declared_size = size;
+    let size = if size > remaining {
+        // a picture gets a warning of its own once it's known how much of the image is missing
+        if id != "APIC" {
+            warnings.push(Warning::new(
+                Some(&id),
+                format!("frame size clamped from {} to {} bytes", size, remaining),
+            ));
+        }
+        remaining
+    } else {
+        size
+    };
+
+    // the body is borrowed from the tag, only the frames that keep their bytes copy them
+    let body_start = buf.position() as usize;
+    buf.set_position((body_start + size) as u64);
+    let body = &buf.get_ref()[body_start..body_start + size];
+
+    // v2.4 unsynchronises frames one by one, the size counting the bytes as stored. The
+    // frame is decoded again once they're restored, with the flag cleared.
+    if v4 && flags[1] & 0x02 != 0 {
+        let body = resynchronise(body);
+        let mut frame = buf.get_ref()[start..start + 4].to_vec();
+        frame.extend(encode_size(body.len() as u32, true));
+        frame.extend([flags[0], flags[1] & !0x02]);
+        frame.extend(body);
+        return decode_frame(&mut io::Cursor::new(frame), v4, options, warnings);
+    }
+
+    // without the key there's no telling what an encrypted frame holds, so it's kept as is
+    if encrypted {
+        return Ok(match decode_encrypted(id.clone(), flags[1], body, v4) {
+            Some(frame @ Frame::Encrypted { method, .. }) => {
+                warnings.push(Warning::new(
+                    Some(&id),
+                    format!("encrypted with method {}, kept as stored", method),
+                ));
+                frame
             }
-            "SYLT" => {
-                let b = consume_bytes(&mut buf, size + 1)?; // encoding not present in SYLT, so size is +1
+            _ => {
+                warnings.push(Warning::new(
+                    Some(&id),
+                    "encrypted, but too short for its format flags, kept as binary",
+                ));
                 Frame::Other {
                     id,
-                    content: Content::Binary(b),
+                    content: Content::Binary(body.to_vec()),
                 }
             }
-            _ => {
-                let text = read_text_from_buf(&mut buf, size, encoding)?;
+        });
+    }
 
-                Frame::Other {
-                    id,
-                    content: Content::Text(text),
+    let mut context = FrameContext {
+        id: &id,
+        v4,
+        options,
+        declared_size,
+        warnings,
+    };
+    match options.decoders.get(&id) {
+        Some(decoder) => decoder(&mut context, body),
+        None => decode_unregistered(&mut context, body),
+    }
+}
+
+/// Whether a buffer starts with what looks like a frame header: an id of upper case letters
+/// and digits, and a size that fits in the buffer
+pub(crate) fn looks_like_frame(buf: &[u8]) -> bool {
+    let Some(header) = buf.get(..10) else {
+        return false;
+    };
+    let id_valid = header[0].is_ascii_uppercase()
+        && header[1..4]
+            .iter()
+            .all(|x| x.is_ascii_uppercase() || x.is_ascii_digit());
+    // either size interpretation will do, the frame itself sorts out which one applies
+    let size = byte_int(&header[4..8]).min(byte_int_unsynch(&header[4..8])) as usize;
+    id_valid && size <= buf.len() - 10
+}
+
+/// Offset of the next frame header after `from`, if any
+fn next_frame(buf: &[u8], from: usize) -> Option<usize> {
+    (from + 1..buf.len()).find(|&x| looks_like_frame(&buf[x..]))
+}
+
+fn gap_warning(from: usize, next: Option<usize>, len: usize) -> Warning {
+    match next {
+        Some(next) => Warning::new(None, format!("gap of {} bytes skipped", next - from)),
+        None => Warning::new(None, format!("{} undecodable bytes at the end", len - from)),
+    }
+}
+
+/// Frames decoded from a buffer, with what was learned along the way
+#[derive(Debug, Default)]
+pub struct DecodedFrames {
+    pub frames: Vec<Frame>,
+    /// Oddities that were worked around
+    pub warnings: Vec<Warning>,
+    /// Offset of every frame within the buffer
+    pub offsets: Vec<usize>,
+    /// Declared and decoded size of every frame
+    pub sizes: Vec<FrameSize>,
+    /// Text encoding of every frame, for those with an encoding byte
+    pub encodings: Vec<Option<Encoding>>,
+    /// Time taken to decode every frame, empty unless profiling
+    pub timings: Vec<Duration>,
+}
+
+pub fn decode_frames(buf: Vec<u8>, v4: bool) -> Result<Vec<Frame>, Box<dyn Error>> {
+    decode_frames_with(buf, v4, &ParseOptions::default()).map(|x| x.frames)
+}
+
+/// Decode frames, keeping track of the oddities that were worked around and where every
+/// frame starts
+pub fn decode_frames_with(
+    buf: Vec<u8>,
+    v4: bool,
+    options: &ParseOptions,
+) -> Result<DecodedFrames, Box<dyn Error>> {
+    let mut buf = io::Cursor::new(buf);
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut warnings: Vec<Warning> = Vec::new();
+    let mut offsets: Vec<usize> = Vec::new();
+    let mut sizes: Vec<FrameSize> = Vec::new();
+    let mut encodings: Vec<Option<Encoding>> = Vec::new();
+    let mut timings: Vec<Duration> = Vec::new();
+
+    loop {
+        let offset = buf.position() as usize;
+
+        // stop at the end of the buffer or at padding, which also covers empty tags
+        let Some(id) = buf.get_ref().get(offset..offset + 4) else {
+            break;
+        };
+        match options.padding {
+            PaddingPolicy::Strict if id == [0; 4] => break,
+            PaddingPolicy::ScanToEnd if id == [0; 4] => match next_frame(buf.get_ref(), offset) {
+                Some(next) => {
+                    warnings.push(Warning::new(
+                        None,
+                        format!("frame found after {} bytes of padding", next - offset),
+                    ));
+                    buf.set_position(next as u64);
+                    continue;
+                }
+                None => break,
+            },
+            PaddingPolicy::Spec if id.contains(&0) => {
+                let junk = buf.get_ref()[offset..].iter().filter(|&&x| x != 0).count();
+                if junk > 0 {
+                    warnings.push(Warning::new(
+                        None,
+                        format!("{} non-zero bytes in the padding", junk),
+                    ));
                 }
+                break;
             }
-        };
+            _ => {}
+        }
 
-        frames.push(frame);
+        if options.recover && !looks_like_frame(&buf.get_ref()[offset..]) {
+            let next = next_frame(buf.get_ref(), offset);
+            warnings.push(gap_warning(offset, next, buf.get_ref().len()));
+            match next {
+                Some(next) => {
+                    buf.set_position(next as u64);
+                    continue;
+                }
+                None => break,
+            }
+        }
 
-        {
-            let cur_pos = buf.position();
-            let bytes = match consume_bytes(&mut buf, 4) {
-                Ok(bytes) => bytes,
-                Err(_) => break,
-            };
-            let num = byte_int(&bytes) as usize;
-            if num == 0 {
-                break;
+        let started = options.profile.then(Instant::now);
+        match decode_frame(&mut buf, v4, options, &mut warnings) {
+            Ok(frame) => {
+                timings.extend(started.map(|x| x.elapsed()));
+                let size_field = &buf.get_ref()[offset + 4..offset + 8];
+                let declared = if v4 {
+                    byte_int_unsynch(size_field)
+                } else {
+                    byte_int(size_field)
+                };
+                sizes.push(FrameSize {
+                    declared: declared as usize,
+                    consumed: buf.position() as usize - offset - 10,
+                });
+                // an encoding byte that makes no sense is read as latin-1, as the frame was
+                let encoding = buf.get_ref().get(offset + 10).filter(|_| {
+                    has_encoding_byte(frame.id()) && !matches!(frame, Frame::Encrypted { .. })
+                });
+                encodings
+                    .push(encoding.map(|&x| Encoding::try_from(x).unwrap_or(Encoding::ISO_8859_1)));
+                frames.push(frame);
+                offsets.push(offset);
+            }
+            Err(_) if options.recover => {
+                let next = next_frame(buf.get_ref(), offset);
+                warnings.push(gap_warning(offset, next, buf.get_ref().len()));
+                match next {
+                    Some(next) => buf.set_position(next as u64),
+                    None => break,
+                }
             }
-            buf.set_position(cur_pos);
+            Err(e) => return Err(e),
         }
     }
 
-    Ok(frames)
+    Ok(DecodedFrames {
+        frames,
+        warnings,
+        offsets,
+        sizes,
+        encodings,
+        timings,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use std::io::Read;
 
+    use super::{Content, Frame, Header, PictureType, Tag};
+
+    fn header(size: u32, footer_present: bool) -> Header {
+        Header {
+            version: 4,
+            revision: 0,
+            unsynchronisation: false,
+            extended: false,
+            experimental: false,
+            footer_present,
+            size,
+        }
+    }
+
+    #[test]
+    fn tag_total_size() {
+        let tag = Tag {
+            header: header(1000, false),
+            frames: Vec::new(),
+            warnings: Vec::new(),
+            offsets: Vec::new(),
+            sizes: Vec::new(),
+            encodings: Vec::new(),
+            timings: Vec::new(),
+            trailer: Vec::new(),
+        };
+        assert_eq!(tag.total_size(), 1010);
+
+        let tag = Tag {
+            header: header(1000, true),
+            frames: Vec::new(),
+            warnings: Vec::new(),
+            offsets: Vec::new(),
+            sizes: Vec::new(),
+            encodings: Vec::new(),
+            timings: Vec::new(),
+            trailer: Vec::new(),
+        };
+        assert_eq!(tag.total_size(), 1020);
+    }
+
     #[test]
     fn parse_utf16_bytes() {
         let mut buf = std::io::Cursor::new([
@@ -439,4 +1941,691 @@ mod tests {
         );
         assert!(buf.bytes().next().is_none());
     }
+
+    #[test]
+    fn decode_v4_utf16_multi_values() {
+        let mut body = vec![0x01]; // UTF-16 with BOM
+        for value in ["Rock", "Pop"] {
+            body.extend([0xFF, 0xFE]);
+            body.extend(value.encode_utf16().flat_map(|x| x.to_le_bytes()));
+            body.extend([0x00, 0x00]);
+        }
+        let mut buf = b"TCON".to_vec();
+        buf.extend([0, 0, 0, body.len() as u8, 0, 0]);
+        buf.extend(body);
+
+        let frames = super::decode_frames(buf, true).unwrap();
+
+        match &frames[0] {
+            Frame::Other {
+                content: Content::TextList(values),
+                ..
+            } => assert_eq!(values, &["Rock", "Pop"]),
+            x => panic!("expected multiple values, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn decode_v4_multi_values_with_multibyte_chars() {
+        use crate::testutil::raw_frame;
+
+        // UTF-8 never has a null byte inside a multibyte char, so every null separates
+        let mut buf = raw_frame("TPE1", "\x03Café Müller\0Ñandú 東京".as_bytes());
+        // in UTF-16BE, "aĀ" ends in the bytes 0x01 0x00, which with the terminator's first
+        // byte would read as another terminator if code units weren't kept whole
+        let mut body = vec![0x02];
+        body.extend("aĀ".encode_utf16().flat_map(|x| x.to_be_bytes()));
+        body.extend([0x00, 0x00]);
+        body.extend("b".encode_utf16().flat_map(|x| x.to_be_bytes()));
+        buf.extend(raw_frame("TCOM", &body));
+
+        let frames = super::decode_frames(buf, true).unwrap();
+
+        let values: Vec<&Content> = frames
+            .iter()
+            .map(|x| match x {
+                Frame::Other { content, .. } => content,
+                x => panic!("expected a text frame, got {:?}", x),
+            })
+            .collect();
+        assert_eq!(
+            values,
+            [
+                &Content::TextList(vec!["Café Müller".into(), "Ñandú 東京".into()]),
+                &Content::TextList(vec!["aĀ".into(), "b".into()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_linked_picture() {
+        let mut body = b"\x00-->\x00\x03\x00".to_vec();
+        body.extend(b"http://example.com/cover.jpg");
+        let mut buf = b"APIC".to_vec();
+        buf.extend((body.len() as u32).to_be_bytes());
+        buf.extend([0, 0]);
+        buf.extend(body);
+
+        let frames = super::decode_frames(buf, false).unwrap();
+
+        assert_eq!(
+            frames[0].linked_picture_url().as_deref(),
+            Some("http://example.com/cover.jpg")
+        );
+    }
+
+    #[test]
+    fn decode_owne() {
+        let body = b"\x00USD9.99\x0020240131Some Store";
+        let frames = super::decode_frames(crate::testutil::raw_frame("OWNE", body), false).unwrap();
+
+        assert_eq!(
+            frames[0],
+            Frame::Owne {
+                price: "USD9.99".into(),
+                date: "20240131".into(),
+                seller: "Some Store".into(),
+            }
+        );
+        assert_eq!(frames[0].to_string(), "USD9.99 on 20240131 from Some Store");
+    }
+
+    #[test]
+    fn tag_play_count() {
+        use crate::testutil::{raw_frame, tag};
+
+        let pcnt = raw_frame("PCNT", &[0x01, 0x00, 0x00, 0x00, 0x05]);
+        let frames = super::decode_frames(pcnt, false).unwrap();
+        assert_eq!(tag(frames).play_count(), Some(0x01_0000_0005));
+
+        let popm = raw_frame("POPM", b"me@example.com\x00\xFF\x00\x2A");
+        let frames = super::decode_frames(popm, false).unwrap();
+        assert_eq!(tag(frames).play_count(), Some(42));
+
+        assert_eq!(super::read_counter(&[0xFF; 9]), u64::MAX);
+    }
+
+    #[test]
+    fn decode_sylt_encoding_byte() {
+        use crate::testutil::raw_frame;
+
+        let mut body = vec![0x01];
+        body.extend(b"eng");
+        body.extend([0x02, 0x01]);
+        body.extend([0xFF, 0xFE, 0x00, 0x00]);
+        body.extend([0xFF, 0xFE, b'h', 0x00, b'i', 0x00, 0x00, 0x00]);
+        body.extend(1_000u32.to_be_bytes());
+        let mut buf = raw_frame("SYLT", &body);
+        buf.extend(raw_frame("WOAR", b"http://example.com"));
+
+        let decoded = super::decode_frames_with(buf, false, &Default::default()).unwrap();
+
+        assert!(decoded.warnings.is_empty());
+        let Frame::Other {
+            content: Content::Binary(b),
+            ..
+        } = &decoded.frames[0]
+        else {
+            panic!("SYLT should be kept as binary");
+        };
+        assert_eq!(b, &body);
+        let lyrics = crate::sylt::decode_sylt(b).unwrap();
+        assert_eq!(lyrics.language, "eng");
+        assert_eq!(lyrics.lines, [(1_000, "hi".to_string())]);
+
+        assert_eq!(decoded.frames[1].to_string(), "http://example.com");
+    }
+
+    #[test]
+    fn picture_type_table() {
+        use clap::ValueEnum;
+
+        for byte in 0..=20 {
+            let picture_type = PictureType::from_byte(byte).unwrap();
+            assert_eq!(picture_type.as_byte(), byte);
+            assert_eq!(
+                picture_type.name(),
+                picture_type.to_possible_value().unwrap().get_name()
+            );
+        }
+        assert_eq!(
+            PictureType::from_byte(3).unwrap().description(),
+            "Cover (front)"
+        );
+        assert_eq!(PictureType::BandLogo.description(), "Band/artist logotype");
+        assert_eq!(PictureType::from_byte(21), None);
+    }
+
+    #[test]
+    fn decode_truncated_picture() {
+        let mut body = b"\x00image/png\x00\x03\x00".to_vec();
+        body.extend([0xAA; 100]);
+        let mut buf = crate::testutil::raw_frame("APIC", &body);
+        buf.truncate(buf.len() - 60);
+
+        let decoded = super::decode_frames_with(buf, false, &Default::default()).unwrap();
+
+        assert!(matches!(&decoded.frames[0], Frame::Apic { data, .. } if data.len() == 40));
+        assert_eq!(
+            decoded.warnings,
+            [super::Warning::new(
+                Some("APIC"),
+                "picture truncated, 40 of 100 bytes"
+            )]
+        );
+        let tag = Tag {
+            sizes: decoded.sizes,
+            ..crate::testutil::tag(decoded.frames.clone())
+        };
+        assert_eq!(tag.truncated_picture(), decoded.frames.first());
+    }
+
+    #[test]
+    fn skip_picture_data() {
+        use crate::testutil::raw_text_frame;
+
+        let mut body = b"\x00image/jpeg\x00\x03Cover\x00".to_vec();
+        body.extend([0xAA; 4096]);
+        let mut buf = crate::testutil::raw_frame("APIC", &body);
+        buf.extend(raw_text_frame("TIT2", "Title"));
+        let options = super::ParseOptions {
+            skip_picture_data: true,
+            ..Default::default()
+        };
+
+        let decoded = super::decode_frames_with(buf, false, &options).unwrap();
+
+        assert_eq!(
+            decoded.frames[0],
+            Frame::Apic {
+                data: Vec::new(),
+                mime_type: "image/jpeg".into(),
+                picture_type: PictureType::CoverFront,
+                description: "Cover".into(),
+            }
+        );
+        assert_eq!(decoded.sizes[0].declared, body.len());
+        assert_eq!(decoded.frames[1].to_string(), "Title");
+    }
+
+    #[test]
+    fn recover_from_garbage() {
+        use crate::testutil::{raw_text_frame, text_frame};
+
+        let mut buf = raw_text_frame("TIT2", "Title");
+        buf.extend(b"\x01\x02garbage\xFF\xFE");
+        buf.extend(raw_text_frame("TPE1", "Artist"));
+        let options = super::ParseOptions {
+            recover: true,
+            ..Default::default()
+        };
+
+        let decoded = super::decode_frames_with(buf, false, &options).unwrap();
+
+        assert_eq!(
+            decoded.frames,
+            [text_frame("TIT2", "Title"), text_frame("TPE1", "Artist")]
+        );
+        assert_eq!(
+            decoded.warnings,
+            [super::Warning::new(None, "gap of 11 bytes skipped")]
+        );
+    }
+
+    #[test]
+    fn picture_data_uri() {
+        use base64::{prelude::BASE64_STANDARD, Engine};
+
+        let data = vec![0x89, b'P', b'N', b'G', 0x00, 0xFF];
+        let picture = Frame::Apic {
+            data: data.clone(),
+            mime_type: "image/png".into(),
+            picture_type: PictureType::CoverFront,
+            description: "".into(),
+        };
+
+        let uri = picture.data_uri().unwrap();
+        let encoded = uri.strip_prefix("data:image/png;base64,").unwrap();
+        assert_eq!(BASE64_STANDARD.decode(encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn decode_seek() {
+        let buf = crate::testutil::raw_frame("SEEK", &4096u32.to_be_bytes());
+
+        let frames = super::decode_frames(buf, true).unwrap();
+
+        assert_eq!(frames, [Frame::Seek { offset: 4096 }]);
+    }
+
+    #[test]
+    fn decode_poss_in_milliseconds() {
+        let buf = crate::testutil::raw_frame("POSS", b"\x02\x00\x01\xD4\xC0");
+
+        let frames = super::decode_frames(buf, true).unwrap();
+
+        assert_eq!(
+            frames,
+            [Frame::Poss {
+                timestamp_format: crate::sylt::TimestampFormat::Milliseconds,
+                position: 120_000,
+            }]
+        );
+        assert_eq!(frames[0].to_string(), "position 02:00");
+    }
+
+    #[test]
+    fn register_custom_decoder() {
+        use crate::testutil::{raw_frame, raw_text_frame};
+
+        // a made-up frame holding a 16 bit speed in percent
+        let mut buf = raw_frame("XSPT", &[0x00, 0x96]);
+        buf.extend(raw_frame("XSPT", &[0x01]));
+        buf.extend(raw_text_frame("TIT2", "Title"));
+        let mut options = super::ParseOptions::default();
+        options.decoders.register("XSPT", |context, body| {
+            let Ok(speed) = <[u8; 2]>::try_from(body) else {
+                context.warn("speed should be 2 bytes");
+                return Err("bad speed".into());
+            };
+            Ok(Frame::Other {
+                id: context.id.into(),
+                content: Content::Text(format!("{}%", u16::from_be_bytes(speed))),
+            })
+        });
+        options.recover = true;
+
+        let decoded = super::decode_frames_with(buf.clone(), false, &options).unwrap();
+
+        assert_eq!(decoded.frames.len(), 2);
+        assert_eq!(decoded.frames[0].to_string(), "150%");
+        assert_eq!(decoded.frames[1].to_string(), "Title");
+        assert!(decoded.warnings[0]
+            .to_string()
+            .contains("speed should be 2 bytes"));
+
+        // without the decoder, the frame is kept as binary
+        let frames = super::decode_frames(buf, false).unwrap();
+        assert_eq!(
+            frames[0],
+            Frame::Other {
+                id: "XSPT".into(),
+                content: Content::Binary(vec![0x00, 0x96]),
+            }
+        );
+    }
+
+    #[test]
+    fn decode_unsynchronised_v4_frame() {
+        // stored size 4 in a synchsafe field, flagged unsynchronised, with a 0xFF 0x00 pair
+        let mut buf = b"MCDI\x00\x00\x00\x04\x00\x02\xFF\x00\xE0\x01".to_vec();
+        buf.extend(crate::testutil::raw_frame("TIT2", b"\x03Title"));
+
+        let frames = super::decode_frames(buf, true).unwrap();
+
+        assert_eq!(
+            frames[0],
+            Frame::Other {
+                id: "MCDI".into(),
+                content: Content::Binary(vec![0xFF, 0xE0, 0x01]),
+            }
+        );
+        assert_eq!(frames[1].to_string(), "Title");
+        assert_eq!(
+            super::resynchronise(b"\xFF\x00\x00\xFF\xFF\x00"),
+            b"\xFF\x00\xFF\xFF"
+        );
+    }
+
+    #[test]
+    fn decode_encrypted_frame() {
+        let mut buf = crate::testutil::raw_frame("ENCR", b"mailto:me@example.com\0\x81key");
+        let mut encrypted = crate::testutil::raw_frame("TIT2", b"\x81\x03\xA7\xF1");
+        encrypted[9] = 0x40; // encryption flag
+        buf.extend(encrypted);
+
+        let decoded =
+            super::decode_frames_with(buf, false, &super::ParseOptions::default()).unwrap();
+
+        assert_eq!(
+            decoded.frames,
+            [
+                Frame::Encr {
+                    owner: "mailto:me@example.com".into(),
+                    method: 0x81,
+                    data: b"key".to_vec(),
+                },
+                Frame::Encrypted {
+                    id: "TIT2".into(),
+                    method: 0x81,
+                    group: None,
+                    compressed: false,
+                    data_length: None,
+                    data: b"\x03\xA7\xF1".to_vec(),
+                },
+            ]
+        );
+        assert_eq!(
+            decoded.warnings[0].to_string(),
+            "TIT2: encrypted with method 129, kept as stored"
+        );
+    }
+
+    #[test]
+    fn decode_mllt() {
+        let mut body = vec![0x00, 0x0A]; // 10 frames between references
+        body.extend([0x00, 0x10, 0x00]); // 4096 bytes
+        body.extend([0x00, 0x01, 0x04]); // 260 ms
+        body.extend([8, 4]);
+        body.extend([0x12, 0x3F, 0xFA]); // (0x12, 3), (0xFF, 0xA)
+        let buf = crate::testutil::raw_frame("MLLT", &body);
+
+        let frames = super::decode_frames(buf, false).unwrap();
+
+        assert_eq!(
+            frames,
+            [Frame::Mllt {
+                frames_between_reference: 10,
+                bytes_between_reference: 4096,
+                ms_between_reference: 260,
+                bits_for_bytes_deviation: 8,
+                bits_for_ms_deviation: 4,
+                deviations: vec![(0x12, 0x3), (0xFF, 0xA)],
+            }]
+        );
+        assert_eq!(crate::encoder::encode_frame_body(&frames[0], false), body);
+    }
+
+    #[test]
+    fn decode_overlong_txxx_description() {
+        use crate::testutil::{raw_frame, raw_text_frame};
+
+        let mut buf = raw_frame("TXXX", b"\0LONG DESCRIPTION");
+        buf.extend(raw_text_frame("TIT2", "Title"));
+
+        let decoded =
+            super::decode_frames_with(buf, false, &super::ParseOptions::default()).unwrap();
+
+        assert_eq!(
+            decoded.frames,
+            [
+                crate::testutil::text_frame("TXXX", "LONG DESCRIPTION"),
+                crate::testutil::text_frame("TIT2", "Title"),
+            ]
+        );
+        assert_eq!(decoded.warnings.len(), 1);
+    }
+
+    #[test]
+    fn decode_txxx_without_value() {
+        let buf = crate::testutil::raw_frame("TXXX", b"\0CATALOG\0");
+
+        let frames = super::decode_frames(buf, false).unwrap();
+
+        assert_eq!(frames, [crate::testutil::text_frame("TXXX", "CATALOG\0")]);
+        assert_eq!(crate::testutil::tag(frames).user_text("catalog"), Some(""));
+    }
+
+    #[test]
+    fn padding_policies() {
+        use super::PaddingPolicy;
+        use crate::testutil::{raw_text_frame, text_frame};
+
+        let decode = |buf: &[u8], padding| {
+            let options = super::ParseOptions {
+                padding,
+                ..Default::default()
+            };
+            super::decode_frames_with(buf.to_vec(), false, &options).unwrap()
+        };
+
+        // a frame hiding after some padding
+        let mut buf = raw_text_frame("TIT2", "Title");
+        buf.extend([0; 16]);
+        buf.extend(raw_text_frame("TPE1", "Artist"));
+        buf.extend([0; 16]);
+
+        let strict = decode(&buf, PaddingPolicy::Strict);
+        assert_eq!(strict.frames, [text_frame("TIT2", "Title")]);
+        assert!(strict.warnings.is_empty());
+
+        let scanned = decode(&buf, PaddingPolicy::ScanToEnd);
+        assert_eq!(
+            scanned.frames,
+            [text_frame("TIT2", "Title"), text_frame("TPE1", "Artist")]
+        );
+        assert_eq!(
+            scanned.warnings[0].to_string(),
+            "frame found after 16 bytes of padding"
+        );
+
+        let spec = decode(&buf, PaddingPolicy::Spec);
+        assert_eq!(spec.frames, [text_frame("TIT2", "Title")]);
+        assert_eq!(
+            spec.warnings[0].to_string(),
+            "11 non-zero bytes in the padding"
+        );
+
+        // padding that starts with a zero byte but isn't all zero
+        let mut buf = raw_text_frame("TIT2", "Title");
+        buf.extend(b"\0ABC");
+        buf.extend([0; 12]);
+        let spec = decode(&buf, PaddingPolicy::Spec);
+        assert_eq!(spec.frames, [text_frame("TIT2", "Title")]);
+        assert_eq!(spec.warnings.len(), 1);
+    }
+
+    #[test]
+    fn trim_title_with_trailing_nulls() {
+        use crate::testutil::text_frame;
+
+        let buf = crate::testutil::raw_frame("TIT2", b"\0Title  \0\0\0");
+
+        let trimmed = super::decode_frames(buf.clone(), false).unwrap();
+        let untrimmed = super::decode_frames_with(
+            buf,
+            false,
+            &super::ParseOptions {
+                trim: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(trimmed, [text_frame("TIT2", "Title")]);
+        assert_eq!(untrimmed.frames, [text_frame("TIT2", "Title  \0\0\0")]);
+    }
+
+    #[test]
+    fn untrimmed_descriptions_lose_their_terminator() {
+        use crate::testutil::raw_frame;
+
+        let mut buf = raw_frame("COMM", b"\0engNote \0Text ");
+        buf.extend(raw_frame("USLT", b"\0engVerse\0Lyrics"));
+        buf.extend(raw_frame("APIC", b"\0image/png\0\x03Cover\0\x89PNG"));
+        let options = super::ParseOptions {
+            trim: false,
+            ..Default::default()
+        };
+
+        let decoded = super::decode_frames_with(buf, false, &options).unwrap();
+
+        assert_eq!(
+            decoded.frames,
+            [
+                Frame::Comm {
+                    text: "Text ".into(),
+                    language: "eng".into(),
+                    description: "Note ".into(),
+                },
+                Frame::Uslt {
+                    text: "Lyrics".into(),
+                    language: "eng".into(),
+                    description: "Verse".into(),
+                },
+                Frame::Apic {
+                    data: b"\x89PNG".to_vec(),
+                    mime_type: "image/png".into(),
+                    picture_type: PictureType::CoverFront,
+                    description: "Cover".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_null_language() {
+        let buf = crate::testutil::raw_frame("USLT", b"\0\0\0\0\0la la la");
+
+        let decoded =
+            super::decode_frames_with(buf, false, &super::ParseOptions::default()).unwrap();
+
+        assert_eq!(
+            decoded.frames,
+            [Frame::Uslt {
+                text: "la la la".into(),
+                language: "XXX".into(),
+                description: "".into(),
+            }]
+        );
+        assert_eq!(
+            decoded.warnings[0].to_string(),
+            "USLT: language \"\\0\\0\\0\" isn't a language code, read as XXX"
+        );
+    }
+
+    #[test]
+    fn frame_kinds() {
+        use super::FrameKind;
+
+        let other = |id: &str| Frame::Other {
+            id: id.into(),
+            content: Content::Text("".into()),
+        };
+
+        assert_eq!(other("TIT2").kind(), FrameKind::Text);
+        assert_eq!(other("TXXX").kind(), FrameKind::Text);
+        assert_eq!(other("TSRC").kind(), FrameKind::Identifier);
+        assert_eq!(other("WOAR").kind(), FrameKind::Url);
+        assert_eq!(other("WPAY").kind(), FrameKind::Commercial);
+        assert_eq!(other("COMM").kind(), FrameKind::Comment);
+        assert_eq!(other("SYLT").kind(), FrameKind::Lyrics);
+        assert_eq!(other("UFID").kind(), FrameKind::Identifier);
+        assert_eq!(other("RVA2").kind(), FrameKind::Binary);
+        assert_eq!(
+            Frame::Uslt {
+                text: "".into(),
+                language: "eng".into(),
+                description: "".into(),
+            }
+            .kind(),
+            FrameKind::Lyrics
+        );
+    }
+
+    #[test]
+    fn decode_normalized() {
+        let text = "Beyonce\u{301}".as_bytes();
+        let options = super::ParseOptions {
+            normalize: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            super::decode_text(text, super::Encoding::UTF_8, &options).unwrap(),
+            "Beyonc\u{e9}"
+        );
+        assert_eq!(
+            super::decode_text(text, super::Encoding::UTF_8, &Default::default()).unwrap(),
+            "Beyonce\u{301}"
+        );
+    }
+
+    #[test]
+    fn decode_cp1252_quotes() {
+        let text = [0x93, b'h', b'i', 0x94];
+        let options = super::ParseOptions {
+            cp1252: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            super::decode_text(&text, super::Encoding::ISO_8859_1, &options).unwrap(),
+            "\u{201C}hi\u{201D}"
+        );
+        assert_eq!(
+            super::decode_text(&text, super::Encoding::ISO_8859_1, &Default::default()).unwrap(),
+            "\u{93}hi\u{94}"
+        );
+    }
+
+    #[test]
+    fn tag_pictures() {
+        let picture = |picture_type, data: &[u8]| Frame::Apic {
+            data: data.to_vec(),
+            mime_type: "image/png".into(),
+            picture_type,
+            description: String::new(),
+        };
+        let tag = crate::testutil::tag(vec![
+            picture(PictureType::CoverBack, b"back"),
+            crate::testutil::text_frame("TIT2", "Title"),
+            picture(PictureType::CoverFront, b"front"),
+            picture(PictureType::CoverFront, b"second front"),
+        ]);
+
+        assert_eq!(tag.pictures().len(), 3);
+        assert!(matches!(
+            tag.picture(PictureType::CoverFront),
+            Some(Frame::Apic { data, .. }) if data == b"front"
+        ));
+        assert!(tag.picture(PictureType::Artist).is_none());
+    }
+
+    #[test]
+    fn warn_on_invalid_encoding() {
+        let mut buf = b"TIT2".to_vec();
+        buf.extend([0, 0, 0, 3, 0, 0, 0x07, b'h', b'i']);
+
+        let decoded = super::decode_frames_with(buf, false, &Default::default()).unwrap();
+
+        assert_eq!(decoded.frames, [crate::testutil::text_frame("TIT2", "hi")]);
+        assert_eq!(
+            decoded.warnings,
+            [super::Warning::new(
+                Some("TIT2"),
+                "invalid encoding byte 0x07 defaulted to latin-1"
+            )]
+        );
+    }
+
+    #[test]
+    fn tag_bpm() {
+        let tag = |bpm| crate::testutil::tag(vec![crate::testutil::text_frame("TBPM", bpm)]);
+
+        assert_eq!(tag("174").bpm(), Some(174));
+        assert_eq!(tag("174\0").bpm(), Some(174));
+        assert_eq!(tag("fast").bpm(), None);
+    }
+
+    #[test]
+    fn parse_track_variants() {
+        assert_eq!(super::parse_track("3"), Some((3, None)));
+        assert_eq!(super::parse_track("3/12"), Some((3, Some(12))));
+        assert_eq!(super::parse_track("03/12"), Some((3, Some(12))));
+        assert_eq!(super::parse_track(" 3 / 12 \0"), Some((3, Some(12))));
+        assert_eq!(super::parse_track("3/"), Some((3, None)));
+        assert_eq!(super::parse_track("A1"), None);
+    }
+
+    #[test]
+    fn tag_track_total_from_txxx() {
+        let tag = crate::testutil::tag(vec![
+            crate::testutil::text_frame("TRCK", "04"),
+            crate::testutil::text_frame("TXXX", "TRACKTOTAL\x0012"),
+        ]);
+
+        assert_eq!(tag.track_number(), Some(4));
+        assert_eq!(tag.track_total(), Some(12));
+    }
 }
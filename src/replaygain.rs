@@ -0,0 +1,273 @@
+use serde::Serialize;
+
+use crate::parser::{Content, Frame, Tag};
+
+/// Volume adjustment for one channel from an RVA2 frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rva2Adjustment {
+    pub identification: String,
+    pub channel: u8,
+    /// Adjustment in dB
+    pub gain: f64,
+    /// Peak as a fraction of full scale
+    pub peak: Option<f64>,
+}
+
+/// One of the two gain adjustments of an RGAD frame
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RgadAdjustment {
+    /// 1 for radio (track) gain, 2 for audiophile (album) gain, 0 when not set
+    pub name: u8,
+    /// Who set the gain: 1 preset, 2 user, 3 automatically determined
+    pub originator: u8,
+    /// Adjustment in dB, in steps of 0.1
+    pub gain: f64,
+}
+
+/// Decode a 16 bit RGAD gain word: 3 bits of name code, 3 of originator code, a sign bit
+/// and the absolute gain in tenths of a dB
+pub(crate) fn decode_rgad_adjustment(word: u16) -> RgadAdjustment {
+    let gain = (word & 0x1FF) as f64 / 10.0;
+    RgadAdjustment {
+        name: (word >> 13) as u8,
+        originator: (word >> 10 & 0x7) as u8,
+        gain: if word & 0x200 != 0 { -gain } else { gain },
+    }
+}
+
+pub(crate) fn encode_rgad_adjustment(adjustment: &RgadAdjustment) -> u16 {
+    let tenths = (adjustment.gain.abs() * 10.0).round() as u16 & 0x1FF;
+    let sign = if adjustment.gain < 0.0 { 0x200 } else { 0 };
+    (adjustment.name as u16 & 0x7) << 13
+        | (adjustment.originator as u16 & 0x7) << 10
+        | sign
+        | tenths
+}
+
+/// Decode the body of the legacy RGAD frame: a 32 bit float peak amplitude followed by the
+/// radio and audiophile gain adjustments
+pub fn decode_rgad(buf: &[u8]) -> Option<Frame> {
+    let buf: &[u8; 8] = buf.get(..8)?.try_into().unwrap();
+    Some(Frame::Rgad {
+        peak: f32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]),
+        radio: decode_rgad_adjustment(u16::from_be_bytes([buf[4], buf[5]])),
+        audiophile: decode_rgad_adjustment(u16::from_be_bytes([buf[6], buf[7]])),
+    })
+}
+
+/// ReplayGain values gathered from TXXX, RVA2 and RGAD frames
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReplayGain {
+    /// Gains are in dB
+    pub track_gain: Option<f64>,
+    /// Peaks are a fraction of full scale
+    pub track_peak: Option<f64>,
+    pub album_gain: Option<f64>,
+    pub album_peak: Option<f64>,
+    pub rva2: Vec<Rva2Adjustment>,
+}
+
+impl ReplayGain {
+    pub fn is_empty(&self) -> bool {
+        self == &ReplayGain::default()
+    }
+}
+
+/// Parse a gain or peak value like "-6.50 dB", "+1.2dB" or "0.988"
+pub fn parse_value(value: &str) -> Option<f64> {
+    let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+    value.trim().trim_start_matches('+').parse().ok()
+}
+
+/// Decode the body of an RVA2 frame: a null terminated identification followed by an
+/// adjustment for each channel
+pub fn decode_rva2(buf: &[u8]) -> Vec<Rva2Adjustment> {
+    let mut adjustments = Vec::new();
+
+    let Some(nul) = buf.iter().position(|&x| x == 0x0) else {
+        return adjustments;
+    };
+    let identification: String = buf[..nul].iter().map(|&x| x as char).collect();
+    let mut rest = &buf[nul + 1..];
+
+    // channel type, 16 bit signed adjustment in 1/512 dB, bits of the peak, then the peak
+    while rest.len() >= 4 {
+        let channel = rest[0];
+        let gain = i16::from_be_bytes([rest[1], rest[2]]) as f64 / 512.0;
+        let peak_bits = rest[3] as usize;
+        let peak_bytes = peak_bits.div_ceil(8);
+        let Some(peak) = rest.get(4..4 + peak_bytes) else {
+            break;
+        };
+
+        let peak = match peak_bits {
+            0 => None,
+            // only the top 64 bits are needed for a sensible f64
+            _ => {
+                let value = peak
+                    .iter()
+                    .take(8)
+                    .fold(0u64, |acc, &x| (acc << 8) | x as u64);
+                let bits = peak_bits.min(64) as i32;
+                Some(value as f64 / 2f64.powi(bits - 1))
+            }
+        };
+
+        adjustments.push(Rva2Adjustment {
+            identification: identification.clone(),
+            channel,
+            gain,
+            peak,
+        });
+        rest = &rest[4 + peak_bytes..];
+    }
+
+    adjustments
+}
+
+/// Collect the ReplayGain information of a tag. TXXX values win over those of the legacy
+/// RGAD frame.
+pub fn from_tag(tag: &Tag) -> ReplayGain {
+    let mut replaygain = ReplayGain::default();
+    let mut rgad = ReplayGain::default();
+
+    for frame in &tag.frames {
+        match frame {
+            Frame::Other {
+                id,
+                content: Content::Text(text),
+            } if id == "TXXX" => {
+                let Some((description, value)) = text.split_once('\0') else {
+                    continue;
+                };
+                let field = match description.to_ascii_uppercase().as_str() {
+                    "REPLAYGAIN_TRACK_GAIN" => &mut replaygain.track_gain,
+                    "REPLAYGAIN_TRACK_PEAK" => &mut replaygain.track_peak,
+                    "REPLAYGAIN_ALBUM_GAIN" => &mut replaygain.album_gain,
+                    "REPLAYGAIN_ALBUM_PEAK" => &mut replaygain.album_peak,
+                    _ => continue,
+                };
+                *field = parse_value(value);
+            }
+            Frame::Other {
+                id,
+                content: Content::Binary(b),
+            } if id == "RVA2" => replaygain.rva2.extend(decode_rva2(b)),
+            Frame::Rgad {
+                peak,
+                radio,
+                audiophile,
+            } => {
+                if *peak > 0.0 {
+                    rgad.track_peak = Some(*peak as f64);
+                }
+                for adjustment in [radio, audiophile] {
+                    match adjustment.name {
+                        1 => rgad.track_gain = Some(adjustment.gain),
+                        2 => rgad.album_gain = Some(adjustment.gain),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    replaygain.track_gain = replaygain.track_gain.or(rgad.track_gain);
+    replaygain.track_peak = replaygain.track_peak.or(rgad.track_peak);
+    replaygain.album_gain = replaygain.album_gain.or(rgad.album_gain);
+    replaygain
+}
+
+/// Linear scale factor of a gain in dB
+pub fn db_to_linear(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parser::Frame,
+        testutil::{tag, text_frame},
+    };
+
+    #[test]
+    fn replaygain_from_txxx() {
+        let tag = tag(vec![
+            text_frame("TXXX", "REPLAYGAIN_TRACK_GAIN\0-6.50 dB"),
+            text_frame("TXXX", "replaygain_track_peak\x000.988235"),
+            text_frame("TXXX", "REPLAYGAIN_ALBUM_GAIN\0+1.25"),
+            text_frame("TXXX", "REPLAYGAIN_ALBUM_PEAK\x001.000000"),
+            text_frame("TXXX", "SOMETHING_ELSE\0-1 dB"),
+        ]);
+
+        let replaygain = super::from_tag(&tag);
+
+        assert_eq!(replaygain.track_gain, Some(-6.5));
+        assert_eq!(replaygain.track_peak, Some(0.988235));
+        assert_eq!(replaygain.album_gain, Some(1.25));
+        assert_eq!(replaygain.album_peak, Some(1.0));
+        assert!(replaygain.rva2.is_empty());
+    }
+
+    #[test]
+    fn replaygain_from_rgad() {
+        // peak 0.75, radio gain -6.3 dB set automatically, audiophile gain +1.5 dB by the user:
+        // name, originator, sign and tenths of a dB
+        let mut buf = 0.75f32.to_be_bytes().to_vec();
+        buf.extend((1u16 << 13 | 3 << 10 | 0x200 | 63).to_be_bytes());
+        buf.extend((2u16 << 13 | 2 << 10 | 15).to_be_bytes());
+
+        let frame = super::decode_rgad(&buf).unwrap();
+        let replaygain = super::from_tag(&tag(vec![
+            frame.clone(),
+            text_frame("TXXX", "REPLAYGAIN_ALBUM_GAIN\0+2.00 dB"),
+        ]));
+
+        assert_eq!(
+            frame,
+            Frame::Rgad {
+                peak: 0.75,
+                radio: super::RgadAdjustment {
+                    name: 1,
+                    originator: 3,
+                    gain: -6.3,
+                },
+                audiophile: super::RgadAdjustment {
+                    name: 2,
+                    originator: 2,
+                    gain: 1.5,
+                },
+            }
+        );
+        assert_eq!(crate::encoder::encode_frame_body(&frame, false), buf);
+        assert_eq!(replaygain.track_gain, Some(-6.3));
+        assert_eq!(replaygain.track_peak, Some(0.75));
+        assert_eq!(replaygain.album_gain, Some(2.0));
+    }
+
+    #[test]
+    fn decode_rva2_master_volume() {
+        // -3 dB on the master volume, with a 16 bit peak of 0.5
+        let mut buf = b"track\0".to_vec();
+        buf.extend([0x01]);
+        buf.extend((-3i16 * 512).to_be_bytes());
+        buf.extend([16, 0x40, 0x00]);
+
+        let adjustments = super::decode_rva2(&buf);
+
+        assert_eq!(
+            adjustments,
+            [super::Rva2Adjustment {
+                identification: "track".into(),
+                channel: 1,
+                gain: -3.0,
+                peak: Some(0.5),
+            }]
+        );
+    }
+}
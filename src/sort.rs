@@ -0,0 +1,83 @@
+use std::cmp::Ordering;
+
+use crate::parser::Tag;
+
+/// Tag field records can be sorted by
+#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq, Eq)]
+pub enum SortField {
+    Title,
+    Artist,
+    Album,
+    Year,
+    Track,
+    Bpm,
+}
+
+/// Value of a field to sort by. Numeric fields sort numerically, text case-insensitively.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Number(u64),
+    Text(String),
+}
+
+fn sort_value(tag: &Tag, field: SortField) -> Option<SortValue> {
+    let text = |x: Option<&str>| {
+        let x = x?.trim_end_matches('\0').trim();
+        (!x.is_empty()).then(|| SortValue::Text(x.to_lowercase()))
+    };
+
+    match field {
+        SortField::Title => text(tag.title()),
+        SortField::Artist => text(tag.artist()),
+        SortField::Album => text(tag.album()),
+        SortField::Year => tag
+            .year()
+            .and_then(|x| x.get(..4)?.parse().ok())
+            .map(SortValue::Number),
+        SortField::Track => tag.track_number().map(|x| SortValue::Number(x.into())),
+        SortField::Bpm => tag.bpm().map(|x| SortValue::Number(x.into())),
+    }
+}
+
+/// Compare two tags field by field. Missing values, and tags that couldn't be read at all,
+/// sort last.
+pub fn compare(a: Option<&Tag>, b: Option<&Tag>, fields: &[SortField]) -> Ordering {
+    for &field in fields {
+        let a = a.and_then(|x| sort_value(x, field));
+        let b = b.and_then(|x| sort_value(x, field));
+
+        let ordering = match (a, b) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SortField;
+    use crate::testutil::{tag, text_frame};
+
+    #[test]
+    fn sort_numeric_and_missing_last() {
+        let mut tags = [
+            tag(vec![text_frame("TPE1", "b"), text_frame("TRCK", "10")]),
+            tag(vec![text_frame("TRCK", "1")]),
+            tag(vec![text_frame("TPE1", "B"), text_frame("TRCK", "9/12")]),
+        ];
+
+        tags.sort_by(|a, b| {
+            super::compare(Some(a), Some(b), &[SortField::Artist, SortField::Track])
+        });
+
+        let tracks: Vec<u32> = tags.iter().filter_map(|x| x.track_number()).collect();
+        assert_eq!(tracks, [9, 10, 1]);
+    }
+}
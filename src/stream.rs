@@ -0,0 +1,117 @@
+use std::{error::Error, io::Read};
+
+use crate::parser::{
+    byte_int, byte_int_unsynch, consume_bytes, decode_frames_with, decode_header, Frame, Header,
+    ParseOptions, Warning,
+};
+
+/// Iterator decoding the frames of a tag one at a time, straight from a reader. Only the
+/// frames that are asked for are read, so stopping early skips reading the rest of the tag.
+pub struct Frames<R> {
+    reader: R,
+    header: Header,
+    options: ParseOptions,
+    /// Bytes of the tag left to read
+    remaining: usize,
+    warnings: Vec<Warning>,
+}
+
+/// Read the tag header from the start of a reader and iterate over the frames after it
+pub fn frames<R: Read>(mut reader: R, options: &ParseOptions) -> Result<Frames<R>, Box<dyn Error>> {
+    let header = consume_bytes(&mut reader, 10)?;
+    let header = decode_header(header.try_into().unwrap())?;
+    let mut remaining = header.size as usize;
+
+    if header.extended {
+        let size = byte_int(&consume_bytes(&mut reader, 4)?) as usize;
+        consume_bytes(&mut reader, size.saturating_sub(4))?;
+        remaining = remaining.saturating_sub(size);
+    }
+
+    Ok(Frames {
+        reader,
+        header,
+        options: options.clone(),
+        remaining,
+        warnings: Vec::new(),
+    })
+}
+
+impl<R> Frames<R> {
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Oddities worked around in the frames read so far
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+}
+
+impl<R: Read> Iterator for Frames<R> {
+    type Item = Result<Frame, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining < 10 {
+            return None;
+        }
+
+        let mut frame = match consume_bytes(&mut self.reader, 10) {
+            Ok(x) => x,
+            Err(e) => return Some(Err(e.into())),
+        };
+        self.remaining -= 10;
+
+        // padding
+        if frame[0] == 0 {
+            self.remaining = 0;
+            return None;
+        }
+
+        let size = match self.header.version {
+            4 => byte_int_unsynch(&frame[4..8]),
+            _ => byte_int(&frame[4..8]),
+        } as usize;
+        let size = size.min(self.remaining);
+        self.remaining -= size;
+
+        if let Err(e) = self
+            .reader
+            .by_ref()
+            .take(size as u64)
+            .read_to_end(&mut frame)
+        {
+            return Some(Err(e.into()));
+        }
+
+        let decoded = match decode_frames_with(frame, self.header.version == 4, &self.options) {
+            Ok(x) => x,
+            Err(e) => return Some(Err(e)),
+        };
+        self.warnings.extend(decoded.warnings);
+        decoded.frames.into_iter().next().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use crate::testutil::{raw_frame, raw_tag, raw_text_frame};
+
+    #[test]
+    fn read_first_frame_only() {
+        let picture = raw_frame("APIC", &[0; 4096]);
+        let tag = raw_tag(&[raw_text_frame("TIT2", "Title"), picture]);
+        let mut reader = Cursor::new(tag);
+
+        let mut frames = super::frames(&mut reader, &Default::default()).unwrap();
+        assert_eq!(frames.next().unwrap().unwrap().to_string(), "Title");
+        drop(frames);
+
+        // the picture was never read
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest.len(), 4096 + 10);
+    }
+}
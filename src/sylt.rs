@@ -0,0 +1,99 @@
+use serde::Serialize;
+
+use crate::parser::{decode_str, take_terminated, Encoding};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    MpegFrames,
+    Milliseconds,
+}
+
+/// Synchronised lyrics/text from a SYLT frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncedLyrics {
+    pub language: String,
+    pub timestamp_format: TimestampFormat,
+    pub content_type: u8,
+    pub description: String,
+    /// Timestamp and text of every line
+    pub lines: Vec<(u32, String)>,
+}
+
+/// Decode the body of a SYLT frame: encoding, language, timestamp format, content type and
+/// a description, followed by null terminated text and 32 bit timestamp pairs
+pub fn decode_sylt(body: &[u8]) -> Option<SyncedLyrics> {
+    let encoding = Encoding::try_from(*body.first()?).ok()?;
+    let language = decode_str(body.get(1..4)?, Encoding::ISO_8859_1).ok()?;
+    let timestamp_format = match body.get(4)? {
+        1 => TimestampFormat::MpegFrames,
+        2 => TimestampFormat::Milliseconds,
+        _ => return None,
+    };
+    let content_type = *body.get(5)?;
+
+    let (description, mut rest) = take_terminated(&body[6..], encoding);
+    let description = decode_str(description, encoding).ok()?;
+
+    let mut lines = Vec::new();
+    while !rest.is_empty() {
+        let (text, after) = take_terminated(rest, encoding);
+        let timestamp = u32::from_be_bytes(after.get(0..4)?.try_into().unwrap());
+        lines.push((timestamp, decode_str(text, encoding).ok()?));
+        rest = &after[4..];
+    }
+
+    Some(SyncedLyrics {
+        language,
+        timestamp_format,
+        content_type,
+        description,
+        lines,
+    })
+}
+
+impl SyncedLyrics {
+    /// Format as an LRC file, only possible with millisecond timestamps
+    pub fn to_lrc(&self) -> Option<String> {
+        if self.timestamp_format != TimestampFormat::Milliseconds {
+            return None;
+        }
+
+        let mut lrc = String::new();
+        for (ms, text) in &self.lines {
+            // SYLT marks new lines with a leading line feed, LRC puts every entry on one
+            let text = text.trim_start_matches(['\n', '\r']);
+            lrc.push_str(&format!(
+                "[{:02}:{:02}.{:02}]{}\n",
+                ms / 60_000,
+                ms / 1000 % 60,
+                ms % 1000 / 10,
+                text
+            ));
+        }
+        Some(lrc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn sylt_to_lrc() {
+        let mut body = vec![0x00];
+        body.extend(b"eng");
+        body.extend([0x02, 0x01]); // milliseconds, lyrics
+        body.extend(b"\0");
+        body.extend(b"First line\0");
+        body.extend(1_500u32.to_be_bytes());
+        body.extend(b"\nSecond line\0");
+        body.extend(72_250u32.to_be_bytes());
+
+        let lyrics = super::decode_sylt(&body).unwrap();
+
+        assert_eq!(lyrics.lines.len(), 2);
+        assert_eq!(
+            lyrics.to_lrc().unwrap(),
+            "[00:01.50]First line\n[01:12.25]Second line\n"
+        );
+    }
+}
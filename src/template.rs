@@ -0,0 +1,121 @@
+use crate::parser::Tag;
+
+/// Replace `{name}` placeholders in a template, resolving each name with `lookup`.
+/// Unknown or missing fields render as `missing`; `{{` and `}}` are literal braces.
+/// A width after a colon pads the value on the left, with zeros if it starts with one:
+/// `{track:02}` renders track 3 as "03".
+pub fn render(template: &str, lookup: impl Fn(&str) -> Option<String>, missing: &str) -> String {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let field: String = chars.by_ref().take_while(|&x| x != '}').collect();
+                let (name, spec) = split_spec(&field);
+                match lookup(name) {
+                    Some(value) => out.push_str(&pad(value, spec)),
+                    None => out.push_str(missing),
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+fn split_spec(field: &str) -> (&str, &str) {
+    let (name, spec) = field.split_once(':').unwrap_or((field, ""));
+    (name.trim(), spec.trim())
+}
+
+fn pad(value: String, spec: &str) -> String {
+    let Ok(width) = spec.parse::<usize>() else {
+        return value;
+    };
+    let fill = if spec.starts_with('0') { '0' } else { ' ' };
+    let count = width.saturating_sub(value.chars().count());
+    std::iter::repeat_n(fill, count)
+        .chain(value.chars())
+        .collect()
+}
+
+/// Names of the fields a template refers to, in order, without their format
+pub fn fields(template: &str) -> Vec<&str> {
+    template
+        .split("{{")
+        .flat_map(|x| x.split('{').skip(1))
+        .filter_map(|x| x.split_once('}'))
+        .map(|(field, _)| split_spec(field).0)
+        .collect()
+}
+
+/// Resolve a template field name against the common tag fields
+pub fn tag_field(tag: &Tag, name: &str) -> Option<String> {
+    let value = match name {
+        "title" => tag.title(),
+        "artist" => tag.artist(),
+        "album" => tag.album(),
+        "year" => tag.year(),
+        "track" => return tag.track_number().map(|x| x.to_string()),
+        _ => None,
+    };
+    value.map(|x| x.trim_end_matches('\0').to_string())
+}
+
+/// Make a value safe to use as (part of) a file name on all common platforms
+pub fn sanitize_filename(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    sanitized.trim().trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{tag, text_frame};
+
+    #[test]
+    fn render_sanitized_picture_name() {
+        let tag = tag(vec![
+            text_frame("TPE1", "AC/DC"),
+            text_frame("TALB", "Who Made Who?"),
+        ]);
+
+        let name = super::render(
+            "{artist} - {album} ({year}).{ext}",
+            |field| match field {
+                "ext" => Some("jpg".into()),
+                _ => super::tag_field(&tag, field).map(|x| super::sanitize_filename(&x)),
+            },
+            "Unknown",
+        );
+
+        assert_eq!(name, "AC_DC - Who Made Who_ (Unknown).jpg");
+    }
+
+    #[test]
+    fn render_padded_track() {
+        let tag = tag(vec![text_frame("TRCK", "3/12"), text_frame("TIT2", "Song")]);
+        let template = "{track:02} {title:6}|{{literal}}";
+
+        let name = super::render(template, |x| super::tag_field(&tag, x), "?");
+
+        assert_eq!(name, "03   Song|{literal}");
+        assert_eq!(super::fields(template), ["track", "title"]);
+    }
+}
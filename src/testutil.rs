@@ -0,0 +1,117 @@
+//! Builders shared by the unit tests
+
+use std::{fs, path::PathBuf};
+
+use crate::parser::{Content, Frame, Header, Tag};
+
+/// A fresh, empty directory for a test to put files in
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mp3info-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn header(version: u8, size: u32) -> Header {
+    Header {
+        version,
+        revision: 0,
+        unsynchronisation: false,
+        extended: false,
+        experimental: false,
+        footer_present: false,
+        size,
+    }
+}
+
+pub fn tag(frames: Vec<Frame>) -> Tag {
+    Tag {
+        header: header(3, 0),
+        frames,
+        warnings: Vec::new(),
+        offsets: Vec::new(),
+        sizes: Vec::new(),
+        encodings: Vec::new(),
+        timings: Vec::new(),
+        trailer: Vec::new(),
+    }
+}
+
+pub fn text_frame(id: &str, text: &str) -> Frame {
+    Frame::Other {
+        id: id.into(),
+        content: Content::Text(text.into()),
+    }
+}
+
+/// Raw bytes of a v2.3 frame
+pub fn raw_frame(id: &str, body: &[u8]) -> Vec<u8> {
+    let mut frame = id.as_bytes().to_vec();
+    frame.extend((body.len() as u32).to_be_bytes());
+    frame.extend([0, 0]);
+    frame.extend(body);
+    frame
+}
+
+/// Raw bytes of a latin-1 v2.3 text frame
+pub fn raw_text_frame(id: &str, text: &str) -> Vec<u8> {
+    let mut body = vec![0];
+    body.extend(text.bytes());
+    raw_frame(id, &body)
+}
+
+/// Raw bytes of a v2.3 tag holding the given raw frames
+pub fn raw_tag(frames: &[Vec<u8>]) -> Vec<u8> {
+    let body = frames.concat();
+    let size = body.len() as u32;
+    let mut tag = b"ID3\x03\x00\x00".to_vec();
+    tag.extend([
+        (size >> 21) as u8 & 0x7F,
+        (size >> 14) as u8 & 0x7F,
+        (size >> 7) as u8 & 0x7F,
+        size as u8 & 0x7F,
+    ]);
+    tag.extend(body);
+    tag
+}
+
+/// Bytes of an MP3 file with a v2.3 tag holding a single TIT2 frame
+pub fn tagged_file(title: &str) -> Vec<u8> {
+    raw_tag(&[raw_text_frame("TIT2", title)])
+}
+
+/// Bytes of an APEv2 tag with the given text items and a binary one, footer included
+pub fn ape_trailer(items: &[(&str, &str)]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (key, value) in items {
+        body.extend((value.len() as u32).to_le_bytes());
+        body.extend(0u32.to_le_bytes());
+        body.extend(key.bytes());
+        body.push(0);
+        body.extend(value.bytes());
+    }
+    // a binary item, which is skipped
+    body.extend(3u32.to_le_bytes());
+    body.extend(2u32.to_le_bytes());
+    body.extend(b"Cover\0abc");
+
+    let mut footer = b"APETAGEX".to_vec();
+    footer.extend(2000u32.to_le_bytes());
+    footer.extend((body.len() as u32 + 32).to_le_bytes());
+    footer.extend((items.len() as u32 + 1).to_le_bytes());
+    footer.extend([0; 12]);
+    body.extend(footer);
+    body
+}
+
+/// Bytes of a Lyrics3 v2 block with the given fields
+pub fn lyrics3_block(fields: &[(&str, &str)]) -> Vec<u8> {
+    let mut block = b"LYRICSBEGIN".to_vec();
+    for (id, value) in fields {
+        block.extend(format!("{id}{:05}{value}", value.len()).bytes());
+    }
+    let size = format!("{:06}", block.len());
+    block.extend(size.bytes());
+    block.extend(b"LYRICS200");
+    block
+}
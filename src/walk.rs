@@ -0,0 +1,437 @@
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt, fs, io,
+    ops::AddAssign,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    parser::{ParseOptions, Tag},
+    read_file_with, AppError,
+};
+
+/// Depth-first iterator over the MP3 files below a path, in a stable (sorted) order.
+/// A path to a file is yielded as is, regardless of its extension. Symlinks to directories
+/// below the root aren't followed unless asked to.
+pub struct Walk {
+    /// Paths still to visit, with how many directories down from the root they are
+    stack: Vec<(PathBuf, usize)>,
+    root: Option<PathBuf>,
+    max_depth: Option<usize>,
+    follow_symlinks: bool,
+    /// Canonical paths of the directories visited so far, so links can't lead into a loop
+    visited: HashSet<PathBuf>,
+    /// Files last modified before this are skipped
+    modified_since: Option<SystemTime>,
+}
+
+impl Walk {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Walk {
+            stack: Vec::new(),
+            root: Some(path.as_ref().into()),
+            max_depth: None,
+            follow_symlinks: false,
+            visited: HashSet::new(),
+            modified_since: None,
+        }
+    }
+
+    /// Only descend this many directories below the root, like find's -maxdepth. At depth 0
+    /// only the files directly inside the root are visited.
+    pub fn max_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Descend into symlinked directories too, each directory being visited only once
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Skip files that weren't modified within this long before now
+    pub fn modified_within(mut self, age: Option<Duration>) -> Self {
+        self.modified_since = age.and_then(|x| SystemTime::now().checked_sub(x));
+        self
+    }
+
+    /// Whether a file was modified recently enough. Files whose time can't be read are kept,
+    /// reading them will tell what's wrong.
+    fn recent_enough(&self, file: &Path) -> bool {
+        self.modified_since.is_none_or(|since| {
+            fs::metadata(file)
+                .and_then(|x| x.modified())
+                .map_or(true, |x| x >= since)
+        })
+    }
+
+    /// Whether a directory should be read: not a link unless links are followed, and not
+    /// one already visited through another link
+    fn should_visit(&mut self, dir: &Path, depth: usize) -> bool {
+        if !self.follow_symlinks {
+            return depth == 0 || !dir.is_symlink();
+        }
+        match dir.canonicalize() {
+            Ok(canonical) => self.visited.insert(canonical),
+            Err(_) => false,
+        }
+    }
+}
+
+pub fn is_mp3(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("mp3"))
+}
+
+/// Parse a duration such as "90s", "15m", "12h", "7d" or "2w"
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let error = || {
+        format!(
+            "invalid duration \"{}\", expected a number followed by s, m, h, d or w, e.g. 7d",
+            text
+        )
+    };
+
+    let unit = match text.chars().last().ok_or_else(error)? {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return Err(error()),
+    };
+    let count: u64 = text[..text.len() - 1].parse().map_err(|_| error())?;
+    let secs = count.checked_mul(unit).ok_or_else(error)?;
+    Ok(Duration::from_secs(secs))
+}
+
+/// Expand the arguments holding wildcards (`*`, `?`, `[...]`, and `**` for any number of
+/// directories) into the paths they match, for shells that leave them alone. Other arguments,
+/// and files whose names happen to hold those characters, are kept as they are. A pattern
+/// matching nothing is an error, as it is in most shells.
+pub fn expand_globs(args: &[impl AsRef<str>]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut paths = Vec::new();
+    for arg in args {
+        let arg = arg.as_ref();
+        if !arg.contains(['*', '?', '[']) || Path::new(arg).exists() {
+            paths.push(arg.into());
+            continue;
+        }
+
+        let before = paths.len();
+        for path in glob::glob(arg)? {
+            paths.push(path?);
+        }
+        if paths.len() == before {
+            return Err(AppError::new(&format!("No files match '{}'", arg)));
+        }
+    }
+    Ok(paths)
+}
+
+fn read_dir_sorted(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|x| x.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+impl Iterator for Walk {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            if !root.is_dir() {
+                return self.recent_enough(&root).then_some(Ok(root));
+            }
+            self.stack.push((root, 0));
+        }
+
+        while let Some((path, depth)) = self.stack.pop() {
+            if path.is_dir() {
+                if self.max_depth.is_some_and(|max| depth > max) || !self.should_visit(&path, depth)
+                {
+                    continue;
+                }
+                match read_dir_sorted(&path) {
+                    Ok(entries) => self
+                        .stack
+                        .extend(entries.into_iter().rev().map(|x| (x, depth + 1))),
+                    Err(e) => return Some(Err(e)),
+                }
+            } else if is_mp3(&path) && self.recent_enough(&path) {
+                return Some(Ok(path));
+            }
+        }
+
+        None
+    }
+}
+
+/// Totals of a recursive scan
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ScanSummary {
+    pub scanned: usize,
+    pub parsed: usize,
+    pub errors: usize,
+}
+
+impl fmt::Display for ScanSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        write!(
+            f,
+            "Scanned {} file{}, {} parsed, {} error{}",
+            self.scanned,
+            plural(self.scanned),
+            self.parsed,
+            self.errors,
+            plural(self.errors)
+        )
+    }
+}
+
+impl AddAssign for ScanSummary {
+    fn add_assign(&mut self, rhs: Self) {
+        self.scanned += rhs.scanned;
+        self.parsed += rhs.parsed;
+        self.errors += rhs.errors;
+    }
+}
+
+/// Read the tag of every MP3 file below `path`, handing each result to `f`, stopping
+/// after `limit` files if given. Files that fail to parse and directories that can't be
+/// listed are counted as errors but don't stop the scan.
+pub fn scan(
+    walk: Walk,
+    options: &ParseOptions,
+    limit: Option<usize>,
+    mut f: impl FnMut(&Path, Result<Tag, Box<dyn Error>>) -> io::Result<()>,
+) -> io::Result<ScanSummary> {
+    let mut summary = ScanSummary::default();
+
+    for entry in walk.take(limit.unwrap_or(usize::MAX)) {
+        // a directory that can't be listed is an error like a file that can't be read
+        let Ok(entry) = entry else {
+            summary.errors += 1;
+            continue;
+        };
+        let tag = read_file_with(&entry, options);
+
+        summary.scanned += 1;
+        match tag {
+            Ok(_) => summary.parsed += 1,
+            Err(_) => summary.errors += 1,
+        }
+
+        f(&entry, tag)?;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use crate::testutil::{tagged_file, temp_dir};
+
+    #[test]
+    fn walk_recently_modified_files() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = temp_dir("modified-within");
+        fs::write(dir.join("new.mp3"), b"").unwrap();
+        fs::write(dir.join("old.mp3"), b"").unwrap();
+        let month_ago = SystemTime::now() - Duration::from_secs(30 * 24 * 60 * 60);
+        fs::File::options()
+            .write(true)
+            .open(dir.join("old.mp3"))
+            .unwrap()
+            .set_modified(month_ago)
+            .unwrap();
+
+        let week = super::parse_duration("7d").unwrap();
+        let files: Vec<_> = super::Walk::new(&dir)
+            .modified_within(Some(week))
+            .map(|x| x.unwrap())
+            .collect();
+
+        assert_eq!(files, [dir.join("new.mp3")]);
+        assert_eq!(super::Walk::new(&dir).count(), 2);
+        assert_eq!(
+            super::parse_duration("12h"),
+            Ok(Duration::from_secs(43_200))
+        );
+        assert!(super::parse_duration("7 days").is_err());
+        assert!(super::parse_duration("99999999999999999w").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_follows_symlinks_when_asked() {
+        use std::os::unix::fs::symlink;
+
+        let dir = temp_dir("symlinks");
+        let library = dir.join("library");
+        let elsewhere = dir.join("elsewhere");
+        fs::create_dir_all(&library).unwrap();
+        fs::create_dir_all(&elsewhere).unwrap();
+        fs::write(library.join("a.mp3"), b"").unwrap();
+        fs::write(elsewhere.join("b.mp3"), b"").unwrap();
+        symlink(&elsewhere, library.join("linked")).unwrap();
+        // a link back up the tree, which would loop forever if followed blindly
+        symlink(&library, elsewhere.join("loop")).unwrap();
+
+        let walk = |follow| -> Vec<_> {
+            super::Walk::new(&library)
+                .follow_symlinks(follow)
+                .map(|x| x.unwrap().strip_prefix(&library).unwrap().to_path_buf())
+                .collect()
+        };
+
+        assert_eq!(walk(false), [Path::new("a.mp3")]);
+        assert_eq!(
+            walk(true),
+            [Path::new("a.mp3"), &Path::new("linked").join("b.mp3")]
+        );
+    }
+
+    #[test]
+    fn expand_glob_patterns() {
+        let dir = temp_dir("glob");
+        fs::create_dir_all(dir.join("a").join("b")).unwrap();
+        for path in [
+            "one.mp3",
+            "a/two.mp3",
+            "a/b/three.mp3",
+            "a/notes.txt",
+            "live [2001].MP3",
+        ] {
+            fs::write(dir.join(path), b"").unwrap();
+        }
+        let root = dir.to_str().unwrap();
+
+        let paths = super::expand_globs(&[
+            format!("{}/**/*.mp3", root),
+            format!("{}/live [2001].MP3", root),
+            format!("{}/missing.mp3", root),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            paths,
+            [
+                dir.join("a/b/three.mp3"),
+                dir.join("a/two.mp3"),
+                dir.join("one.mp3"),
+                dir.join("live [2001].MP3"),
+                dir.join("missing.mp3"),
+            ]
+        );
+        assert!(super::expand_globs(&[format!("{}/*.flac", root)]).is_err());
+    }
+
+    #[test]
+    fn scan_summary_counts_errors() {
+        let dir = temp_dir("scan-summary");
+        fs::write(dir.join("a.mp3"), tagged_file("A")).unwrap();
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub").join("b.mp3"), tagged_file("B")).unwrap();
+        fs::write(dir.join("sub").join("broken.mp3"), b"not a tag").unwrap();
+        fs::write(dir.join("notes.txt"), b"ignored").unwrap();
+
+        let mut seen = Vec::new();
+        let summary = super::scan(
+            super::Walk::new(&dir),
+            &Default::default(),
+            None,
+            |path, _| {
+                seen.push(path.to_path_buf());
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            summary,
+            super::ScanSummary {
+                scanned: 3,
+                parsed: 2,
+                errors: 1,
+            }
+        );
+        assert_eq!(summary.to_string(), "Scanned 3 files, 2 parsed, 1 error");
+        assert_eq!(seen[0], dir.join("a.mp3"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scan_counts_unreadable_entries() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("scan-unreadable");
+        fs::write(dir.join("a.mp3"), tagged_file("A")).unwrap();
+        fs::write(dir.join("locked.mp3"), tagged_file("Locked")).unwrap();
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub").join("b.mp3"), tagged_file("B")).unwrap();
+        let lock = |path: &Path, mode| {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap()
+        };
+        lock(&dir.join("locked.mp3"), 0o000);
+        lock(&dir.join("sub"), 0o000);
+
+        // permissions don't stop root
+        let summary = if fs::File::open(dir.join("locked.mp3")).is_err() {
+            Some(super::scan(
+                super::Walk::new(&dir),
+                &Default::default(),
+                None,
+                |_, _| Ok(()),
+            ))
+        } else {
+            None
+        };
+        lock(&dir.join("locked.mp3"), 0o644);
+        lock(&dir.join("sub"), 0o755);
+
+        if let Some(summary) = summary {
+            assert_eq!(
+                summary.unwrap(),
+                super::ScanSummary {
+                    scanned: 2,
+                    parsed: 1,
+                    errors: 2,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn walk_max_depth() {
+        let dir = temp_dir("max-depth");
+        fs::create_dir_all(dir.join("artist").join("album").join("junk")).unwrap();
+        for path in [
+            "top.mp3",
+            "artist/a.mp3",
+            "artist/album/b.mp3",
+            "artist/album/junk/c.mp3",
+        ] {
+            fs::write(dir.join(path), b"").unwrap();
+        }
+        let names = |depth| {
+            super::Walk::new(&dir)
+                .max_depth(depth)
+                .map(|x| x.unwrap().file_name().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(Some(0)), ["top.mp3"]);
+        assert_eq!(names(Some(2)), ["a.mp3", "b.mp3", "top.mp3"]);
+        assert_eq!(names(None).len(), 4);
+    }
+}